@@ -0,0 +1,119 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use rand::{thread_rng, Rng};
+
+mod helpers;
+
+struct TilesTexture(Handle<Texture>);
+
+fn startup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut map_query: MapQuery,
+) {
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+
+    let texture_handle = asset_server.load("tiles.png");
+    let material_handle = materials.add(ColorMaterial::texture(texture_handle.clone()));
+    commands.insert_resource(TilesTexture(texture_handle));
+
+    // Create map entity and component:
+    let map_entity = commands.spawn().id();
+    let mut map = Map::new(0u16, map_entity);
+
+    let settings = LayerSettings::new(
+        MapSize(4, 4),
+        ChunkSize(8, 8),
+        TileSize(16.0, 16.0),
+        TextureSize(96.0, 16.0),
+    );
+
+    let (mut layer_builder, layer_entity) =
+        LayerBuilder::<TileBundle>::new(&mut commands, settings, 0u16, 0u16, None);
+    map.add_layer(&mut commands, 0u16, layer_entity);
+
+    let mut random = thread_rng();
+    layer_builder.for_each_tiles_mut(|_, tile_bundle| {
+        let mut tile = Tile {
+            texture_index: random.gen_range(0..6),
+            ..Default::default()
+        };
+        tile.set_rotation(random.gen_range(0..4));
+        *tile_bundle = Some(tile.into());
+    });
+    map_query.build_layer(&mut commands, layer_builder, material_handle);
+
+    // Spawn Map
+    // Required in order to use map_query to retrieve layers/tiles.
+    commands
+        .entity(map_entity)
+        .insert(map)
+        .insert(Transform::from_xyz(-256.0, -256.0, 0.0))
+        .insert(GlobalTransform::default());
+}
+
+// Replaces the layer with a single sprite of its baked tiles when space is pressed.
+#[allow(clippy::too_many_arguments)]
+fn bake(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    tiles_texture: Res<TilesTexture>,
+    mut textures: ResMut<Assets<Texture>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut map_query: MapQuery,
+    tile_query: Query<&Tile>,
+    transform_query: Query<&GlobalTransform>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Space) {
+        return;
+    }
+    let (layer_entity, layer) = match map_query.get_layer(0u16, 0u16) {
+        Some(layer) => layer,
+        None => return,
+    };
+    let texture = match textures.get(&tiles_texture.0) {
+        Some(texture) => texture,
+        None => return,
+    };
+
+    let baked = map_query
+        .bake_layer(&tile_query, texture, 0u16, 0u16)
+        .unwrap();
+    let settings = layer.settings;
+    let layer_size = layer.get_layer_size_in_tiles();
+    let size = Vec2::new(layer_size.0 as f32, layer_size.1 as f32) * settings.grid_size;
+    let center = size / 2.0 - settings.get_anchor_offset();
+    let layer_translation = transform_query
+        .get(layer_entity)
+        .map_or(Vec3::ZERO, |transform| transform.translation);
+
+    commands.spawn_bundle(SpriteBundle {
+        material: materials.add(textures.add(baked).into()),
+        sprite: Sprite::new(size),
+        transform: Transform::from_translation(layer_translation + center.extend(0.0)),
+        ..Default::default()
+    });
+    map_query.despawn_layer(&mut commands, 0u16, 0u16);
+}
+
+fn main() {
+    env_logger::Builder::from_default_env()
+        .filter_level(log::LevelFilter::Info)
+        .init();
+
+    App::build()
+        .insert_resource(WindowDescriptor {
+            width: 1270.0,
+            height: 720.0,
+            title: String::from("Bake Example - Press space to bake the layer into a sprite"),
+            ..Default::default()
+        })
+        .add_plugins(DefaultPlugins)
+        .add_plugin(TilemapPlugin)
+        .add_startup_system(startup.system())
+        .add_system(helpers::camera::movement.system())
+        .add_system(helpers::texture::set_texture_filters_to_nearest.system())
+        .add_system(bake.system())
+        .run();
+}
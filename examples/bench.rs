@@ -17,16 +17,20 @@ fn startup(
     let texture_handle = asset_server.load("tiles.png");
     let material_handle = materials.add(ColorMaterial::texture(texture_handle));
 
+    let mut layer_settings = LayerSettings::new(
+        MapSize(10, 10),
+        ChunkSize(128, 128),
+        TileSize(16.0, 16.0),
+        TextureSize(96.0, 16.0),
+    );
+    // With `--greedy` the identical tiles of each chunk are merged into a single quad.
+    layer_settings.greedy_meshing = std::env::args().any(|arg| arg == "--greedy");
+
     // Create map with (10 * 128) ^ 2 tiles or 1,638,400 tiles.
     // Be patient when running this example as meshing does not run on multiple CPU's yet..
     let layer_entity = LayerBuilder::<TileBundle>::new_batch(
         &mut commands,
-        LayerSettings::new(
-            MapSize(10, 10),
-            ChunkSize(128, 128),
-            TileSize(16.0, 16.0),
-            TextureSize(96.0, 16.0),
-        ),
+        layer_settings,
         &mut meshes,
         material_handle,
         0u16,
@@ -35,6 +39,11 @@ fn startup(
         |_| Some(TileBundle::default()),
     );
 
+    // With `--batch` the chunks are drawn as a single mesh instead of a draw call per chunk.
+    if std::env::args().any(|arg| arg == "--batch") {
+        commands.entity(layer_entity).insert(TilemapBatching);
+    }
+
     // Create map entity and component:
     let map_entity = commands.spawn().id();
     let mut map = Map::new(0u16, map_entity);
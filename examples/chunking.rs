@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+mod helpers;
+
+const CHUNK_SIZE: ChunkSize = ChunkSize(16, 16);
+const MAP_SIZE: MapSize = MapSize(1024, 1024);
+
+fn startup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+
+    let texture_handle = asset_server.load("tiles.png");
+    let material_handle = materials.add(ColorMaterial::texture(texture_handle));
+
+    // Create map entity and component:
+    let map_entity = commands.spawn().id();
+    let mut map = Map::new(0u16, map_entity);
+
+    let mut settings = LayerSettings::new(
+        MAP_SIZE,
+        CHUNK_SIZE,
+        TileSize(16.0, 16.0),
+        TextureSize(96.0, 16.0),
+    );
+    settings.set_map_id(0u16);
+    settings.set_layer_id(0u16);
+    settings.anchor = TilemapAnchor::Center;
+
+    // The layer starts without chunks, they are spawned from the generator as the camera moves
+    // and despawned once they are more than 2 chunks away.
+    let layer_entity = commands
+        .spawn_bundle(LayerBundle {
+            layer: Layer::new(settings),
+            ..Default::default()
+        })
+        .insert(TilemapStreaming::new(2, material_handle, generate_chunk))
+        .id();
+    map.add_layer(&mut commands, 0u16, layer_entity);
+
+    // Spawn Map
+    // Required in order to use map_query to retrieve layers/tiles.
+    commands
+        .entity(map_entity)
+        .insert(map)
+        .insert(Transform::default())
+        .insert(GlobalTransform::default());
+}
+
+// The generator for the tiles of a streamed chunk.
+fn generate_chunk(chunk_pos: ChunkPos) -> impl Iterator<Item = (TilePos, Tile)> {
+    (0..CHUNK_SIZE.0).flat_map(move |x| {
+        (0..CHUNK_SIZE.1).map(move |y| {
+            let tile_pos = TilePos(
+                chunk_pos.0 * CHUNK_SIZE.0 + x,
+                chunk_pos.1 * CHUNK_SIZE.1 + y,
+            );
+            // A cheap hash, so the same chunk looks the same when it's streamed in again.
+            let hash = (tile_pos.0.wrapping_mul(73_856_093) ^ tile_pos.1.wrapping_mul(19_349_663))
+                .wrapping_mul(83_492_791);
+            let tile = Tile {
+                texture_index: (hash >> 16) as u16 % 6,
+                ..Default::default()
+            };
+            (tile_pos, tile)
+        })
+    })
+}
+
+fn main() {
+    env_logger::Builder::from_default_env()
+        .filter_level(log::LevelFilter::Info)
+        .init();
+
+    App::build()
+        .insert_resource(WindowDescriptor {
+            width: 1270.0,
+            height: 720.0,
+            title: String::from("Chunking Example - Move with WASD to stream in chunks"),
+            ..Default::default()
+        })
+        .add_plugins(DefaultPlugins)
+        .add_plugin(TilemapPlugin)
+        .add_startup_system(startup.system())
+        .add_system(helpers::camera::movement.system())
+        .add_system(helpers::texture::set_texture_filters_to_nearest.system())
+        .run();
+}
@@ -0,0 +1,53 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+mod helpers;
+
+fn startup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+
+    let texture_handle = asset_server.load("tiles.png");
+    let material_handle = materials.add(ColorMaterial::texture(texture_handle));
+
+    let map_entity = commands.spawn().id();
+
+    // The CSV only holds texture indices, the sizes of the tiles come from the config.
+    commands.entity(map_entity).insert_bundle(CsvMapBundle {
+        csv_map: asset_server.load("map.csv"),
+        map: Map::new(0u16, map_entity),
+        transform: Transform::from_xyz(-128.0, -80.0, 0.0),
+        csv_map_config: CsvMapConfig {
+            material: material_handle,
+            chunk_size: ChunkSize(8, 8),
+            tile_size: TileSize(16.0, 16.0),
+            texture_size: TextureSize(96.0, 16.0),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+}
+
+fn main() {
+    env_logger::Builder::from_default_env()
+        .filter_level(log::LevelFilter::Info)
+        .init();
+
+    App::build()
+        .insert_resource(WindowDescriptor {
+            width: 1270.0,
+            height: 720.0,
+            title: String::from("CSV Map Example"),
+            ..Default::default()
+        })
+        .add_plugins(DefaultPlugins)
+        .add_plugin(TilemapPlugin)
+        .add_plugin(CsvMapPlugin)
+        .add_startup_system(startup.system())
+        .add_system(helpers::camera::movement.system())
+        .add_system(helpers::texture::set_texture_filters_to_nearest.system())
+        .run();
+}
@@ -0,0 +1,141 @@
+use bevy::{prelude::*, reflect::TypeUuid, render::renderer::RenderResources};
+use bevy_ecs_tilemap::prelude::*;
+
+mod helpers;
+
+const FRAGMENT_SHADER: &str = r#"#version 450
+
+layout(location = 0) in vec2 v_Uv;
+layout(location = 1) in vec4 v_color;
+layout(location = 2) in float v_TextureLayer;
+
+layout(location = 0) out vec4 o_Target;
+
+layout(set = 1, binding = 0) uniform ColorMaterial_color {
+    vec4 Color;
+};
+layout(set = 1, binding = 1) uniform texture2D ColorMaterial_texture;
+layout(set = 1, binding = 2) uniform sampler ColorMaterial_texture_sampler;
+
+layout(set = 3, binding = 0) uniform TimeTint_color {
+    vec4 tint;
+};
+layout(set = 3, binding = 1) uniform TimeTint_time {
+    float time;
+};
+
+void main() {
+    vec4 color = Color * v_color * texture(
+        sampler2D(ColorMaterial_texture, ColorMaterial_texture_sampler),
+        v_Uv);
+
+    if (color.a < 0.001) {
+        discard;
+    }
+
+    float amount = 0.5 + 0.5 * sin(time * 2.0);
+    o_Target = vec4(mix(color.rgb, color.rgb * tint.rgb, amount), color.a);
+}
+"#;
+
+#[derive(RenderResources, TypeUuid)]
+#[uuid = "5a5dd9b2-6f0c-4a8e-9a2b-3b1f07a1d0c4"]
+struct TimeTint {
+    color: Color,
+    time: f32,
+}
+
+impl TilemapMaterial for TimeTint {
+    fn fragment_shader(_: &AssetServer, shaders: &mut Assets<Shader>) -> Handle<Shader> {
+        shaders.add(Shader::from_glsl(
+            bevy::render::shader::ShaderStage::Fragment,
+            FRAGMENT_SHADER,
+        ))
+    }
+}
+
+fn startup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut tints: ResMut<Assets<TimeTint>>,
+    mut map_query: MapQuery,
+) {
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+
+    let texture_handle = asset_server.load("tiles.png");
+    let material_handle = materials.add(ColorMaterial::texture(texture_handle));
+
+    // Create map entity and component:
+    let map_entity = commands.spawn().id();
+    let mut map = Map::new(0u16, map_entity);
+
+    // Creates a new layer builder with a layer entity.
+    let (mut layer_builder, _) = LayerBuilder::new(
+        &mut commands,
+        LayerSettings::new(
+            MapSize(2, 2),
+            ChunkSize(8, 8),
+            TileSize(16.0, 16.0),
+            TextureSize(96.0, 16.0),
+        ),
+        0u16,
+        0u16,
+        None,
+    );
+
+    layer_builder.set_all(TileBundle::default());
+
+    let layer_entity = map_query.build_layer(&mut commands, layer_builder, material_handle);
+
+    // Draws the layer with the custom material.
+    commands.entity(layer_entity).insert(tints.add(TimeTint {
+        color: Color::rgb(1.0, 0.3, 0.3),
+        time: 0.0,
+    }));
+
+    // Required to keep track of layers for a map internally.
+    map.add_layer(&mut commands, 0u16, layer_entity);
+
+    // Spawn Map
+    // Required in order to use map_query to retrieve layers/tiles.
+    commands
+        .entity(map_entity)
+        .insert(map)
+        .insert(Transform::from_xyz(-128.0, -128.0, 0.0))
+        .insert(GlobalTransform::default());
+}
+
+fn update_time(
+    time: Res<Time>,
+    mut tints: ResMut<Assets<TimeTint>>,
+    query: Query<&Handle<TimeTint>, With<Layer>>,
+) {
+    for handle in query.iter() {
+        if let Some(tint) = tints.get_mut(handle) {
+            tint.time = time.seconds_since_startup() as f32;
+        }
+    }
+}
+
+fn main() {
+    env_logger::Builder::from_default_env()
+        .filter_level(log::LevelFilter::Info)
+        .init();
+
+    App::build()
+        .insert_resource(WindowDescriptor {
+            width: 1270.0,
+            height: 720.0,
+            title: String::from("Custom Material Example"),
+            ..Default::default()
+        })
+        .add_plugins(DefaultPlugins)
+        .add_plugin(TilemapPlugin)
+        .add_plugin(TilemapMaterialPlugin::<TimeTint>::default())
+        .add_startup_system(startup.system())
+        .add_system(helpers::camera::movement.system())
+        .add_system(helpers::texture::set_texture_filters_to_nearest.system())
+        .add_system(update_time.system())
+        .run();
+}
@@ -0,0 +1,183 @@
+use bevy::{
+    prelude::*,
+    render::{
+        pass::{
+            LoadOp, Operations, PassDescriptor, RenderPassDepthStencilAttachmentDescriptor,
+            TextureAttachment,
+        },
+        render_graph::{base, PassNode, RenderGraph, WindowSwapChainNode, WindowTextureNode},
+    },
+};
+use bevy_ecs_tilemap::prelude::*;
+
+mod helpers;
+
+const OVERLAY_PASS: &str = "tilemap_overlay_pass";
+
+// Marks the entities drawn by the overlay pass.
+#[derive(Clone)]
+struct OverlayPass;
+
+fn startup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut map_query: MapQuery,
+) {
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+
+    let texture_handle = asset_server.load("tiles.png");
+    let material_handle = materials.add(ColorMaterial::texture(texture_handle));
+
+    // Create map entity and component:
+    let map_entity = commands.spawn().id();
+    let mut map = Map::new(0u16, map_entity);
+
+    let settings = LayerSettings::new(
+        MapSize(2, 2),
+        ChunkSize(8, 8),
+        TileSize(16.0, 16.0),
+        TextureSize(96.0, 16.0),
+    );
+
+    // The overlay layer has the lower layer id, it's still drawn over the ground because the
+    // overlay pass runs after the main pass.
+    let (mut layer_builder, _) =
+        LayerBuilder::<TileBundle>::new(&mut commands, settings, 0u16, 0u16, None);
+    for i in 0..16 {
+        layer_builder
+            .set_tile(
+                TilePos(i, i),
+                Tile {
+                    texture_index: 5,
+                    ..Default::default()
+                }
+                .into(),
+            )
+            .unwrap();
+    }
+    let overlay_entity =
+        map_query.build_layer(&mut commands, layer_builder, material_handle.clone());
+    commands.entity(overlay_entity).insert(OverlayPass);
+    map.add_layer(&mut commands, 0u16, overlay_entity);
+
+    let (mut layer_builder, _) =
+        LayerBuilder::<TileBundle>::new(&mut commands, settings, 0u16, 1u16, None);
+    layer_builder.set_all(TileBundle::default());
+    let ground_entity = map_query.build_layer(&mut commands, layer_builder, material_handle);
+    map.add_layer(&mut commands, 1u16, ground_entity);
+
+    // Spawn Map
+    // Required in order to use map_query to retrieve layers/tiles.
+    commands
+        .entity(map_entity)
+        .insert(map)
+        .insert(Transform::from_xyz(-128.0, -128.0, 0.0))
+        .insert(GlobalTransform::default());
+}
+
+// Adds a pass which draws the entities marked with `OverlayPass` over the main pass, with the
+// 2d camera. It loads the color of the main pass and clears its depth.
+fn add_overlay_pass(app: &mut AppBuilder) {
+    let world = app.world_mut();
+    let samples = world.get_resource::<Msaa>().unwrap().samples;
+    let mut graph = world.get_resource_mut::<RenderGraph>().unwrap();
+
+    let mut pass_node = PassNode::<&OverlayPass>::new(PassDescriptor {
+        color_attachments: vec![Msaa { samples }.color_attachment_descriptor(
+            TextureAttachment::Input("color_attachment".to_string()),
+            TextureAttachment::Input("color_resolve_target".to_string()),
+            Operations {
+                load: LoadOp::Load,
+                store: true,
+            },
+        )],
+        depth_stencil_attachment: Some(RenderPassDepthStencilAttachmentDescriptor {
+            attachment: TextureAttachment::Input("depth".to_string()),
+            depth_ops: Some(Operations {
+                load: LoadOp::Clear(1.0),
+                store: true,
+            }),
+            stencil_ops: None,
+        }),
+        sample_count: samples,
+    });
+    pass_node.add_camera(base::camera::CAMERA_2D);
+    graph.add_node(OVERLAY_PASS, pass_node);
+
+    graph
+        .add_slot_edge(
+            base::node::PRIMARY_SWAP_CHAIN,
+            WindowSwapChainNode::OUT_TEXTURE,
+            OVERLAY_PASS,
+            if samples > 1 {
+                "color_resolve_target"
+            } else {
+                "color_attachment"
+            },
+        )
+        .unwrap();
+    graph
+        .add_slot_edge(
+            base::node::MAIN_DEPTH_TEXTURE,
+            WindowTextureNode::OUT_TEXTURE,
+            OVERLAY_PASS,
+            "depth",
+        )
+        .unwrap();
+    if samples > 1 {
+        graph
+            .add_slot_edge(
+                base::node::MAIN_SAMPLED_COLOR_ATTACHMENT,
+                WindowSwapChainNode::OUT_TEXTURE,
+                OVERLAY_PASS,
+                "color_attachment",
+            )
+            .unwrap();
+    }
+    graph
+        .add_node_edge(base::node::MAIN_PASS, OVERLAY_PASS)
+        .unwrap();
+}
+
+// Moves the overlay layer between the overlay pass and the main pass with the space bar.
+fn toggle_pass(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    map_query: MapQuery,
+    overlay_query: Query<(), With<OverlayPass>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Space) {
+        return;
+    }
+    if let Some((layer_entity, _)) = map_query.get_layer(0u16, 0u16) {
+        if overlay_query.get(layer_entity).is_ok() {
+            commands.entity(layer_entity).remove::<OverlayPass>();
+        } else {
+            commands.entity(layer_entity).insert(OverlayPass);
+        }
+    }
+}
+
+fn main() {
+    env_logger::Builder::from_default_env()
+        .filter_level(log::LevelFilter::Info)
+        .init();
+
+    let mut app = App::build();
+    app.insert_resource(WindowDescriptor {
+        width: 1270.0,
+        height: 720.0,
+        title: String::from("Custom Pass Example"),
+        ..Default::default()
+    })
+    .add_plugins(DefaultPlugins)
+    .add_plugin(TilemapPlugin)
+    .add_plugin(TilemapPassPlugin::<OverlayPass>::default())
+    .add_startup_system(startup.system())
+    .add_system(helpers::camera::movement.system())
+    .add_system(helpers::texture::set_texture_filters_to_nearest.system())
+    .add_system(toggle_pass.system());
+    add_overlay_pass(&mut app);
+    app.run();
+}
@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+mod helpers;
+
+struct FadeTarget(f32);
+
+fn startup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut map_query: MapQuery,
+) {
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+
+    let texture_handle = asset_server.load("tiles.png");
+    let material_handle = materials.add(ColorMaterial::texture(texture_handle));
+
+    // Create map entity and component:
+    let map_entity = commands.spawn().id();
+    let mut map = Map::new(0u16, map_entity);
+
+    let map_settings = LayerSettings::new(
+        MapSize(2, 2),
+        ChunkSize(8, 8),
+        TileSize(16.0, 16.0),
+        TextureSize(96.0, 16.0),
+    );
+
+    // Layer 0
+    let (mut layer_0, layer_0_entity) =
+        LayerBuilder::new(&mut commands, map_settings, 0u16, 0u16, None);
+    map.add_layer(&mut commands, 0u16, layer_0_entity);
+    layer_0.set_all(TileBundle::default());
+    map_query.build_layer(&mut commands, layer_0, material_handle.clone());
+
+    // Layer 1, the "roof" that gets faded out.
+    let mut roof_settings = map_settings;
+    roof_settings.set_layer_id(1u16);
+    let (mut layer_1, layer_1_entity) =
+        LayerBuilder::<TileBundle>::new(&mut commands, roof_settings, 0u16, 1u16, None);
+    map.add_layer(&mut commands, 1u16, layer_1_entity);
+    layer_1.fill(
+        TilePos(4, 4),
+        TilePos(12, 12),
+        Tile {
+            texture_index: 2,
+            ..Default::default()
+        }
+        .into(),
+    );
+    map_query.build_layer(&mut commands, layer_1, material_handle);
+    commands.entity(layer_1_entity).insert(FadeTarget(1.0));
+
+    // Spawn Map
+    // Required in order to use map_query to retrieve layers/tiles.
+    commands
+        .entity(map_entity)
+        .insert(map)
+        .insert(Transform::from_xyz(-128.0, -128.0, 0.0))
+        .insert(GlobalTransform::default());
+}
+
+fn fade(
+    time: Res<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut query: Query<(&mut Layer, &mut FadeTarget)>,
+) {
+    for (mut layer, mut target) in query.iter_mut() {
+        if keyboard_input.just_pressed(KeyCode::Space) {
+            target.0 = 1.0 - target.0;
+        }
+
+        // Only touch the layer while fading, any change to it updates the chunk uniforms.
+        let alpha = layer.settings.alpha;
+        if (alpha - target.0).abs() > f32::EPSILON {
+            let step = time.delta_seconds() * 2.0;
+            layer.settings.alpha = if alpha < target.0 {
+                (alpha + step).min(target.0)
+            } else {
+                (alpha - step).max(target.0)
+            };
+        }
+    }
+}
+
+fn main() {
+    env_logger::Builder::from_default_env()
+        .filter_level(log::LevelFilter::Info)
+        .init();
+
+    App::build()
+        .insert_resource(WindowDescriptor {
+            width: 1270.0,
+            height: 720.0,
+            title: String::from("Fade Layer Example - Press space to fade the top layer"),
+            ..Default::default()
+        })
+        .add_plugins(DefaultPlugins)
+        .add_plugin(TilemapPlugin)
+        .add_startup_system(startup.system())
+        .add_system(helpers::camera::movement.system())
+        .add_system(helpers::texture::set_texture_filters_to_nearest.system())
+        .add_system(fade.system())
+        .run();
+}
@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use rand::{thread_rng, Rng};
+
+mod helpers;
+
+fn startup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut map_query: MapQuery,
+) {
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+
+    let texture_handle = asset_server.load("tiles.png");
+    let material_handle = materials.add(ColorMaterial::texture(texture_handle));
+
+    // Create map entity and component:
+    let map_entity = commands.spawn().id();
+    let mut map = Map::new(0u16, map_entity);
+
+    // Creates a new layer builder with a layer entity.
+    let (mut layer_builder, _) = LayerBuilder::<TileBundle>::new(
+        &mut commands,
+        LayerSettings::new(
+            MapSize(4, 4),
+            ChunkSize(8, 8),
+            TileSize(16.0, 16.0),
+            TextureSize(96.0, 16.0),
+        ),
+        0u16,
+        0u16,
+        None,
+    );
+
+    let mut random = thread_rng();
+    layer_builder.for_each_tiles_mut(|_, tile_bundle| {
+        *tile_bundle = Some(TileBundle {
+            tile: Tile {
+                texture_index: random.gen_range(0..6),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+    });
+
+    let layer_entity = map_query.build_layer(&mut commands, layer_builder, material_handle);
+
+    // Only the tiles around the center of the fog stay visible.
+    commands.entity(layer_entity).insert(TilemapFog {
+        color: Color::rgb(0.05, 0.05, 0.1),
+        center: Vec2::ZERO,
+        radius: 96.0,
+        falloff: 64.0,
+    });
+
+    // Required to keep track of layers for a map internally.
+    map.add_layer(&mut commands, 0u16, layer_entity);
+
+    // Spawn Map
+    // Required in order to use map_query to retrieve layers/tiles.
+    commands
+        .entity(map_entity)
+        .insert(map)
+        .insert(Transform::from_xyz(-256.0, -256.0, 0.0))
+        .insert(GlobalTransform::default());
+}
+
+// Moves the center of the fog in a circle around the middle of the map.
+fn move_fog(time: Res<Time>, mut query: Query<&mut TilemapFog>) {
+    let angle = time.seconds_since_startup() as f32 * 0.5;
+    for mut fog in query.iter_mut() {
+        fog.center = Vec2::new(angle.cos(), angle.sin()) * 160.0;
+    }
+}
+
+fn main() {
+    env_logger::Builder::from_default_env()
+        .filter_level(log::LevelFilter::Info)
+        .init();
+
+    App::build()
+        .insert_resource(WindowDescriptor {
+            width: 1270.0,
+            height: 720.0,
+            title: String::from("Fog Example"),
+            ..Default::default()
+        })
+        .add_plugins(DefaultPlugins)
+        .add_plugin(TilemapPlugin)
+        .add_startup_system(startup.system())
+        .add_system(helpers::camera::movement.system())
+        .add_system(helpers::texture::set_texture_filters_to_nearest.system())
+        .add_system(move_fog.system())
+        .run();
+}
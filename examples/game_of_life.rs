@@ -53,7 +53,7 @@ fn startup(
         }
     }
 
-    map_query.build_layer(&mut commands, layer_builder, material_handle.clone());
+    map_query.build_layer(&mut commands, layer_builder, material_handle);
 
     commands.entity(layer_entity).insert(LastUpdate(0.0));
 
@@ -87,9 +87,9 @@ fn update(
                     .get_tile_neighbors(*pos, 0u16, 0u16)
                     .iter()
                     .filter(|&&neighboring_result| {
-                        if neighboring_result.is_ok() {
+                        if let Ok(neighboring_entity) = neighboring_result {
                             let tile_component: &Tile = tile_query
-                                .get_component::<Tile>(neighboring_result.unwrap())
+                                .get_component::<Tile>(neighboring_entity)
                                 .unwrap();
                             tile_component.visible
                         } else {
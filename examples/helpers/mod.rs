@@ -1,3 +1,3 @@
 pub mod camera;
+pub mod movement;
 pub mod texture;
-pub mod movement;
\ No newline at end of file
@@ -6,13 +6,10 @@ pub fn set_texture_filters_to_nearest(
 ) {
     // quick and dirty, run this for all textures anytime a texture is created.
     for event in texture_events.iter() {
-        match event {
-            AssetEvent::Created { handle } => {
-                if let Some(mut texture) = textures.get_mut(handle) {
-                    texture.sampler.min_filter = FilterMode::Nearest;
-                }
+        if let AssetEvent::Created { handle } = event {
+            if let Some(mut texture) = textures.get_mut(handle) {
+                texture.sampler.min_filter = FilterMode::Nearest;
             }
-            _ => (),
         }
     }
 }
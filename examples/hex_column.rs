@@ -28,7 +28,7 @@ fn startup(
     map_settings.mesh_type = TilemapMeshType::Hexagon(HexType::Column);
 
     let (mut layer_builder, layer_entity) =
-        LayerBuilder::<TileBundle>::new(&mut commands, map_settings.clone(), 0u16, 0u16, None);
+        LayerBuilder::<TileBundle>::new(&mut commands, map_settings, 0u16, 0u16, None);
     map.add_layer(&mut commands, 0u16, layer_entity);
 
     layer_builder.fill(
@@ -71,7 +71,7 @@ fn startup(
     map_query.build_layer(&mut commands, layer_builder, material_handle.clone());
 
     for z in 0..2 {
-        let mut new_settings = map_settings.clone();
+        let mut new_settings = map_settings;
         new_settings.layer_id = z + 1;
         let (mut layer_builder, layer_entity) =
             LayerBuilder::<TileBundle>::new(&mut commands, new_settings, 0u16, 0u16, None);
@@ -31,7 +31,7 @@ fn startup(
 
     // Layer 0
     let (mut layer_0, layer_0_entity) =
-        LayerBuilder::<TileBundle>::new(&mut commands, map_settings.clone(), 0u16, 0u16, None);
+        LayerBuilder::<TileBundle>::new(&mut commands, map_settings, 0u16, 0u16, None);
     map.add_layer(&mut commands, 0u16, layer_0_entity);
 
     layer_0.fill(
@@ -75,11 +75,11 @@ fn startup(
 
     // Make 2 layers on "top" of the base map.
     for z in 0..1 {
-        let mut new_settings = map_settings.clone();
+        let mut new_settings = map_settings;
         new_settings.layer_id = z + 1;
         let (mut layer_builder, layer_entity) = LayerBuilder::new(
             &mut commands,
-            new_settings.clone(),
+            new_settings,
             0u16,
             new_settings.layer_id,
             None,
@@ -95,7 +95,7 @@ fn startup(
                 position,
                 TileBundle {
                     tile: Tile {
-                        texture_index: 0 + z + 1,
+                        texture_index: z + 1,
                         ..Default::default()
                     },
                     ..Default::default()
@@ -136,7 +136,7 @@ fn startup(
 fn project_iso(pos: Vec2, tile_width: f32, tile_height: f32) -> Vec2 {
     let x = (pos.x - pos.y) * tile_width / 2.0;
     let y = (pos.x + pos.y) * tile_height / 2.0;
-    return Vec2::new(x, -y);
+    Vec2::new(x, -y)
 }
 
 fn main() {
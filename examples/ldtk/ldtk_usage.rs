@@ -1,10 +1,8 @@
-use crate::ldtk::*;
 use bevy::prelude::*;
 use bevy_ecs_tilemap::prelude::*;
 
 #[path = "../helpers/mod.rs"]
 mod helpers;
-mod ldtk;
 
 fn startup(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands.spawn_bundle(OrthographicCameraBundle::new_2d());
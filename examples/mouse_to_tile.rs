@@ -0,0 +1,131 @@
+use bevy::{prelude::*, render::camera::Camera};
+use bevy_ecs_tilemap::prelude::*;
+
+mod helpers;
+
+#[derive(Default)]
+struct HoveredTile(Option<Entity>);
+
+fn startup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut map_query: MapQuery,
+) {
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+
+    let texture_handle = asset_server.load("tiles.png");
+    let material_handle = materials.add(ColorMaterial::texture(texture_handle));
+
+    // Create map entity and component:
+    let map_entity = commands.spawn().id();
+    let mut map = Map::new(0u16, map_entity);
+
+    let (mut layer_builder, layer_entity) = LayerBuilder::<TileBundle>::new(
+        &mut commands,
+        LayerSettings::new(
+            MapSize(2, 2),
+            ChunkSize(8, 8),
+            TileSize(16.0, 16.0),
+            TextureSize(96.0, 16.0),
+        ),
+        0u16,
+        0u16,
+        None,
+    );
+
+    layer_builder.set_all(TileBundle::default());
+
+    map_query.build_layer(&mut commands, layer_builder, material_handle);
+
+    // Required to keep track of layers for a map internally.
+    map.add_layer(&mut commands, 0u16, layer_entity);
+
+    // Spawn Map
+    // Required in order to use map_query to retrieve layers/tiles.
+    commands
+        .entity(map_entity)
+        .insert(map)
+        .insert(Transform::from_xyz(-128.0, -128.0, 0.0))
+        .insert(GlobalTransform::default());
+}
+
+// Rotates the camera with Q and E to show that picking follows it.
+fn rotate_camera(
+    time: Res<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut query: Query<&mut Transform, With<Camera>>,
+) {
+    let mut angle = 0.0;
+    if keyboard_input.pressed(KeyCode::Q) {
+        angle += time.delta_seconds();
+    }
+    if keyboard_input.pressed(KeyCode::E) {
+        angle -= time.delta_seconds();
+    }
+
+    for mut transform in query.iter_mut() {
+        transform.rotate(Quat::from_rotation_z(angle));
+    }
+}
+
+fn highlight_tile(
+    windows: Res<Windows>,
+    mut hovered: Local<HoveredTile>,
+    map_query: MapQuery,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    mut tile_query: Query<&mut Tile>,
+) {
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+
+    let tile_entity = match (window.cursor_position(), camera_query.iter().next()) {
+        (Some(cursor_pos), Some((camera, camera_transform))) => map_query
+            .screen_to_tile(
+                cursor_pos,
+                Vec2::new(window.width(), window.height()),
+                camera,
+                camera_transform,
+                0u16,
+                0u16,
+            )
+            .and_then(|tile_pos| map_query.get_tile_entity(tile_pos, 0u16, 0u16).ok()),
+        _ => None,
+    };
+
+    if tile_entity == hovered.0 {
+        return;
+    }
+
+    if let Some(mut tile) = hovered.0.and_then(|entity| tile_query.get_mut(entity).ok()) {
+        tile.color = Color::WHITE;
+    }
+    if let Some(mut tile) = tile_entity.and_then(|entity| tile_query.get_mut(entity).ok()) {
+        tile.color = Color::rgb(1.0, 0.3, 0.3);
+    }
+    hovered.0 = tile_entity;
+}
+
+fn main() {
+    env_logger::Builder::from_default_env()
+        .filter_level(log::LevelFilter::Info)
+        .init();
+
+    App::build()
+        .insert_resource(WindowDescriptor {
+            width: 1270.0,
+            height: 720.0,
+            title: String::from("Mouse To Tile Example - Q and E rotate the camera"),
+            ..Default::default()
+        })
+        .add_plugins(DefaultPlugins)
+        .add_plugin(TilemapPlugin)
+        .add_startup_system(startup.system())
+        .add_system(helpers::camera::movement.system())
+        .add_system(helpers::texture::set_texture_filters_to_nearest.system())
+        .add_system(rotate_camera.system())
+        .add_system(highlight_tile.system())
+        .run();
+}
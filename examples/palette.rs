@@ -0,0 +1,164 @@
+use bevy::{
+    prelude::*,
+    render::texture::{Extent3d, TextureDimension, TextureFormat},
+};
+use bevy_ecs_tilemap::prelude::*;
+use rand::{thread_rng, Rng};
+
+mod helpers;
+
+// Builds an atlas of four 16x16 tiles which store color indices in the red channel.
+fn index_texture() -> Texture {
+    let mut data = Vec::with_capacity(64 * 16 * 4);
+    for y in 0..16u32 {
+        for x in 0..64u32 {
+            let tile = x / 16;
+            let (tx, ty) = (x % 16, y % 16);
+            let index = match tile {
+                0 => 1,
+                1 => 1 + ((tx / 4 + ty / 4) % 2),
+                // Rings around the center of the tile.
+                2 => 1 + (tx.min(ty).min(15 - tx).min(15 - ty) / 2) % 3,
+                _ => {
+                    if tx == 0 || ty == 0 || tx == 15 || ty == 15 {
+                        3
+                    } else {
+                        2
+                    }
+                }
+            };
+            data.extend_from_slice(&[index as u8, 0, 0, 255]);
+        }
+    }
+    Texture::new(
+        Extent3d::new(64, 16, 1),
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8Unorm,
+    )
+}
+
+// Builds a texture with three palettes of four colors.
+fn palette_texture() -> Texture {
+    let palettes: [[[u8; 3]; 4]; 3] = [
+        [[0, 0, 0], [48, 98, 48], [139, 172, 15], [155, 188, 15]],
+        [[0, 0, 0], [136, 20, 0], [228, 92, 16], [252, 160, 68]],
+        [[0, 0, 0], [0, 64, 88], [0, 120, 248], [104, 136, 252]],
+    ];
+    let mut data = Vec::with_capacity(4 * 3 * 4);
+    for palette in palettes.iter() {
+        for color in palette.iter() {
+            data.extend_from_slice(&[color[0], color[1], color[2], 255]);
+        }
+    }
+    Texture::new(
+        Extent3d::new(4, 3, 1),
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+fn startup(
+    mut commands: Commands,
+    mut textures: ResMut<Assets<Texture>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut palettes: ResMut<Assets<TilemapPalette>>,
+    mut map_query: MapQuery,
+) {
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+
+    let texture_handle = textures.add(index_texture());
+    let material_handle = materials.add(ColorMaterial::texture(texture_handle));
+
+    // Create map entity and component:
+    let map_entity = commands.spawn().id();
+    let mut map = Map::new(0u16, map_entity);
+
+    // Creates a new layer builder with a layer entity.
+    let (mut layer_builder, _) = LayerBuilder::<TileBundle>::new(
+        &mut commands,
+        LayerSettings::new(
+            MapSize(2, 2),
+            ChunkSize(8, 8),
+            TileSize(16.0, 16.0),
+            TextureSize(64.0, 16.0),
+        ),
+        0u16,
+        0u16,
+        None,
+    );
+
+    let mut random = thread_rng();
+    for x in 0..16 {
+        for y in 0..16 {
+            let _ = layer_builder.set_tile(
+                TilePos(x, y),
+                Tile {
+                    texture_index: random.gen_range(0..4),
+                    // Every other tile is drawn with the next palette.
+                    palette: ((x + y) % 2) as u8,
+                    ..Default::default()
+                }
+                .into(),
+            );
+        }
+    }
+
+    let layer_entity = map_query.build_layer(&mut commands, layer_builder, material_handle);
+
+    // Draws the layer through the palettes.
+    let palette_handle = textures.add(palette_texture());
+    commands
+        .entity(layer_entity)
+        .insert(palettes.add(TilemapPalette::new(palette_handle)));
+
+    // Required to keep track of layers for a map internally.
+    map.add_layer(&mut commands, 0u16, layer_entity);
+
+    // Spawn Map
+    // Required in order to use map_query to retrieve layers/tiles.
+    commands
+        .entity(map_entity)
+        .insert(map)
+        .insert(Transform::from_xyz(-128.0, -128.0, 0.0))
+        .insert(GlobalTransform::default());
+}
+
+// Swaps to the next palette when space is pressed.
+fn swap_palette(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut palettes: ResMut<Assets<TilemapPalette>>,
+    query: Query<&Handle<TilemapPalette>, With<Layer>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Space) {
+        return;
+    }
+    for handle in query.iter() {
+        if let Some(palette) = palettes.get_mut(handle) {
+            palette.active_palette = (palette.active_palette + 1) % 3;
+        }
+    }
+}
+
+fn main() {
+    env_logger::Builder::from_default_env()
+        .filter_level(log::LevelFilter::Info)
+        .init();
+
+    App::build()
+        .insert_resource(WindowDescriptor {
+            width: 1270.0,
+            height: 720.0,
+            title: String::from("Palette Example"),
+            ..Default::default()
+        })
+        .add_plugins(DefaultPlugins)
+        .add_plugin(TilemapPlugin)
+        .add_plugin(TilemapMaterialPlugin::<TilemapPalette>::default())
+        .add_startup_system(startup.system())
+        .add_system(helpers::camera::movement.system())
+        .add_system(helpers::texture::set_texture_filters_to_nearest.system())
+        .add_system(swap_palette.system())
+        .run();
+}
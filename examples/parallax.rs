@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use rand::{thread_rng, Rng};
+
+mod helpers;
+
+fn startup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut map_query: MapQuery,
+) {
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+
+    let texture_handle = asset_server.load("tiles.png");
+    let material_handle = materials.add(ColorMaterial::texture(texture_handle));
+
+    // Create map entity and component:
+    let map_entity = commands.spawn().id();
+    let mut map = Map::new(0u16, map_entity);
+
+    let map_settings = LayerSettings::new(
+        MapSize(2, 2),
+        ChunkSize(8, 8),
+        TileSize(16.0, 16.0),
+        TextureSize(96.0, 16.0),
+    );
+
+    // The background layer drifts slowly to the left.
+    let mut background_settings = map_settings;
+    background_settings.scroll_speed = Vec2::new(-8.0, 0.0);
+    let (mut background, background_entity) =
+        LayerBuilder::new(&mut commands, background_settings, 0u16, 0u16, None);
+    background.set_all(TileBundle::default());
+    map_query.build_layer(&mut commands, background, material_handle.clone());
+
+    // Required to keep track of layers for a map internally.
+    map.add_layer(&mut commands, 0u16, background_entity);
+
+    // The sparse layer in front of it scrolls faster and diagonally, so it looks closer.
+    let mut clouds_settings = map_settings;
+    clouds_settings.set_layer_id(1u16);
+    clouds_settings.scroll_speed = Vec2::new(-32.0, 8.0);
+    let (mut clouds, clouds_entity) =
+        LayerBuilder::new(&mut commands, clouds_settings, 0u16, 1u16, None);
+
+    let mut random = thread_rng();
+    for _ in 0..60 {
+        let position = TilePos(random.gen_range(0..16), random.gen_range(0..16));
+        // Ignore errors for demo sake.
+        let _ = clouds.set_tile(
+            position,
+            TileBundle {
+                tile: Tile {
+                    texture_index: 2,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+    }
+    map_query.build_layer(&mut commands, clouds, material_handle);
+
+    map.add_layer(&mut commands, 1u16, clouds_entity);
+
+    // Spawn Map
+    // Required in order to use map_query to retrieve layers/tiles.
+    commands
+        .entity(map_entity)
+        .insert(map)
+        .insert(Transform::from_xyz(-128.0, -128.0, 0.0))
+        .insert(GlobalTransform::default());
+}
+
+// Stops and restarts the scrolling when space is pressed.
+fn toggle_scroll(keyboard_input: Res<Input<KeyCode>>, mut query: Query<&mut Layer>) {
+    if !keyboard_input.just_pressed(KeyCode::Space) {
+        return;
+    }
+    for mut layer in query.iter_mut() {
+        layer.settings.scroll_speed = if layer.settings.scroll_speed == Vec2::ZERO {
+            match layer.settings.layer_id {
+                0 => Vec2::new(-8.0, 0.0),
+                _ => Vec2::new(-32.0, 8.0),
+            }
+        } else {
+            Vec2::ZERO
+        };
+    }
+}
+
+fn main() {
+    env_logger::Builder::from_default_env()
+        .filter_level(log::LevelFilter::Info)
+        .init();
+
+    App::build()
+        .insert_resource(WindowDescriptor {
+            width: 1270.0,
+            height: 720.0,
+            title: String::from("Parallax Example"),
+            ..Default::default()
+        })
+        .add_plugins(DefaultPlugins)
+        .add_plugin(TilemapPlugin)
+        .add_startup_system(startup.system())
+        .add_system(helpers::camera::movement.system())
+        .add_system(helpers::texture::set_texture_filters_to_nearest.system())
+        .add_system(toggle_scroll.system())
+        .run();
+}
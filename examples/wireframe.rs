@@ -0,0 +1,81 @@
+use bevy::{
+    prelude::*,
+    wgpu::{WgpuFeature, WgpuFeatures, WgpuOptions},
+};
+use bevy_ecs_tilemap::prelude::*;
+
+mod helpers;
+
+fn startup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut map_query: MapQuery,
+) {
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+
+    let texture_handle = asset_server.load("tiles.png");
+    let material_handle = materials.add(ColorMaterial::texture(texture_handle));
+
+    // Create map entity and component:
+    let map_entity = commands.spawn().id();
+    let mut map = Map::new(0u16, map_entity);
+
+    let layer_settings = LayerSettings::new(
+        MapSize(4, 4),
+        ChunkSize(8, 8),
+        TileSize(16.0, 16.0),
+        TextureSize(96.0, 16.0),
+    );
+    let center = layer_settings.get_pixel_center();
+
+    let (mut layer_builder, layer_entity) =
+        LayerBuilder::new(&mut commands, layer_settings, 0u16, 0u16, None);
+    map.add_layer(&mut commands, 0u16, layer_entity);
+
+    layer_builder.set_all(TileBundle::default());
+
+    map_query.build_layer(&mut commands, layer_builder, material_handle);
+
+    // Spawn Map
+    // Required in order to use map_query to retrieve layers/tiles.
+    commands
+        .entity(map_entity)
+        .insert(map)
+        .insert(Transform::from_xyz(-center.x, -center.y, 0.0))
+        .insert(GlobalTransform::default());
+}
+
+fn toggle_wireframe(keyboard_input: Res<Input<KeyCode>>, mut debug: ResMut<TilemapDebug>) {
+    if keyboard_input.just_pressed(KeyCode::Space) {
+        debug.wireframe = !debug.wireframe;
+    }
+}
+
+fn main() {
+    env_logger::Builder::from_default_env()
+        .filter_level(log::LevelFilter::Info)
+        .init();
+
+    App::build()
+        .insert_resource(WindowDescriptor {
+            width: 1270.0,
+            height: 720.0,
+            title: String::from("Wireframe Example - Press space to toggle the wireframe"),
+            ..Default::default()
+        })
+        // Line rendering is an optional wgpu feature.
+        .insert_resource(WgpuOptions {
+            features: WgpuFeatures {
+                features: vec![WgpuFeature::NonFillPolygonMode],
+            },
+            ..Default::default()
+        })
+        .add_plugins(DefaultPlugins)
+        .add_plugin(TilemapPlugin)
+        .add_startup_system(startup.system())
+        .add_system(helpers::camera::movement.system())
+        .add_system(helpers::texture::set_texture_filters_to_nearest.system())
+        .add_system(toggle_wireframe.system())
+        .run();
+}
@@ -0,0 +1,283 @@
+use crate::layer::{LayerId, MapTileError};
+use crate::map::MapId;
+use crate::map_query::MapQuery;
+use crate::neighbors::get_neighboring_pos;
+use crate::tile::{Tile, TileParent};
+use crate::TilePos;
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+/// Marks a tile as "solid" for auto-tiling.
+///
+/// The texture index of every auto tile in a layer with an `AutoTileConfig` is picked from the
+/// config's table based on which of its neighbors are auto tiles as well.
+/// Tiles without this component are treated as empty and are never changed.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct AutoTile;
+
+/// The tileset layouts supported by auto-tiling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoTileMode {
+    /// The 16 tile set, only the orthogonal neighbors are looked at.
+    /// Bitmask: N = 1, E = 2, S = 4, W = 8.
+    Bitmask16,
+    /// The 47 tile blob set, diagonal neighbors are only counted when both of the orthogonal
+    /// neighbors next to them are solid.
+    /// Bitmask: N = 1, NE = 2, E = 4, SE = 8, S = 16, SW = 32, W = 64, NW = 128.
+    Blob47,
+}
+
+/// Auto-tiling settings for a layer, see `MapQuery::configure_auto_tile`.
+#[derive(Debug, Clone)]
+pub struct AutoTileConfig {
+    pub mode: AutoTileMode,
+    /// Maps a neighbor bitmask to a texture index.
+    /// Tiles whose bitmask isn't in the table keep their current texture index.
+    pub table: HashMap<u8, u16>,
+}
+
+impl AutoTileConfig {
+    /// Gets the bitmask for the neighbors of a tile.
+    /// `solid` is in the order returned by `get_neighboring_pos`: N, S, W, E, NW, NE, SW, SE.
+    /// ```
+    /// use bevy_ecs_tilemap::prelude::*;
+    /// use std::collections::HashMap;
+    ///
+    /// let config = |mode| AutoTileConfig {
+    ///     mode,
+    ///     table: HashMap::default(),
+    /// };
+    /// let bitmask16 = config(AutoTileMode::Bitmask16);
+    /// let blob47 = config(AutoTileMode::Blob47);
+    ///
+    /// // The top left corner of a block has solid neighbors to the E, S and SE.
+    /// let corner = [false, true, false, true, false, false, false, true];
+    /// assert_eq!(bitmask16.get_mask(corner), 2 | 4);
+    /// assert_eq!(blob47.get_mask(corner), 4 | 8 | 16);
+    ///
+    /// // A tile on the top edge of a block has everything but the northern neighbors.
+    /// let edge = [false, true, true, true, false, false, true, true];
+    /// assert_eq!(bitmask16.get_mask(edge), 2 | 4 | 8);
+    /// assert_eq!(blob47.get_mask(edge), 4 | 8 | 16 | 32 | 64);
+    ///
+    /// // A tile inside of a block.
+    /// assert_eq!(bitmask16.get_mask([true; 8]), 15);
+    /// assert_eq!(blob47.get_mask([true; 8]), 255);
+    ///
+    /// // Diagonals only count next to two solid orthogonal neighbors.
+    /// let diagonal = [true, false, false, false, false, true, false, false];
+    /// assert_eq!(bitmask16.get_mask(diagonal), 1);
+    /// assert_eq!(blob47.get_mask(diagonal), 1);
+    /// ```
+    pub fn get_mask(&self, solid: [bool; 8]) -> u8 {
+        let [n, s, w, e, nw, ne, sw, se] = solid;
+        match self.mode {
+            AutoTileMode::Bitmask16 => n as u8 | (e as u8) << 1 | (s as u8) << 2 | (w as u8) << 3,
+            AutoTileMode::Blob47 => {
+                n as u8
+                    | ((ne && n && e) as u8) << 1
+                    | (e as u8) << 2
+                    | ((se && s && e) as u8) << 3
+                    | (s as u8) << 4
+                    | ((sw && s && w) as u8) << 5
+                    | (w as u8) << 6
+                    | ((nw && n && w) as u8) << 7
+            }
+        }
+    }
+}
+
+impl<'a> MapQuery<'a> {
+    /// Enables auto-tiling for a layer using the given bitmask to texture index table.
+    /// Every tile with an `AutoTile` component in the layer is updated, afterwards tiles are
+    /// updated whenever an auto tile next to them is added or removed.
+    /// Returns an error if the layer doesn't exist.
+    pub fn configure_auto_tile(
+        &self,
+        commands: &mut Commands,
+        map_id: impl MapId,
+        layer_id: impl LayerId,
+        mode: AutoTileMode,
+        table: HashMap<u8, u16>,
+    ) -> Result<(), MapTileError> {
+        if let Some((layer_entity, _)) = self.get_layer(map_id, layer_id) {
+            commands
+                .entity(layer_entity)
+                .insert(AutoTileConfig { mode, table });
+            return Ok(());
+        }
+
+        Err(MapTileError::NonExistent)
+    }
+}
+
+/// Positions of the auto tiles, kept so their neighbors can be updated after they are despawned.
+#[derive(Default)]
+pub(crate) struct AutoTileCache {
+    tiles: HashMap<Entity, (u16, u16, TilePos)>,
+}
+
+type AddedAutoTileQuery<'a> = Query<
+    'a,
+    (Entity, &'static TilePos, &'static TileParent),
+    (With<AutoTile>, Or<(Added<AutoTile>, Changed<TilePos>)>),
+>;
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn update_auto_tiles(
+    mut cache: ResMut<AutoTileCache>,
+    map_query: MapQuery,
+    added_tiles: AddedAutoTileQuery,
+    removed_tiles: RemovedComponents<AutoTile>,
+    all_tiles: Query<(&TilePos, &TileParent), With<AutoTile>>,
+    changed_configs: Query<Entity, Changed<AutoTileConfig>>,
+    configs: Query<&AutoTileConfig>,
+    mut tile_query: Query<&mut Tile, With<AutoTile>>,
+) {
+    // Tile positions whose neighbors changed, keyed by map and layer id.
+    let mut dirty = HashSet::new();
+
+    for (entity, tile_pos, tile_parent) in added_tiles.iter() {
+        let key = (tile_parent.map_id, tile_parent.layer_id, *tile_pos);
+        if let Some(old_key) = cache.tiles.insert(entity, key) {
+            dirty.insert(old_key);
+        }
+        dirty.insert(key);
+    }
+
+    for entity in removed_tiles.iter() {
+        if let Some(key) = cache.tiles.remove(&entity) {
+            dirty.insert(key);
+        }
+    }
+
+    // Every neighbor of a changed tile might need a new texture as well.
+    for (map_id, layer_id, tile_pos) in dirty.clone() {
+        for neighbor_pos in get_neighboring_pos(tile_pos).iter().flatten() {
+            dirty.insert((map_id, layer_id, *neighbor_pos));
+        }
+    }
+
+    // A new or changed config updates the entire layer.
+    let changed_layers: HashSet<Entity> = changed_configs.iter().collect();
+    if !changed_layers.is_empty() {
+        for (tile_pos, tile_parent) in all_tiles.iter() {
+            if let Some((layer_entity, _)) =
+                map_query.get_layer(tile_parent.map_id, tile_parent.layer_id)
+            {
+                if changed_layers.contains(&layer_entity) {
+                    dirty.insert((tile_parent.map_id, tile_parent.layer_id, *tile_pos));
+                }
+            }
+        }
+    }
+
+    for (map_id, layer_id, tile_pos) in dirty {
+        let config = match map_query
+            .get_layer(map_id, layer_id)
+            .and_then(|(layer_entity, _)| configs.get(layer_entity).ok())
+        {
+            Some(config) => config,
+            None => continue,
+        };
+
+        let tile_entity = match map_query.get_tile_entity(tile_pos, map_id, layer_id) {
+            Ok(tile_entity) if all_tiles.get(tile_entity).is_ok() => tile_entity,
+            _ => continue,
+        };
+
+        let mut solid = [false; 8];
+        for (solid, neighbor_pos) in solid.iter_mut().zip(get_neighboring_pos(tile_pos).iter()) {
+            if let Some(neighbor_pos) = neighbor_pos {
+                *solid = map_query
+                    .get_tile_entity(*neighbor_pos, map_id, layer_id)
+                    .map_or(false, |neighbor| all_tiles.get(neighbor).is_ok());
+            }
+        }
+
+        if let Some(texture_index) = config.table.get(&config.get_mask(solid)) {
+            if let Ok(mut tile) = tile_query.get_mut(tile_entity) {
+                // Only touch the tile if needed so unchanged chunks aren't remeshed.
+                if tile.texture_index != *texture_index {
+                    tile.texture_index = *texture_index;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use crate::test_util::{into_world, layer_settings, new_app, spawn_layer};
+    use bevy::ecs::schedule::{Schedule, SystemStage};
+
+    fn texture_indices(world: &mut World) -> HashMap<(u32, u32), u16> {
+        world
+            .query::<(&TilePos, &Tile)>()
+            .iter(world)
+            .map(|(tile_pos, tile)| ((tile_pos.0, tile_pos.1), tile.texture_index))
+            .collect()
+    }
+
+    #[test]
+    fn auto_tiles_follow_their_neighbors() {
+        let mut app = new_app();
+        app.init_resource::<AutoTileCache>();
+        let mut world = into_world(app);
+        let settings = layer_settings(MapSize(1, 1), ChunkSize(4, 4));
+        let layer_entity = spawn_layer(&mut world, settings, Handle::default(), |layer_builder| {
+            layer_builder.set_all(TileBundle::default());
+        });
+
+        // A 3x3 block of auto tiles in the bottom left of the layer.
+        let tiles: Vec<(Entity, TilePos)> = world
+            .query::<(Entity, &TilePos)>()
+            .iter(&world)
+            .map(|(entity, tile_pos)| (entity, *tile_pos))
+            .collect();
+        for (tile_entity, tile_pos) in tiles.iter() {
+            if tile_pos.0 < 3 && tile_pos.1 < 3 {
+                world.entity_mut(*tile_entity).insert(AutoTile);
+            }
+        }
+        world.entity_mut(layer_entity).insert(AutoTileConfig {
+            mode: AutoTileMode::Bitmask16,
+            table: (0..16).map(|mask| (mask, mask as u16)).collect(),
+        });
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage(
+            "auto_tile",
+            SystemStage::single_threaded().with_system(update_auto_tiles.system()),
+        );
+        schedule.run(&mut world);
+
+        let indices = texture_indices(&mut world);
+        assert_eq!(indices[&(0, 0)], 1 | 2);
+        assert_eq!(indices[&(1, 0)], 1 | 2 | 8);
+        assert_eq!(indices[&(0, 1)], 1 | 2 | 4);
+        assert_eq!(indices[&(1, 1)], 15);
+        assert_eq!(indices[&(2, 2)], 4 | 8);
+        // Tiles without `AutoTile` are left alone.
+        assert_eq!(indices[&(3, 1)], 0);
+        assert_eq!(indices[&(3, 3)], 0);
+
+        // Removing the center of the block updates its neighbors.
+        let (center, _) = tiles
+            .iter()
+            .find(|(_, tile_pos)| *tile_pos == TilePos(1, 1))
+            .unwrap();
+        world.entity_mut(*center).remove::<AutoTile>();
+        schedule.run(&mut world);
+
+        let indices = texture_indices(&mut world);
+        assert_eq!(indices[&(1, 0)], 2 | 8);
+        assert_eq!(indices[&(0, 1)], 1 | 4);
+        assert_eq!(indices[&(2, 1)], 1 | 4);
+        assert_eq!(indices[&(1, 2)], 2 | 8);
+        assert_eq!(indices[&(0, 0)], 1 | 2);
+        assert_eq!(indices[&(1, 1)], 15);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::layer::LayerId;
+use crate::map::MapId;
+use crate::map_query::MapQuery;
+use crate::tile::Tile;
+use crate::{LayerSettings, TilePos, TilemapMeshType, TilemapTextureMode};
+use bevy::{
+    prelude::*,
+    render::texture::{Extent3d, TextureDimension, TextureFormat},
+};
+
+/// Why a layer couldn't be baked into a texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapBakeError {
+    /// The layer doesn't exist.
+    NonExistent,
+    /// Only square layers can be baked.
+    UnsupportedMeshType,
+    /// The layer's texture isn't an 8 bit RGBA or BGRA texture.
+    UnsupportedFormat(TextureFormat),
+}
+
+/// Copies tiles from a layer's texture into a single texture covering the whole layer, to draw
+/// a static layer as one sprite instead of a tile per entity.
+///
+/// Every tile gets a `tile_size` cell of the texture, with tile (0, 0) in the bottom left
+/// corner, so the texture is `get_layer_size_in_tiles() * tile_size` pixels large. Draw it
+/// stretched over `get_layer_size_in_tiles() * grid_size` to line up with the layer.
+/// Tiles are flipped and tinted by their color and the layer's `tint` and `alpha` like the
+/// shaders do, hidden tiles and cells without a tile stay transparent. Animations are baked at
+/// their current `texture_index` and `Tile::angle`, `tile_offset` and the layer's shader
+/// effects are ignored.
+/// ```
+/// use bevy::{
+///     prelude::*,
+///     render::texture::{Extent3d, TextureDimension, TextureFormat},
+/// };
+/// use bevy_ecs_tilemap::prelude::*;
+///
+/// // Two 2x2 tiles side by side, the first one transparent and the second one white.
+/// let mut atlas_data = vec![0; 4 * 2 * 4];
+/// for row in 0..2 {
+///     atlas_data[row * 16 + 8..row * 16 + 16].copy_from_slice(&[255; 8]);
+/// }
+/// let atlas = Texture::new(
+///     Extent3d::new(4, 2, 1),
+///     TextureDimension::D2,
+///     atlas_data,
+///     TextureFormat::Rgba8UnormSrgb,
+/// );
+///
+/// let settings = LayerSettings::new(
+///     MapSize(1, 1),
+///     ChunkSize(4, 4),
+///     TileSize(2.0, 2.0),
+///     TextureSize(4.0, 2.0),
+/// );
+/// let tile = Tile {
+///     texture_index: 1,
+///     color: Color::rgba(1.0, 0.0, 0.0, 1.0),
+///     ..Default::default()
+/// };
+/// let baked = bake_tiles(&settings, vec![(TilePos(1, 0), &tile)], &atlas).unwrap();
+///
+/// assert_eq!(baked.size, Extent3d::new(8, 8, 1));
+/// // The tile is in the bottom row of cells, the texture's rows start at the top.
+/// let pixel = |x: usize, y: usize| &baked.data[(y * 8 + x) * 4..][..4];
+/// assert_eq!(pixel(2, 7), &[255, 0, 0, 255]);
+/// assert_eq!(pixel(3, 6), &[255, 0, 0, 255]);
+/// assert_eq!(pixel(0, 7), &[0, 0, 0, 0]);
+/// assert_eq!(pixel(2, 5), &[0, 0, 0, 0]);
+/// ```
+pub fn bake_tiles<'t>(
+    settings: &LayerSettings,
+    tiles: impl IntoIterator<Item = (TilePos, &'t Tile)>,
+    texture: &Texture,
+) -> Result<Texture, MapBakeError> {
+    if settings.mesh_type != TilemapMeshType::Square {
+        return Err(MapBakeError::UnsupportedMeshType);
+    }
+    let bgra = match texture.format {
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => false,
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => true,
+        format => return Err(MapBakeError::UnsupportedFormat(format)),
+    };
+
+    let layer_width = settings.map_size.0 * settings.chunk_size.0;
+    let layer_height = settings.map_size.1 * settings.chunk_size.1;
+    let tile_width = settings.tile_size.0 as usize;
+    let tile_height = settings.tile_size.1 as usize;
+    let width = layer_width as usize * tile_width;
+    let height = layer_height as usize * tile_height;
+    let source_width = texture.size.width as usize;
+    let source_height = texture.size.height as usize;
+    let mut data = vec![0; width * height * 4];
+
+    for (tile_pos, tile) in tiles {
+        if !tile.visible || tile_pos.0 >= layer_width || tile_pos.1 >= layer_height {
+            continue;
+        }
+
+        let rect_min = settings.get_texture_rect(tile.texture_index).min;
+        let (rect_x, mut rect_y) = (rect_min.x as usize, rect_min.y as usize);
+        // Array textures are stored as their layers stacked on top of each other.
+        if settings.texture_mode == TilemapTextureMode::Array {
+            rect_y += tile.texture_index as usize * settings.texture_size.1 as usize;
+        }
+
+        let [r, g, b, a] = settings.get_tile_color(tile).as_rgba_f32();
+        let tint = if bgra { [b, g, r, a] } else { [r, g, b, a] };
+        let cell_x = tile_pos.0 as usize * tile_width;
+        let cell_y = (layer_height - 1 - tile_pos.1) as usize * tile_height;
+
+        for y in 0..tile_height {
+            for x in 0..tile_width {
+                // The same corner mapping as the vertex shaders, flip_d swaps the axes last.
+                let mut source_x = if tile.flip_x { tile_width - 1 - x } else { x };
+                let mut source_y = if tile.flip_y { tile_height - 1 - y } else { y };
+                if tile.flip_d {
+                    std::mem::swap(&mut source_x, &mut source_y);
+                }
+                source_x += rect_x;
+                source_y += rect_y;
+                if settings.flip_v {
+                    source_y = source_height.wrapping_sub(source_y + 1);
+                }
+                if source_x >= source_width || source_y >= source_height {
+                    continue;
+                }
+
+                let source = &texture.data[(source_y * source_width + source_x) * 4..][..4];
+                let target = &mut data[((cell_y + y) * width + cell_x + x) * 4..][..4];
+                for channel in 0..4 {
+                    target[channel] = (source[channel] as f32 * tint[channel]).round() as u8;
+                }
+            }
+        }
+    }
+
+    let mut baked = Texture::new(
+        Extent3d::new(width as u32, height as u32, 1),
+        TextureDimension::D2,
+        data,
+        texture.format,
+    );
+    baked.sampler = texture.sampler;
+    Ok(baked)
+}
+
+impl<'a> MapQuery<'a> {
+    /// Bakes the tiles of a layer into a single texture with `bake_tiles`, using the layer's
+    /// `texture`. Static layers can then be drawn as one sprite and despawned with
+    /// `despawn_layer`.
+    ///
+    /// ## Example
+    ///
+    /// ```ignore
+    /// let baked = map_query.bake_layer(&tile_query, textures.get(&texture_handle).unwrap(), 0u16, 0u16)?;
+    /// let sprite_material = materials.add(textures.add(baked).into());
+    /// ```
+    pub fn bake_layer(
+        &self,
+        tile_query: &Query<&Tile>,
+        texture: &Texture,
+        map_id: impl MapId,
+        layer_id: impl LayerId,
+    ) -> Result<Texture, MapBakeError> {
+        let map_id = map_id.into();
+        let layer_id = layer_id.into();
+        let (_, layer) = self
+            .get_layer(map_id, layer_id)
+            .ok_or(MapBakeError::NonExistent)?;
+        let layer_size = layer.get_layer_size_in_tiles();
+        let tiles = self.tiles_in_rect(
+            tile_query,
+            TilePos(0, 0),
+            TilePos(layer_size.0, layer_size.1),
+            map_id,
+            layer_id,
+        );
+        bake_tiles(&layer.settings, tiles, texture)
+    }
+}
@@ -0,0 +1,122 @@
+use crate::tile::Tile;
+use crate::TilePos;
+use std::collections::HashMap;
+
+/// Collects tile writes for a layer, which are applied in a single pass with
+/// `MapQuery::apply_tile_batch`.
+///
+/// Useful when thousands of tiles change every frame, like in falling sand or fluid simulations.
+/// Writing the same position more than once keeps only the last tile.
+#[derive(Debug, Clone, Default)]
+pub struct TileUpdateBatch {
+    pub(crate) updates: HashMap<TilePos, Tile>,
+}
+
+impl TileUpdateBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a tile for a position, replacing the tile queued for it before.
+    pub fn set(&mut self, tile_pos: TilePos, tile: Tile) {
+        self.updates.insert(tile_pos, tile);
+    }
+
+    /// Gets the tile queued for a position.
+    pub fn get(&self, tile_pos: TilePos) -> Option<&Tile> {
+        self.updates.get(&tile_pos)
+    }
+
+    /// The number of positions with a queued tile.
+    pub fn len(&self) -> usize {
+        self.updates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.updates.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.updates.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use crate::test_util::{layer_settings, new_world, run_system, spawn_layer};
+    use bevy::prelude::*;
+
+    struct Remeshed(usize);
+
+    fn meshed(mut chunks: Query<&mut Chunk>) {
+        for mut chunk in chunks.iter_mut() {
+            chunk.needs_remesh = false;
+        }
+    }
+
+    // A 100x100 square spanning the four chunks in the bottom left of the layer.
+    fn apply_batch(
+        mut commands: Commands,
+        mut map_query: MapQuery,
+        mut tile_query: Query<&mut Tile>,
+    ) {
+        let mut batch = TileUpdateBatch::new();
+        for x in 0..100 {
+            for y in 0..100 {
+                let tile = Tile {
+                    texture_index: ((x + y) % 6) as u16,
+                    ..Default::default()
+                };
+                batch.set(TilePos(x, y), tile);
+            }
+        }
+        assert_eq!(batch.len(), 10_000);
+        let remeshed =
+            map_query.apply_tile_batch(&mut commands, &mut batch, &mut tile_query, 0u16, 0u16);
+        assert!(batch.is_empty());
+        commands.insert_resource(Remeshed(remeshed));
+    }
+
+    #[test]
+    fn batch_remeshes_each_chunk_once() {
+        let mut world = new_world();
+        let settings = layer_settings(MapSize(3, 3), ChunkSize(64, 64));
+        spawn_layer(&mut world, settings, Handle::default(), |layer_builder| {
+            // Some of the positions already have a tile.
+            layer_builder.fill(TilePos(0, 0), TilePos(10, 10), TileBundle::default());
+        });
+        run_system(&mut world, meshed.system());
+        let existing: Vec<Entity> = world
+            .query_filtered::<Entity, With<Tile>>()
+            .iter(&world)
+            .collect();
+        assert_eq!(existing.len(), 100);
+        run_system(&mut world, apply_batch.system());
+
+        assert_eq!(world.get_resource::<Remeshed>().unwrap().0, 4);
+        let mut remeshed: Vec<(u32, u32)> = world
+            .query::<&Chunk>()
+            .iter(&world)
+            .filter(|chunk| chunk.needs_remesh)
+            .map(|chunk| (chunk.position.0, chunk.position.1))
+            .collect();
+        remeshed.sort_unstable();
+        assert_eq!(remeshed, vec![(0, 0), (0, 1), (1, 0), (1, 1)]);
+
+        // Existing tiles are updated in place, the other positions get a new tile.
+        let tiles: Vec<(Entity, TilePos, u16)> = world
+            .query::<(Entity, &TilePos, &Tile)>()
+            .iter(&world)
+            .map(|(tile_entity, tile_pos, tile)| (tile_entity, *tile_pos, tile.texture_index))
+            .collect();
+        assert_eq!(tiles.len(), 10_000);
+        for (tile_entity, tile_pos, texture_index) in tiles {
+            assert_eq!(texture_index, ((tile_pos.0 + tile_pos.1) % 6) as u16);
+            if tile_pos.0 < 10 && tile_pos.1 < 10 {
+                assert!(existing.contains(&tile_entity));
+            }
+        }
+    }
+}
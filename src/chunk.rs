@@ -1,19 +1,27 @@
 use crate::{
+    chunk_batch::ChunkBatched,
+    chunk_material::apply_chunk_sub_meshes,
+    layer::{Layer, TilemapCrop, TilemapFog, TilemapSortKey},
+    mesher::{ChunkMeshes, MeshTileQuery},
     morton_index, morton_pos,
-    render::TilemapData,
+    render::{tint_to_vec4, TilemapData},
     round_to_power_of_two,
-    tile::{GPUAnimated, Tile},
-    ChunkPos, LayerSettings, LocalTilePos, TilePos, TilemapMeshType,
+    tile::{GPUAnimated, Tile, TileMaterial, TileParent},
+    ChunkPos, HexType, IsoType, LayerSettings, LocalTilePos, TilePos, TilemapMeshType,
+    TilemapSampling,
 };
 use bevy::{
+    math::Mat2,
     prelude::*,
     render::{
-        camera::{Camera, OrthographicProjection},
+        camera::{Camera, OrthographicProjection, RenderLayers},
         render_graph::base::{camera::CAMERA_2D, MainPass},
+        texture::FilterMode,
     },
-    tasks::AsyncComputeTaskPool,
+    tasks::{AsyncComputeTaskPool, Task},
 };
-use std::sync::Mutex;
+use futures_lite::future;
+use std::collections::HashMap;
 
 #[derive(Bundle)]
 pub(crate) struct ChunkBundle {
@@ -54,7 +62,7 @@ impl Default for ChunkBundle {
 pub struct Chunk {
     /// The specific location x,y of the chunk in the tile map in chunk coords.
     pub position: ChunkPos,
-    /// The map entity that parents the chunk.
+    /// The layer entity that parents the chunk.
     pub map_entity: Entity,
     /// Chunk specific settings.
     pub settings: LayerSettings,
@@ -62,6 +70,49 @@ pub struct Chunk {
     pub needs_remesh: bool,
     pub(crate) tiles: Vec<Option<Entity>>,
     pub(crate) mesh_handle: Handle<Mesh>,
+    pub(crate) shader_features: ChunkShaderFeatures,
+    /// The first vertex of each tile in the chunk mesh, indexed like `tiles`.
+    pub(crate) tile_vertices: Vec<Option<u32>>,
+    /// The material, entity and mesh of each sub-mesh drawing tiles with a `TileMaterial`.
+    pub(crate) sub_meshes: Vec<(Handle<ColorMaterial>, Entity, Handle<Mesh>)>,
+}
+
+/// Optional shader features used by the tiles of a chunk, each one is compiled into the chunk
+/// pipeline with a shader define only when it's needed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ChunkShaderFeatures {
+    /// A tile is GPU animated, `TILEMAP_ANIMATION`.
+    pub animation: bool,
+    /// A tile has a color other than white, `TILEMAP_TINT`.
+    pub tint: bool,
+    /// A tile is emissive, `TILEMAP_EMISSIVE`.
+    pub emissive: bool,
+    /// Tiles were merged into larger quads, `TILEMAP_MERGED`.
+    pub merged: bool,
+    /// A tile has an angle, `TILEMAP_ROTATION`.
+    pub rotation: bool,
+}
+
+impl ChunkShaderFeatures {
+    pub(crate) fn shader_defs(&self) -> Vec<String> {
+        let mut shader_defs = Vec::new();
+        if self.animation {
+            shader_defs.push("TILEMAP_ANIMATION".to_string());
+        }
+        if self.tint {
+            shader_defs.push("TILEMAP_TINT".to_string());
+        }
+        if self.emissive {
+            shader_defs.push("TILEMAP_EMISSIVE".to_string());
+        }
+        if self.merged {
+            shader_defs.push("TILEMAP_MERGED".to_string());
+        }
+        if self.rotation {
+            shader_defs.push("TILEMAP_ROTATION".to_string());
+        }
+        shader_defs
+    }
 }
 
 impl Default for Chunk {
@@ -73,6 +124,9 @@ impl Default for Chunk {
             position: Default::default(),
             settings: Default::default(),
             tiles: Vec::new(),
+            shader_features: ChunkShaderFeatures::default(),
+            tile_vertices: Vec::new(),
+            sub_meshes: Vec::new(),
         }
     }
 }
@@ -96,6 +150,9 @@ impl Chunk {
             position,
             settings: layer_settings,
             tiles,
+            shader_features: ChunkShaderFeatures::default(),
+            tile_vertices: Vec::new(),
+            sub_meshes: Vec::new(),
         }
     }
 
@@ -125,6 +182,27 @@ impl Chunk {
         None
     }
 
+    /// Iterates over the tiles of the chunk and their positions in the chunk, in the order the
+    /// tiles are stored in.
+    ///
+    /// Tiles are stored in Morton order (Z-order), the index of a tile interleaves the bits of
+    /// its position with y in the even bits and x in the odd bits, so the order starts with:
+    /// (0, 0), (0, 1), (1, 0), (1, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 0), ...
+    /// Tiles which are close to each other in the chunk are close to each other in this order,
+    /// which keeps algorithms that visit neighboring tiles, like lighting or path finding,
+    /// cache friendly.
+    pub fn iter_tile_entities(&self) -> impl Iterator<Item = (LocalTilePos, Entity)> + '_ {
+        self.tiles
+            .iter()
+            .enumerate()
+            .filter_map(|(index, tile_entity)| {
+                let tile_pos = morton_pos(index);
+                Some((LocalTilePos(tile_pos.x, tile_pos.y), (*tile_entity)?))
+            })
+    }
+
+    /// Calls `f` with every position in the chunk and its tile entity, in the same order as
+    /// `iter_tile_entities`.
     pub fn for_each_tile_entity<F>(&self, mut f: F)
     where
         F: FnMut((TilePos, &Option<Entity>)),
@@ -135,6 +213,61 @@ impl Chunk {
         });
     }
 
+    /// Returns the bounds (min, max) of the chunk's mesh relative to the chunk's transform.
+    /// The bounds are conservative and `None` is returned for mesh types whose
+    /// extent isn't known ahead of time.
+    pub(crate) fn get_local_bounds(&self) -> Option<(Vec2, Vec2)> {
+        let chunk_size: Vec2 = self.settings.chunk_size.into();
+        let tile_size: Vec2 = self.settings.tile_size.into();
+        let grid_size = self.settings.grid_size;
+        let size = chunk_size * tile_size;
+
+        let bounds = match self.settings.mesh_type {
+            TilemapMeshType::Square => {
+                let quad_size = self.settings.get_quad_size();
+                let mut offset = self.settings.tile_offset;
+                // Rotated tiles reach past their cell by up to half their diagonal.
+                let mut overhang = Vec2::ZERO;
+                if self.shader_features.rotation {
+                    overhang = Vec2::splat(quad_size.length() * 0.5) - quad_size * 0.5;
+                    offset -= overhang;
+                }
+                (
+                    offset,
+                    (chunk_size - Vec2::ONE) * grid_size + quad_size + offset + overhang * 2.0,
+                )
+            }
+            TilemapMeshType::Hexagon(HexType::Row) => (
+                Vec2::ZERO,
+                Vec2::new(size.x + chunk_size.y * tile_size.x * 0.5, size.y),
+            ),
+            TilemapMeshType::Hexagon(HexType::RowEven)
+            | TilemapMeshType::Hexagon(HexType::RowOdd) => (
+                Vec2::new(-tile_size.x * 0.25, 0.0),
+                Vec2::new(size.x + tile_size.x * 0.25, size.y),
+            ),
+            TilemapMeshType::Hexagon(HexType::Column) => (
+                Vec2::ZERO,
+                Vec2::new(size.x, size.y + chunk_size.x * tile_size.y * 0.5),
+            ),
+            TilemapMeshType::Hexagon(HexType::ColumnEven)
+            | TilemapMeshType::Hexagon(HexType::ColumnOdd) => (
+                Vec2::new(0.0, -tile_size.y * 0.25),
+                Vec2::new(size.x, size.y + tile_size.y * 0.25),
+            ),
+            TilemapMeshType::Isometric(IsoType::Diamond) => (
+                Vec2::new(
+                    -chunk_size.y * grid_size.x * 0.5 - tile_size.x * 0.5,
+                    -(chunk_size.x + chunk_size.y) * grid_size.y * 0.5 - tile_size.y,
+                ),
+                Vec2::new(chunk_size.x * grid_size.x * 0.5 + tile_size.x * 0.5, 0.0),
+            ),
+            TilemapMeshType::Isometric(IsoType::Staggered) => return None,
+        };
+
+        Some(bounds)
+    }
+
     /// Returns the local coordinates of a tile
     ///
     /// Coordinates are relative to the origin of the chunk that this method is called on
@@ -146,100 +279,668 @@ impl Chunk {
     }
 }
 
+/// The chunk mesh being built on the `AsyncComputeTaskPool`.
+pub(crate) struct ChunkMeshTask(Task<ChunkMeshes>);
+
+type ChangedChunkQuery<'a> = Query<
+    'a,
+    (
+        Entity,
+        &'static mut Chunk,
+        &'static Visible,
+        Option<&'static ChunkBatched>,
+    ),
+    (
+        Or<(Changed<Visible>, Changed<Chunk>, Changed<ChunkBatched>)>,
+        Without<ChunkMeshTask>,
+    ),
+>;
+
+// Starts building the meshes of changed chunks in the background.
+// Chunks changed while their mesh is being built are remeshed again once it is done.
 pub(crate) fn update_chunk_mesh(
+    mut commands: Commands,
     task_pool: Res<AsyncComputeTaskPool>,
-    meshes: ResMut<Assets<Mesh>>,
-    tile_query: Query<(&TilePos, &Tile, Option<&GPUAnimated>)>,
-    mut changed_chunks: Query<(&mut Chunk, &Visible), Or<(Changed<Visible>, Changed<Chunk>)>>,
+    tile_query: MeshTileQuery,
+    mut changed_chunks: ChangedChunkQuery,
 ) {
-    let threaded_meshes = Mutex::new(meshes);
-
-    changed_chunks.par_for_each_mut(&task_pool, 5, |(mut chunk, visible)| {
-        if visible.is_visible && chunk.needs_remesh {
+    for (chunk_entity, mut chunk, visible, batched) in changed_chunks.iter_mut() {
+        // Batched chunks are hidden, their meshes are still drawn by the batch.
+        let is_visible = batched.map_or(visible.is_visible, |batched| batched.is_visible);
+        if is_visible && chunk.needs_remesh {
             log::trace!(
                 "Re-meshing chunk at: {:?} layer id of: {}",
                 chunk.position,
                 chunk.settings.layer_id
             );
 
-            let mut meshes = threaded_meshes.lock().unwrap();
-            chunk
-                .settings
-                .mesher
-                .mesh(&chunk, &chunk.tiles, &tile_query, &mut meshes);
+            let mesher = chunk.settings.mesher;
+            let tile_count = chunk.tiles.len();
+            let settings = chunk.settings;
+            let tiles = mesher.collect_tiles(&chunk, &tile_query);
+            let task = task_pool
+                .spawn(async move { mesher.build_with_materials(tile_count, tiles, &settings) });
+            commands.entity(chunk_entity).insert(ChunkMeshTask(task));
 
             chunk.needs_remesh = false;
         }
-    });
+    }
+}
+
+/// An event sent when the mesh of a chunk has been built and written to its mesh asset, which is
+/// uploaded to the GPU when the frame is rendered.
+/// It's sent once for every finished mesh, so a map is ready to be shown once an event has been
+/// received for each of its chunks.
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkMeshed {
+    /// The chunk entity.
+    pub chunk_entity: Entity,
+    /// The position of the chunk in the layer.
+    pub position: ChunkPos,
+    /// The map id of the chunk's layer.
+    pub map_id: u16,
+    /// The layer id of the chunk's layer.
+    pub layer_id: u16,
+}
+
+// Applies the chunk meshes which finished building.
+pub(crate) fn apply_chunk_meshes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    materials: Res<Assets<ColorMaterial>>,
+    mut chunk_meshed_events: EventWriter<ChunkMeshed>,
+    mut chunk_query: Query<(Entity, &mut Chunk, &mut ChunkMeshTask)>,
+) {
+    for (chunk_entity, mut chunk, mut task) in chunk_query.iter_mut() {
+        if let Some(chunk_meshes) = future::block_on(future::poll_once(&mut task.0)) {
+            if let Some(mesh) = meshes.get_mut(&chunk.mesh_handle) {
+                let (shader_features, tile_vertices) = chunk_meshes.chunk.apply(mesh);
+                chunk.shader_features = shader_features;
+                chunk.tile_vertices = tile_vertices;
+                apply_chunk_sub_meshes(
+                    &mut commands,
+                    &mut meshes,
+                    &materials,
+                    chunk_entity,
+                    &mut chunk,
+                    chunk_meshes.sub_meshes,
+                );
+                chunk_meshed_events.send(ChunkMeshed {
+                    chunk_entity,
+                    position: chunk.position,
+                    map_id: chunk.settings.map_id,
+                    layer_id: chunk.settings.layer_id,
+                });
+            }
+            commands.entity(chunk_entity).remove::<ChunkMeshTask>();
+        }
+    }
+}
+
+type ChangedTileQuery<'a> = Query<
+    'a,
+    (
+        &'static TilePos,
+        &'static Tile,
+        &'static TileParent,
+        Option<&'static GPUAnimated>,
+    ),
+    Changed<Tile>,
+>;
+
+// Flags the chunks of changed tiles so only those chunks get remeshed.
+// Tiles which were only shown or hidden are updated in the existing mesh instead.
+pub(crate) fn update_chunk_for_changed_tiles(
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut chunk_query: Query<(&mut Chunk, Option<&ChunkMeshTask>)>,
+    tile_query: ChangedTileQuery,
+    changed_materials: Query<&TileParent, Changed<TileMaterial>>,
+) {
+    for tile_parent in changed_materials.iter() {
+        if let Ok((mut chunk, _)) = chunk_query.get_mut(tile_parent.chunk) {
+            chunk.needs_remesh = true;
+        }
+    }
+
+    for (tile_pos, tile, tile_parent, gpu_animated) in tile_query.iter() {
+        if let Ok((mut chunk, mesh_task)) = chunk_query.get_mut(tile_parent.chunk) {
+            if chunk.needs_remesh {
+                continue;
+            }
+
+            // The mesh being built would overwrite an update made to the current one.
+            if mesh_task.is_some() {
+                chunk.needs_remesh = true;
+                continue;
+            }
+
+            let tile_index = morton_index(chunk.to_chunk_pos(*tile_pos));
+            if let Some(vertex) = chunk.tile_vertices.get(tile_index).copied().flatten() {
+                if let Some(mesh) = meshes.get_mut(&chunk.mesh_handle) {
+                    if chunk.settings.mesher.set_tile_visibility(
+                        mesh,
+                        vertex,
+                        tile,
+                        gpu_animated,
+                        chunk.settings.get_quad_size(),
+                    ) {
+                        continue;
+                    }
+                }
+            }
+
+            chunk.needs_remesh = true;
+        }
+    }
 }
 
+type LayerVisibleQuery<'a> = Query<
+    'a,
+    (Option<&'static Visible>, Option<&'static TilemapCrop>),
+    (With<Layer>, Without<Chunk>),
+>;
+
+type ChunkVisibleQuery<'a> = Query<
+    'a,
+    (
+        &'static GlobalTransform,
+        &'static Chunk,
+        &'static TilemapData,
+        &'static mut Visible,
+        Option<&'static mut ChunkBatched>,
+    ),
+>;
+
+// Culls the chunks outside of the camera and hides the chunks of layers with a hidden `Visible`
+// or outside of the layer's crop rectangle. Batched chunks are always hidden, the visibility
+// they would have is kept in `ChunkBatched` for their batch.
 pub(crate) fn update_chunk_visibility(
     camera: Query<(&Camera, &OrthographicProjection, &Transform)>,
-    mut chunks: Query<(&GlobalTransform, &Chunk, &mut Visible)>,
+    layer_query: LayerVisibleQuery,
+    mut chunks: ChunkVisibleQuery,
+) {
+    let camera_bounds = camera
+        .iter()
+        .find(|data| {
+            if let Some(name) = &data.0.name {
+                name == CAMERA_2D
+            } else {
+                false
+            }
+        })
+        .map(|(_current_camera, ortho, camera_transform)| {
+            // Transform camera into world space.
+            let camera_min = camera_transform.translation.truncate()
+                + Vec2::new(ortho.left, ortho.bottom)
+                    * ortho.scale
+                    * camera_transform.scale.truncate();
+            let camera_max = camera_transform.translation.truncate()
+                + Vec2::new(ortho.right, ortho.top)
+                    * ortho.scale
+                    * camera_transform.scale.truncate();
+            (camera_min, camera_max)
+        });
+
+    for (global_transform, chunk, data, mut visible, batched) in chunks.iter_mut() {
+        // The chunk's `map_entity` is the layer it was built in.
+        let layer_visible = match layer_query.get(chunk.map_entity) {
+            Ok((None, None)) | Err(_) => None,
+            Ok((layer_visible, crop)) => Some(
+                layer_visible.map_or(true, |layer_visible| layer_visible.is_visible)
+                    && crop.map_or(true, |crop| crop.overlaps_chunk(chunk)),
+            ),
+        };
+
+        let overlaps_camera = match camera_bounds {
+            // Scrolled tiles can end up anywhere in the layer.
+            Some(_) if chunk.settings.cull && data.scroll != Vec2::ZERO => Some(true),
+            // Chunks without known bounds aren't culled, their layer can still hide them.
+            Some((camera_min, camera_max)) if chunk.settings.cull => {
+                chunk.get_local_bounds().map(|(local_min, local_max)| {
+                    // Transform the chunk bounds into world space.
+                    let translation = global_transform.translation.truncate();
+                    let scale = global_transform.scale.truncate();
+                    let corner_a = translation + local_min * scale;
+                    let corner_b = translation + local_max * scale;
+                    let bounds_min = corner_a.min(corner_b);
+                    let bounds_max = corner_a.max(corner_b);
+
+                    bounds_max.x >= camera_min.x
+                        && bounds_min.x <= camera_max.x
+                        && bounds_max.y >= camera_min.y
+                        && bounds_min.y <= camera_max.y
+                })
+            }
+            _ => None,
+        };
+
+        // Chunks which are neither culled nor in a layer with a `Visible` or `TilemapCrop` are
+        // left alone.
+        let is_visible = match (layer_visible, overlaps_camera) {
+            (None, None) => None,
+            (layer_visible, overlaps_camera) => {
+                Some(layer_visible.unwrap_or(true) && overlaps_camera.unwrap_or(true))
+            }
+        };
+
+        if let Some(mut batched) = batched {
+            if let Some(is_visible) = is_visible {
+                if batched.is_visible != is_visible {
+                    batched.is_visible = is_visible;
+                }
+            }
+            if visible.is_visible {
+                visible.is_visible = false;
+            }
+            continue;
+        }
+        let is_visible = match is_visible {
+            Some(is_visible) => is_visible,
+            None => continue,
+        };
+
+        if is_visible && !visible.is_visible {
+            log::trace!("Showing chunk @: {:?}", chunk.position);
+            visible.is_visible = true;
+        } else if !is_visible && visible.is_visible {
+            log::trace!("Hiding chunk @: {:?}", chunk.position);
+            visible.is_visible = false;
+        }
+    }
+}
+
+type AddedOrRematerializedChunkQuery<'a> =
+    Query<'a, (), Or<(Added<Chunk>, Changed<Handle<ColorMaterial>>)>>;
+
+type SampledChunkQuery<'a> = Query<
+    'a,
+    (
+        Entity,
+        &'static Chunk,
+        &'static Handle<ColorMaterial>,
+        Option<&'static ChunkSampledMaterial>,
+    ),
+>;
+
+// The copies of materials whose texture has the sampler of a `TilemapSampling`, so the original
+// texture keeps its own sampler. The handles are weak, copies no chunk uses are dropped.
+#[derive(Default)]
+pub(crate) struct SampledMaterials(
+    HashMap<(Handle<ColorMaterial>, TilemapSampling), Handle<ColorMaterial>>,
+);
+
+// The material a chunk was given, while it's drawn with a sampled copy of it.
+pub(crate) struct ChunkSampledMaterial {
+    source: Handle<ColorMaterial>,
+    sampled: Handle<ColorMaterial>,
+}
+
+// Draws the chunks of layers with a `TilemapSampling` with a copy of their material and texture
+// which has the layer's sampler, once the texture is loaded or swapped out, or when the sampling
+// of the layer changes. The chunk pipelines stay the same.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn update_chunk_texture_sampling(
+    mut commands: Commands,
+    mut texture_events: EventReader<AssetEvent<Texture>>,
+    mut textures: ResMut<Assets<Texture>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut sampled_materials: ResMut<SampledMaterials>,
+    layer_query: Query<&Layer>,
+    changed_layers: Query<(), (With<Layer>, Changed<Layer>)>,
+    chunk_query: SampledChunkQuery,
+    changed_chunks: AddedOrRematerializedChunkQuery,
 ) {
-    if let Some((_current_camera, ortho, camera_transform)) = camera.iter().find(|data| {
-        if let Some(name) = &data.0.name {
-            name == CAMERA_2D
-        } else {
-            false
-        }
-    }) {
-        // Transform camera into world space.
-        let left = camera_transform.translation.x + (ortho.left * ortho.scale * camera_transform.scale.x);
-        let right = camera_transform.translation.x + (ortho.right * ortho.scale * camera_transform.scale.x);
-        let bottom = camera_transform.translation.y + (ortho.bottom * ortho.scale * camera_transform.scale.y);
-        let top = camera_transform.translation.y + (ortho.top * ortho.scale * camera_transform.scale.y);
-
-        let camera_bounds = Vec4::new(left, right, bottom, top);
-
-        for (global_transform, chunk, mut visible) in chunks.iter_mut() {
-            if chunk.settings.mesh_type != TilemapMeshType::Square || !chunk.settings.cull {
+    let changed_textures: Vec<Handle<Texture>> = texture_events
+        .iter()
+        .filter_map(|event| match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                Some(handle.clone_weak())
+            }
+            AssetEvent::Removed { .. } => None,
+        })
+        .collect();
+    if changed_textures.is_empty()
+        && changed_chunks.iter().next().is_none()
+        && changed_layers.iter().next().is_none()
+    {
+        return;
+    }
+
+    // Copies of reloaded textures are copied again.
+    for ((source, sampling), sampled) in sampled_materials.0.iter() {
+        let source_texture = match materials
+            .get(source)
+            .and_then(|material| material.texture.clone())
+        {
+            Some(texture) if changed_textures.contains(&texture) => texture,
+            _ => continue,
+        };
+        let sampled_texture = match materials
+            .get(sampled)
+            .and_then(|material| material.texture.clone())
+        {
+            Some(texture) => texture,
+            None => continue,
+        };
+        if let Some(texture) = textures.get(source_texture) {
+            let texture = sampled_texture_of(texture, *sampling);
+            textures.set_untracked(sampled_texture, texture);
+        }
+    }
+
+    for (chunk_entity, chunk, material_handle, sampled_material) in chunk_query.iter() {
+        // Materials swapped in by the user replace the one the copy was made of.
+        let source = match sampled_material {
+            Some(sampled_material) if sampled_material.sampled == *material_handle => {
+                sampled_material.source.clone()
+            }
+            _ => material_handle.clone(),
+        };
+        // The chunk's `map_entity` is the layer it was built in.
+        let sampling = layer_query
+            .get(chunk.map_entity)
+            .map_or(chunk.settings.sampling, |layer| layer.settings.sampling);
+        let sampling = match sampling {
+            Some(sampling) => sampling,
+            None => {
+                if sampled_material.is_some() {
+                    commands
+                        .entity(chunk_entity)
+                        .insert(source)
+                        .remove::<ChunkSampledMaterial>();
+                }
                 continue;
             }
+        };
 
-            let bounds_size = Vec2::new(
-                chunk.settings.chunk_size.0 as f32 * chunk.settings.tile_size.0,
-                chunk.settings.chunk_size.1 as f32 * chunk.settings.tile_size.1,
-            );
+        let key = (source.clone_weak(), sampling);
+        let sampled = match sampled_materials.0.get(&key) {
+            Some(sampled) if materials.get(sampled).is_some() => sampled.clone_weak(),
+            _ => {
+                let material = match materials.get(&source) {
+                    Some(material) => material,
+                    None => continue,
+                };
+                let texture = match material
+                    .texture
+                    .as_ref()
+                    .and_then(|texture_handle| textures.get(texture_handle))
+                {
+                    Some(texture) => sampled_texture_of(texture, sampling),
+                    None => continue,
+                };
+                let material = ColorMaterial {
+                    color: material.color,
+                    texture: Some(textures.add(texture)),
+                };
+                let sampled = materials.add(material);
+                sampled_materials.0.insert(key, sampled.clone_weak());
+                sampled
+            }
+        };
+        if sampled != *material_handle {
+            let sampled = materials.get_handle(&sampled);
+            commands
+                .entity(chunk_entity)
+                .insert(sampled.clone())
+                .insert(ChunkSampledMaterial { source, sampled });
+        }
+    }
+}
 
-            let bounds = Vec4::new(
-                global_transform.translation.x,
-                global_transform.translation.x + bounds_size.x,
-                global_transform.translation.y,
-                global_transform.translation.y + bounds_size.y,
-            );
+// A copy of `texture` with the sampler of `sampling`.
+fn sampled_texture_of(texture: &Texture, sampling: TilemapSampling) -> Texture {
+    let filter_mode = match sampling {
+        TilemapSampling::Nearest => FilterMode::Nearest,
+        TilemapSampling::Linear => FilterMode::Linear,
+    };
+    let mut texture = texture.clone();
+    texture.sampler.mag_filter = filter_mode;
+    texture.sampler.min_filter = filter_mode;
+    texture.sampler.mipmap_filter = filter_mode;
+    texture
+}
 
-            let padded_camera_bounds = Vec4::new(
-                camera_bounds.x - (bounds_size.x),
-                camera_bounds.y + (bounds_size.x),
-                camera_bounds.z - (bounds_size.y),
-                camera_bounds.w + (bounds_size.y),
-            );
+// Pushes layer alpha and tint changes to the chunk uniforms so fading or tinting a layer doesn't
+// remesh it.
+pub(crate) fn update_chunk_color(
+    layer_query: Query<&Layer, Changed<Layer>>,
+    mut chunk_query: Query<(&mut Chunk, &mut TilemapData)>,
+) {
+    for layer in layer_query.iter() {
+        for chunk_entity in layer.chunks.iter().flatten() {
+            if let Ok((mut chunk, mut data)) = chunk_query.get_mut(*chunk_entity) {
+                if (chunk.settings.alpha - layer.settings.alpha).abs() > f32::EPSILON {
+                    chunk.settings.alpha = layer.settings.alpha;
+                    data.alpha = layer.settings.alpha;
+                }
+                if chunk.settings.tint != layer.settings.tint {
+                    chunk.settings.tint = layer.settings.tint;
+                    data.tint = tint_to_vec4(layer.settings.tint);
+                }
+            }
+        }
+    }
+}
 
-            if (bounds.x >= padded_camera_bounds.x) && (bounds.y <= padded_camera_bounds.y) {
-                if (bounds.z < padded_camera_bounds.z) || (bounds.w > padded_camera_bounds.w) {
-                    if visible.is_visible {
-                        log::trace!("Hiding chunk @: {:?}", bounds);
-                        visible.is_visible = false;
-                    }
-                } else {
-                    if !visible.is_visible {
-                        log::trace!("Showing chunk @: {:?}", bounds);
-                        visible.is_visible = true;
+// Pushes layer scroll speed changes to the chunks and scrolls the chunk uniforms, wrapping
+// around at the size of the layer.
+pub(crate) fn update_chunk_scroll(
+    time: Res<Time>,
+    layer_query: Query<&Layer, Changed<Layer>>,
+    mut chunk_query: Query<(&mut Chunk, &mut TilemapData)>,
+) {
+    for layer in layer_query.iter() {
+        for chunk_entity in layer.chunks.iter().flatten() {
+            if let Ok((mut chunk, _)) = chunk_query.get_mut(*chunk_entity) {
+                if chunk.settings.scroll_speed != layer.settings.scroll_speed {
+                    let merged = chunk.settings.merges_tiles();
+                    chunk.settings.scroll_speed = layer.settings.scroll_speed;
+                    // Scrolled layers aren't merged.
+                    if chunk.settings.merges_tiles() != merged {
+                        chunk.needs_remesh = true;
                     }
                 }
-            } else {
-                if visible.is_visible {
-                    log::trace!(
-                        "Hiding chunk @: {:?}, with camera_bounds: {:?}, bounds_size: {:?}",
-                        bounds,
-                        padded_camera_bounds,
-                        bounds_size
-                    );
-                    visible.is_visible = false;
+            }
+        }
+    }
+
+    let delta = time.delta_seconds();
+    for (chunk, mut data) in chunk_query.iter_mut() {
+        let scroll_speed = chunk.settings.scroll_speed;
+        if scroll_speed == Vec2::ZERO || data.map_size.min_element() <= 0.0 {
+            continue;
+        }
+        let scroll = data.scroll + scroll_speed * delta;
+        data.scroll = Vec2::new(
+            scroll.x.rem_euclid(data.map_size.x),
+            scroll.y.rem_euclid(data.map_size.y),
+        );
+    }
+}
+
+type LayerRenderLayersQuery<'a> =
+    Query<'a, (&'static Layer, &'static RenderLayers), Changed<RenderLayers>>;
+
+// Copies the `RenderLayers` of layers to their chunks, so a layer is only drawn by the cameras
+// on the same render layers.
+pub(crate) fn update_chunk_render_layers(
+    mut commands: Commands,
+    changed_layers: LayerRenderLayersQuery,
+    removed_render_layers: RemovedComponents<RenderLayers>,
+    layer_query: Query<&Layer>,
+) {
+    for (layer, render_layers) in changed_layers.iter() {
+        for chunk_entity in layer.chunks.iter().flatten() {
+            commands.entity(*chunk_entity).insert(*render_layers);
+        }
+    }
+
+    for layer_entity in removed_render_layers.iter() {
+        if let Ok(layer) = layer_query.get(layer_entity) {
+            for chunk_entity in layer.chunks.iter().flatten() {
+                commands.entity(*chunk_entity).remove::<RenderLayers>();
+            }
+        }
+    }
+}
+
+// Passes the fog of layers to the uniforms of their chunks, removed fog is cleared.
+pub(crate) fn update_chunk_fog(
+    changed_layers: Query<(&Layer, &TilemapFog), Changed<TilemapFog>>,
+    removed_fog: RemovedComponents<TilemapFog>,
+    layer_query: Query<&Layer>,
+    mut chunk_query: Query<&mut TilemapData>,
+) {
+    let mut set_fog = |layer: &Layer, fog: Option<&TilemapFog>| {
+        for chunk_entity in layer.chunks.iter().flatten() {
+            if let Ok(mut data) = chunk_query.get_mut(*chunk_entity) {
+                // A transparent fog color leaves the tiles as they are.
+                let (color, fog) = match fog {
+                    Some(fog) => (
+                        Vec4::new(fog.color.r(), fog.color.g(), fog.color.b(), fog.color.a()),
+                        *fog,
+                    ),
+                    None => (Vec4::ZERO, TilemapFog::default()),
+                };
+                data.fog_color = color;
+                data.fog_center = fog.center;
+                data.fog_radius = fog.radius;
+                data.fog_falloff = fog.falloff;
+            }
+        }
+    };
+
+    for (layer, fog) in changed_layers.iter() {
+        set_fog(layer, Some(fog));
+    }
+    for layer_entity in removed_fog.iter() {
+        if let Ok(layer) = layer_query.get(layer_entity) {
+            set_fog(layer, None);
+        }
+    }
+}
+
+type ChangedSortKeyQuery<'a> = Query<
+    'a,
+    (&'static Layer, &'static TilemapSortKey),
+    Or<(Changed<TilemapSortKey>, Changed<Layer>)>,
+>;
+
+// Moves the chunks of layers to the depths from their sort key, removed sort keys restore the
+// default chunk depths.
+pub(crate) fn update_chunk_sort_key(
+    changed_layers: ChangedSortKeyQuery,
+    removed_sort_keys: RemovedComponents<TilemapSortKey>,
+    layer_query: Query<&Layer>,
+    mut chunk_query: Query<(&Chunk, &mut Transform)>,
+) {
+    let mut set_depths = |layer: &Layer, sort_key: Option<&TilemapSortKey>| {
+        for chunk_entity in layer.chunks.iter().flatten() {
+            if let Ok((chunk, mut transform)) = chunk_query.get_mut(*chunk_entity) {
+                let depth = match sort_key {
+                    Some(sort_key) => (sort_key.0)(chunk),
+                    None => layer.settings.get_chunk_depth(chunk.position),
+                };
+                // Only touch the transform when the depth changed, so it isn't propagated again.
+                if transform.translation.z.to_bits() != depth.to_bits() {
+                    transform.translation.z = depth;
                 }
             }
         }
+    };
+
+    for (layer, sort_key) in changed_layers.iter() {
+        set_depths(layer, Some(sort_key));
+    }
+    for layer_entity in removed_sort_keys.iter() {
+        if let Ok(layer) = layer_query.get(layer_entity) {
+            set_depths(layer, None);
+        }
+    }
+}
+
+type WrapLayerQuery<'a> = Query<'a, (&'static Layer, &'static GlobalTransform), Without<Chunk>>;
+
+// Moves the chunks of wrapping layers to the copy of the layer closest to the 2d camera. The
+// global transform is updated as well, so culling sees the chunks where they are drawn.
+pub(crate) fn update_chunk_wrap(
+    camera: Query<(&Camera, &Transform)>,
+    layer_query: WrapLayerQuery,
+    mut chunk_query: Query<(&Chunk, &mut Transform, &mut GlobalTransform), Without<Camera>>,
+) {
+    let camera_pos = camera
+        .iter()
+        .find(|(camera, _)| camera.name.as_deref() == Some(CAMERA_2D))
+        .map(|(_, camera_transform)| camera_transform.translation);
+    let camera_pos = if let Some(camera_pos) = camera_pos {
+        camera_pos
+    } else {
+        return;
+    };
+
+    for (layer, layer_transform) in layer_query.iter() {
+        let settings = &layer.settings;
+        if !settings.wrap {
+            continue;
+        }
+
+        // The offsets between copies of the layer, along the chunk axes.
+        let origin = settings.get_chunk_origin(ChunkPos(0, 0));
+        let wrap_x = settings.get_chunk_origin(ChunkPos(settings.map_size.0, 0)) - origin;
+        let wrap_y = settings.get_chunk_origin(ChunkPos(0, settings.map_size.1)) - origin;
+        let to_copies = Mat2::from_cols(wrap_x, wrap_y).inverse();
+        let camera_pos = layer_transform
+            .compute_matrix()
+            .inverse()
+            .transform_point3(camera_pos)
+            .truncate();
+
+        for chunk_entity in layer.chunks.iter().flatten() {
+            let (chunk, mut transform, mut global_transform) =
+                if let Ok(chunk) = chunk_query.get_mut(*chunk_entity) {
+                    chunk
+                } else {
+                    continue;
+                };
+
+            let base = settings.get_chunk_origin(chunk.position) - settings.get_anchor_offset();
+            let center = chunk
+                .get_local_bounds()
+                .map_or(base, |(min, max)| base + (min + max) * 0.5);
+            let copy = (to_copies * (camera_pos - center)).round();
+            if !copy.is_finite() {
+                continue;
+            }
+
+            let translation = base + wrap_x * copy.x + wrap_y * copy.y;
+            // Only touch the transform when the chunk moved, so it isn't propagated again.
+            if transform.translation.truncate() != translation {
+                transform.translation.x = translation.x;
+                transform.translation.y = translation.y;
+                *global_transform = layer_transform.mul_transform(*transform);
+            }
+        }
+    }
+}
+
+// Bevy clears the shader defs after every frame, so they are added again each frame.
+pub(crate) fn update_chunk_shader_defs(mut chunk_query: Query<(&Chunk, &mut RenderPipelines)>) {
+    for (chunk, mut render_pipelines) in chunk_query.iter_mut() {
+        let mut shader_defs = chunk.shader_features.shader_defs();
+        if let Some(shader_def) = chunk.settings.blend_mode.shader_def() {
+            shader_defs.push(shader_def.to_string());
+        }
+        if shader_defs.is_empty() {
+            continue;
+        }
+        for render_pipeline in render_pipelines.pipelines.iter_mut() {
+            render_pipeline
+                .specialization
+                .shader_specialization
+                .shader_defs
+                .extend(shader_defs.iter().cloned());
+        }
     }
 }
 
@@ -248,3 +949,265 @@ pub(crate) fn update_chunk_time(time: Res<Time>, mut query: Query<&mut TilemapDa
         data.time = time.seconds_since_startup() as f32;
     }
 }
+
+// Passes the size of the primary window to the chunks for pixel snapping.
+pub(crate) fn update_chunk_viewport_size(
+    windows: Option<Res<Windows>>,
+    mut query: Query<&mut TilemapData>,
+) {
+    let viewport_size = windows
+        .and_then(|windows| {
+            windows.get_primary().map(|window| {
+                Vec2::new(
+                    window.physical_width() as f32,
+                    window.physical_height() as f32,
+                )
+            })
+        })
+        .unwrap_or(Vec2::ZERO);
+    for mut data in query.iter_mut() {
+        if data.viewport_size != viewport_size {
+            data.viewport_size = viewport_size;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use crate::test_util::{layer_settings, new_world, run_system, spawn_layer};
+    use bevy::{
+        app::Events,
+        ecs::schedule::{Schedule, SystemStage},
+    };
+
+    // Builds a layer of 2x2 chunks with every tile set and returns the layer entity.
+    fn build_layer(mesh_type: TilemapMeshType) -> (World, Entity) {
+        let mut world = new_world();
+        let mut settings = layer_settings(MapSize(2, 2), ChunkSize(4, 4));
+        settings.mesh_type = mesh_type;
+        let layer_entity = spawn_layer(&mut world, settings, Handle::default(), |layer_builder| {
+            layer_builder.set_all(Tile::default().into());
+        });
+        (world, layer_entity)
+    }
+
+    fn chunks_of(world: &mut World, layer_entity: Entity) -> Vec<(Chunk, bool)> {
+        world
+            .query::<(&Chunk, &Visible)>()
+            .iter(world)
+            .filter(|(chunk, _)| chunk.map_entity == layer_entity)
+            .map(|(chunk, visible)| (chunk.clone(), visible.is_visible))
+            .collect()
+    }
+
+    #[test]
+    fn hidden_staggered_layer_hides_its_chunks() {
+        let (mut world, layer_entity) = build_layer(TilemapMeshType::Isometric(IsoType::Staggered));
+        world
+            .spawn()
+            .insert_bundle(OrthographicCameraBundle::new_2d());
+        world.entity_mut(layer_entity).insert(Visible {
+            is_visible: false,
+            is_transparent: true,
+        });
+
+        let chunks = chunks_of(&mut world, layer_entity);
+        assert_eq!(chunks.len(), 4);
+        assert!(chunks
+            .iter()
+            .all(|(chunk, _)| chunk.get_local_bounds().is_none()));
+
+        run_system(&mut world, update_chunk_visibility.system());
+        assert!(chunks_of(&mut world, layer_entity)
+            .iter()
+            .all(|(_, is_visible)| !is_visible));
+
+        // Showing the layer shows the chunks again, they are never culled.
+        world.get_mut::<Visible>(layer_entity).unwrap().is_visible = true;
+        run_system(&mut world, update_chunk_visibility.system());
+        assert!(chunks_of(&mut world, layer_entity)
+            .iter()
+            .all(|(_, is_visible)| *is_visible));
+    }
+
+    #[test]
+    fn tile_entities_iterate_in_morton_order() {
+        let (mut world, layer_entity) = build_layer(TilemapMeshType::Square);
+        let chunk = chunks_of(&mut world, layer_entity)
+            .into_iter()
+            .map(|(chunk, _)| chunk)
+            .find(|chunk| chunk.position == ChunkPos(1, 1))
+            .unwrap();
+
+        let tile_entities: Vec<(LocalTilePos, Entity)> = chunk.iter_tile_entities().collect();
+        assert_eq!(tile_entities.len(), 16);
+        let positions: Vec<(u32, u32)> = tile_entities
+            .iter()
+            .map(|(local_pos, _)| (local_pos.0, local_pos.1))
+            .collect();
+        assert_eq!(
+            positions[..9],
+            [
+                (0, 0),
+                (0, 1),
+                (1, 0),
+                (1, 1),
+                (0, 2),
+                (0, 3),
+                (1, 2),
+                (1, 3),
+                (2, 0)
+            ]
+        );
+        // Each position is the one of the tile entity it comes with.
+        for (local_pos, tile_entity) in tile_entities {
+            let tile_pos = *world.get::<TilePos>(tile_entity).unwrap();
+            assert_eq!(chunk.to_chunk_pos(tile_pos), local_pos);
+        }
+    }
+
+    fn chunk_textures(world: &mut World) -> Vec<Handle<Texture>> {
+        let handles: Vec<Handle<ColorMaterial>> = world
+            .query_filtered::<&Handle<ColorMaterial>, With<Chunk>>()
+            .iter(world)
+            .cloned()
+            .collect();
+        let materials = world.get_resource::<Assets<ColorMaterial>>().unwrap();
+        handles
+            .iter()
+            .map(|handle| materials.get(handle).unwrap().texture.clone().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn sampling_copies_the_shared_texture() {
+        let mut world = new_world();
+        world.insert_resource(SampledMaterials::default());
+        let texture = world
+            .get_resource_mut::<Assets<Texture>>()
+            .unwrap()
+            .add(Texture::default());
+        let material = world
+            .get_resource_mut::<Assets<ColorMaterial>>()
+            .unwrap()
+            .add(texture.clone().into());
+        let filters = |texture: &Texture| {
+            let sampler = &texture.sampler;
+            (
+                sampler.mag_filter,
+                sampler.min_filter,
+                sampler.mipmap_filter,
+            )
+        };
+        let texture_filters = filters(
+            world
+                .get_resource::<Assets<Texture>>()
+                .unwrap()
+                .get(&texture)
+                .unwrap(),
+        );
+
+        let mut settings = layer_settings(MapSize(2, 1), ChunkSize(4, 4));
+        settings.sampling = Some(TilemapSampling::Nearest);
+        let layer_entity = spawn_layer(&mut world, settings, material, |layer_builder| {
+            layer_builder.set_all(Tile::default().into());
+        });
+
+        for sampling in [TilemapSampling::Nearest, TilemapSampling::Linear] {
+            let filter_mode = match sampling {
+                TilemapSampling::Nearest => FilterMode::Nearest,
+                TilemapSampling::Linear => FilterMode::Linear,
+            };
+            world
+                .get_mut::<Layer>(layer_entity)
+                .unwrap()
+                .settings
+                .sampling = Some(sampling);
+            run_system(&mut world, update_chunk_texture_sampling.system());
+
+            // Both chunks share one copy with the layer's sampler.
+            let chunk_textures = chunk_textures(&mut world);
+            assert_eq!(chunk_textures.len(), 2);
+            assert_eq!(chunk_textures[0], chunk_textures[1]);
+            assert_ne!(chunk_textures[0], texture);
+            let textures = world.get_resource::<Assets<Texture>>().unwrap();
+            let copy = textures.get(&chunk_textures[0]).unwrap();
+            assert_eq!(filters(copy), (filter_mode, filter_mode, filter_mode));
+            // The shared texture keeps its own sampler.
+            assert_eq!(filters(textures.get(&texture).unwrap()), texture_filters);
+        }
+
+        // Without a sampling the chunks are drawn with their own material again.
+        world
+            .get_mut::<Layer>(layer_entity)
+            .unwrap()
+            .settings
+            .sampling = None;
+        run_system(&mut world, update_chunk_texture_sampling.system());
+        assert_eq!(chunk_textures(&mut world), vec![texture.clone(), texture]);
+    }
+
+    // A 10x10 brush stroke in one chunk.
+    fn paint(mut commands: Commands, mut map_query: MapQuery) {
+        for x in 0..10 {
+            for y in 0..10 {
+                let tile = Tile {
+                    texture_index: 1,
+                    ..Default::default()
+                };
+                map_query
+                    .set_tile(&mut commands, TilePos(x, y), tile, 0u16, 0u16)
+                    .unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn painting_many_tiles_remeshes_the_chunk_once() {
+        let mut world = new_world();
+        let settings = layer_settings(MapSize(1, 1), ChunkSize(16, 16));
+        spawn_layer(&mut world, settings, Handle::default(), |layer_builder| {
+            layer_builder.set_all(Tile::default().into());
+        });
+
+        // The systems of a frame, in the order the plugin runs them.
+        let mut schedule = Schedule::default();
+        schedule
+            .add_stage(
+                "detect_changes",
+                SystemStage::single_threaded().with_system(update_chunk_for_changed_tiles.system()),
+            )
+            .add_stage(
+                "mesh",
+                SystemStage::single_threaded().with_system(update_chunk_mesh.system()),
+            )
+            .add_stage(
+                "apply",
+                SystemStage::single_threaded().with_system(apply_chunk_meshes.system()),
+            );
+        let mut run_frames = |world: &mut World| {
+            for _ in 0..1000 {
+                schedule.run(world);
+                if world.query::<&ChunkMeshTask>().iter(world).next().is_none() {
+                    break;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+            schedule.run(world);
+        };
+        run_frames(&mut world);
+        let mut reader = world
+            .get_resource::<Events<ChunkMeshed>>()
+            .unwrap()
+            .get_reader();
+        let events = world.get_resource::<Events<ChunkMeshed>>().unwrap();
+        assert_eq!(reader.iter(events).count(), 1);
+
+        run_system(&mut world, paint.system());
+        run_frames(&mut world);
+        let events = world.get_resource::<Events<ChunkMeshed>>().unwrap();
+        assert_eq!(reader.iter(events).count(), 1);
+    }
+}
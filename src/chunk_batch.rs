@@ -0,0 +1,590 @@
+use crate::{chunk::ChunkShaderFeatures, render::TilemapData, Chunk, Layer, TilemapMeshType};
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, VertexAttributeValues},
+        pipeline::PrimitiveTopology,
+        render_graph::base::MainPass,
+    },
+};
+
+// The vertex attributes of chunk meshes, the optional ones are only set by some chunks.
+const CHUNK_ATTRIBUTES: [&str; 7] = [
+    "Vertex_Position",
+    "Vertex_Texture",
+    "Vertex_Color",
+    "Vertex_Emissive",
+    "Vertex_Data",
+    "Vertex_Size",
+    "Vertex_Corner",
+];
+
+/// A component which merges the chunk meshes of a layer into as few meshes as possible, so the
+/// layer takes fewer draw calls.
+///
+/// Insert it on a layer entity. Chunks sharing a material and the same shader features are
+/// drawn as a single batch mesh, the chunks themselves are no longer drawn. Batches are shown
+/// while any of their chunks is visible, so culling only hides a batch once all of its chunks
+/// are off screen. The batches are rebuilt whenever the mesh of one of their chunks changes,
+/// which makes batching a good fit for large static layers. Removing the component draws the
+/// chunks on their own again.
+///
+/// Note: Only square layers which don't wrap are batched. Batches are drawn in the main pass at
+/// the depth of their first chunk, so `TilemapSortKey` and `TilemapPassPlugin` don't apply to
+/// them.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TilemapBatching;
+
+// Marks a chunk drawn by a batch. The chunk itself stays hidden, `update_chunk_visibility` keeps
+// track of whether it would be visible on its own, which shows its batch.
+pub(crate) struct ChunkBatched {
+    pub is_visible: bool,
+}
+
+// A mesh holding the chunks of a batched layer, a child of the layer.
+pub(crate) struct ChunkBatch {
+    layer_entity: Entity,
+    chunks: Vec<Entity>,
+    // The offset of the batch from its first chunk, which the batch follows.
+    offset: Vec2,
+}
+
+#[derive(Bundle)]
+struct ChunkBatchBundle {
+    batch: ChunkBatch,
+    main_pass: MainPass,
+    material: Handle<ColorMaterial>,
+    render_pipeline: RenderPipelines,
+    visible: Visible,
+    draw: Draw,
+    mesh: Handle<Mesh>,
+    transform: Transform,
+    global_transform: GlobalTransform,
+    tilemap_data: TilemapData,
+}
+
+// What chunks need to have in common to share a batch.
+#[derive(PartialEq)]
+struct BatchKey {
+    material: Handle<ColorMaterial>,
+    features: ChunkShaderFeatures,
+    topology: PrimitiveTopology,
+    attributes: Vec<bool>,
+}
+
+type BatchChunkQuery<'a> = Query<
+    'a,
+    (
+        &'static Chunk,
+        &'static Handle<ColorMaterial>,
+        &'static RenderPipelines,
+        &'static Transform,
+        &'static Visible,
+        Option<&'static ChunkBatched>,
+    ),
+>;
+
+type ChangedBatchingLayerQuery<'a> = Query<
+    'a,
+    Entity,
+    (
+        With<TilemapBatching>,
+        Or<(Added<TilemapBatching>, Changed<Layer>)>,
+    ),
+>;
+
+// Rebuilds the batches of layers whose chunk meshes, materials or chunks changed.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn update_chunk_batches(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut mesh_events: EventReader<AssetEvent<Mesh>>,
+    layer_query: Query<(Entity, &Layer), With<TilemapBatching>>,
+    changed_layers: ChangedBatchingLayerQuery,
+    removed_batching: RemovedComponents<TilemapBatching>,
+    chunk_query: BatchChunkQuery,
+    changed_materials: Query<&Chunk, Changed<Handle<ColorMaterial>>>,
+    batch_query: Query<(Entity, &ChunkBatch)>,
+) {
+    let mut dirty_layers: Vec<Entity> = changed_layers.iter().collect();
+    let mut add_dirty_layer = |layer_entity: Entity| {
+        if !dirty_layers.contains(&layer_entity) {
+            dirty_layers.push(layer_entity);
+        }
+    };
+
+    let changed_meshes: Vec<Handle<Mesh>> = mesh_events
+        .iter()
+        .filter_map(|event| match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                Some(handle.clone_weak())
+            }
+            AssetEvent::Removed { .. } => None,
+        })
+        .collect();
+    if !changed_meshes.is_empty() {
+        for (layer_entity, layer) in layer_query.iter() {
+            let changed = layer.iter_chunks().any(|(_, chunk_entity)| {
+                chunk_query.get(chunk_entity).map_or(false, |(chunk, ..)| {
+                    changed_meshes.contains(&chunk.mesh_handle)
+                })
+            });
+            if changed {
+                add_dirty_layer(layer_entity);
+            }
+        }
+    }
+    for chunk in changed_materials.iter() {
+        if layer_query.get(chunk.map_entity).is_ok() {
+            add_dirty_layer(chunk.map_entity);
+        }
+    }
+    // Batches of despawned chunks.
+    for (_, batch) in batch_query.iter() {
+        if batch
+            .chunks
+            .iter()
+            .any(|chunk_entity| chunk_query.get(*chunk_entity).is_err())
+            && layer_query.get(batch.layer_entity).is_ok()
+        {
+            add_dirty_layer(batch.layer_entity);
+        }
+    }
+
+    // Layers which stopped batching draw their chunks again.
+    for layer_entity in removed_batching.iter() {
+        for (batch_entity, batch) in batch_query.iter() {
+            if batch.layer_entity == layer_entity {
+                unbatch(&mut commands, &chunk_query, batch_entity, batch);
+            }
+        }
+    }
+
+    for layer_entity in dirty_layers {
+        for (batch_entity, batch) in batch_query.iter() {
+            if batch.layer_entity == layer_entity {
+                unbatch(&mut commands, &chunk_query, batch_entity, batch);
+            }
+        }
+
+        let layer = match layer_query.get(layer_entity) {
+            Ok((_, layer)) => layer,
+            Err(_) => continue,
+        };
+        if layer.settings.mesh_type != TilemapMeshType::Square || layer.settings.wrap {
+            continue;
+        }
+
+        let mut groups: Vec<(BatchKey, Vec<Entity>)> = Vec::new();
+        for (_, chunk_entity) in layer.iter_chunks() {
+            let (chunk, material, ..) = match chunk_query.get(chunk_entity) {
+                Ok(chunk) => chunk,
+                Err(_) => continue,
+            };
+            let mesh = match meshes.get(&chunk.mesh_handle) {
+                Some(mesh) => mesh,
+                None => continue,
+            };
+            let key = BatchKey {
+                material: material.clone_weak(),
+                features: chunk.shader_features,
+                topology: mesh.primitive_topology(),
+                attributes: CHUNK_ATTRIBUTES
+                    .iter()
+                    .map(|name| mesh.attribute(*name).is_some())
+                    .collect(),
+            };
+            match groups.iter_mut().find(|(group_key, _)| *group_key == key) {
+                Some((_, chunks)) => chunks.push(chunk_entity),
+                None => groups.push((key, vec![chunk_entity])),
+            }
+        }
+
+        for (_, chunks) in groups.into_iter().filter(|(_, chunks)| chunks.len() > 1) {
+            let (first_chunk, material, render_pipelines, transform, ..) =
+                chunk_query.get(chunks[0]).unwrap();
+            let chunk_meshes: Vec<(&Mesh, Vec2)> = chunks
+                .iter()
+                .map(|chunk_entity| {
+                    let (chunk, ..) = chunk_query.get(*chunk_entity).unwrap();
+                    let offset: Vec2 =
+                        Vec2::from(chunk.position) * Vec2::from(chunk.settings.chunk_size);
+                    (meshes.get(&chunk.mesh_handle).unwrap(), offset)
+                })
+                .collect();
+            let mesh = merge_chunk_meshes(&chunk_meshes);
+
+            for chunk_entity in chunks.iter() {
+                let (_, _, _, _, visible, batched) = chunk_query.get(*chunk_entity).unwrap();
+                // Chunks moved between batches keep the visibility they would have.
+                let is_visible = batched.map_or(visible.is_visible, |batched| batched.is_visible);
+                commands
+                    .entity(*chunk_entity)
+                    .insert(ChunkBatched { is_visible })
+                    .insert(Visible {
+                        is_visible: false,
+                        ..*visible
+                    });
+            }
+            let offset = -Vec2::from(first_chunk.position)
+                * Vec2::from(first_chunk.settings.chunk_size)
+                * first_chunk.settings.grid_size;
+            let mut transform = *transform;
+            transform.translation += offset.extend(0.0);
+            let mut tilemap_data = TilemapData::from(first_chunk);
+            tilemap_data.chunk_pos = Vec2::ZERO;
+
+            let batch = ChunkBatchBundle {
+                batch: ChunkBatch {
+                    layer_entity,
+                    chunks,
+                    offset,
+                },
+                main_pass: MainPass,
+                material: material.clone(),
+                render_pipeline: RenderPipelines::from_pipelines(
+                    render_pipelines.pipelines.clone(),
+                ),
+                visible: Visible {
+                    is_visible: true,
+                    is_transparent: true,
+                },
+                draw: Draw::default(),
+                mesh: meshes.add(mesh),
+                transform,
+                global_transform: GlobalTransform::default(),
+                tilemap_data,
+            };
+            commands
+                .entity(layer_entity)
+                .with_children(|child_builder| {
+                    child_builder.spawn_bundle(batch);
+                });
+        }
+    }
+}
+
+fn unbatch(
+    commands: &mut Commands,
+    chunk_query: &BatchChunkQuery,
+    batch_entity: Entity,
+    batch: &ChunkBatch,
+) {
+    for chunk_entity in batch.chunks.iter() {
+        if let Ok((_, _, _, _, visible, Some(batched))) = chunk_query.get(*chunk_entity) {
+            commands
+                .entity(*chunk_entity)
+                .remove::<ChunkBatched>()
+                .insert(Visible {
+                    is_visible: batched.is_visible,
+                    ..*visible
+                });
+        }
+    }
+    commands.entity(batch_entity).despawn_recursive();
+}
+
+type BatchSyncQuery<'a> = Query<
+    'a,
+    (
+        &'static ChunkBatch,
+        &'static mut RenderPipelines,
+        &'static mut TilemapData,
+        &'static mut Visible,
+        &'static mut Transform,
+    ),
+    Without<Chunk>,
+>;
+
+type BatchSourceQuery<'a> = Query<
+    'a,
+    (
+        &'static RenderPipelines,
+        &'static TilemapData,
+        &'static ChunkBatched,
+        &'static Transform,
+    ),
+    (With<Chunk>, Without<ChunkBatch>),
+>;
+
+// Keeps the pipelines, uniforms and transform of the batches in step with their chunks, and
+// shows the batches while any of their chunks is visible.
+pub(crate) fn update_chunk_batch_data(
+    mut batch_query: BatchSyncQuery,
+    chunk_query: BatchSourceQuery,
+) {
+    for (batch, mut render_pipelines, mut tilemap_data, mut visible, mut transform) in
+        batch_query.iter_mut()
+    {
+        let (chunk_pipelines, chunk_data, _, chunk_transform) =
+            match chunk_query.get(batch.chunks[0]) {
+                Ok(chunk) => chunk,
+                Err(_) => continue,
+            };
+
+        // The chunk pipelines are swapped by materials, wireframes and target formats.
+        let same_pipelines = render_pipelines.pipelines.len() == chunk_pipelines.pipelines.len()
+            && render_pipelines
+                .pipelines
+                .iter()
+                .zip(chunk_pipelines.pipelines.iter())
+                .all(|(pipeline, chunk_pipeline)| pipeline.pipeline == chunk_pipeline.pipeline);
+        if !same_pipelines {
+            render_pipelines.pipelines = chunk_pipelines.pipelines.clone();
+        } else {
+            for (pipeline, chunk_pipeline) in render_pipelines
+                .pipelines
+                .iter_mut()
+                .zip(chunk_pipelines.pipelines.iter())
+            {
+                pipeline
+                    .specialization
+                    .shader_specialization
+                    .shader_defs
+                    .extend(
+                        chunk_pipeline
+                            .specialization
+                            .shader_specialization
+                            .shader_defs
+                            .iter()
+                            .cloned(),
+                    );
+            }
+        }
+
+        let mut data = chunk_data.clone();
+        data.chunk_pos = Vec2::ZERO;
+        *tilemap_data = data;
+
+        let mut batch_transform = *chunk_transform;
+        batch_transform.translation += batch.offset.extend(0.0);
+        if *transform != batch_transform {
+            *transform = batch_transform;
+        }
+
+        let is_visible = batch.chunks.iter().any(|chunk_entity| {
+            chunk_query
+                .get(*chunk_entity)
+                .map_or(false, |(_, _, batched, _)| batched.is_visible)
+        });
+        if visible.is_visible != is_visible {
+            visible.is_visible = is_visible;
+        }
+    }
+}
+
+// Appends the chunk meshes into one mesh, offsetting the tile positions of each chunk by the
+// position of its first tile in the layer.
+fn merge_chunk_meshes(chunk_meshes: &[(&Mesh, Vec2)]) -> Mesh {
+    let mut merged = Mesh::new(chunk_meshes[0].0.primitive_topology());
+    let mut indices = Vec::new();
+    for name in CHUNK_ATTRIBUTES.iter() {
+        let mut merged_values: Option<VertexAttributeValues> = None;
+        for (mesh, offset) in chunk_meshes.iter() {
+            let values = match mesh.attribute(*name) {
+                Some(values) => values,
+                None => continue,
+            };
+            match (&mut merged_values, values) {
+                (None, values) => merged_values = Some(values.clone()),
+                (
+                    Some(VertexAttributeValues::Float(merged)),
+                    VertexAttributeValues::Float(values),
+                ) => merged.extend_from_slice(values),
+                (
+                    Some(VertexAttributeValues::Float2(merged)),
+                    VertexAttributeValues::Float2(values),
+                ) => merged.extend_from_slice(values),
+                (
+                    Some(VertexAttributeValues::Float3(merged)),
+                    VertexAttributeValues::Float3(values),
+                ) => merged.extend_from_slice(values),
+                (
+                    Some(VertexAttributeValues::Float4(merged)),
+                    VertexAttributeValues::Float4(values),
+                ) => merged.extend_from_slice(values),
+                (
+                    Some(VertexAttributeValues::Int4(merged)),
+                    VertexAttributeValues::Int4(values),
+                ) => merged.extend_from_slice(values),
+                _ => {}
+            }
+            // Tile positions are relative to the chunk.
+            if *name == "Vertex_Position" {
+                if let Some(VertexAttributeValues::Float3(merged)) = &mut merged_values {
+                    let start = merged.len() - mesh.count_vertices();
+                    for position in merged[start..].iter_mut() {
+                        position[0] += offset.x;
+                        position[1] += offset.y;
+                    }
+                }
+            }
+        }
+        if let Some(values) = merged_values {
+            merged.set_attribute(*name, values);
+        }
+    }
+
+    let mut vertex_count = 0;
+    for (mesh, _) in chunk_meshes.iter() {
+        if let Some(Indices::U32(mesh_indices)) = mesh.indices() {
+            indices.extend(mesh_indices.iter().map(|index| index + vertex_count));
+        }
+        vertex_count += mesh.count_vertices() as u32;
+    }
+    merged.set_indices(Some(Indices::U32(indices)));
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::{
+        apply_chunk_meshes, update_chunk_mesh, update_chunk_visibility, ChunkMeshTask,
+    };
+    use crate::prelude::*;
+    use crate::test_util::{layer_settings, new_world, spawn_layer};
+    use bevy::ecs::schedule::{Schedule, SystemStage};
+
+    fn setup() -> (World, Schedule, Entity) {
+        let mut world = new_world();
+        let settings = layer_settings(MapSize(4, 4), ChunkSize(4, 4));
+        let layer_entity = spawn_layer(&mut world, settings, Handle::default(), |layer_builder| {
+            layer_builder.set_all(Tile::default().into());
+        });
+
+        // The systems of a frame, in the order the plugin runs them.
+        let mut schedule = Schedule::default();
+        schedule
+            .add_stage(
+                "visibility",
+                SystemStage::single_threaded().with_system(update_chunk_visibility.system()),
+            )
+            .add_stage(
+                "mesh",
+                SystemStage::single_threaded().with_system(update_chunk_mesh.system()),
+            )
+            .add_stage(
+                "apply",
+                SystemStage::single_threaded().with_system(apply_chunk_meshes.system()),
+            )
+            .add_stage(
+                "batch",
+                SystemStage::single_threaded().with_system(update_chunk_batches.system()),
+            )
+            .add_stage(
+                "sync",
+                SystemStage::single_threaded().with_system(update_chunk_batch_data.system()),
+            )
+            .add_stage(
+                "asset_events",
+                SystemStage::single_threaded()
+                    .with_system(Assets::<Mesh>::asset_event_system.system()),
+            );
+        (world, schedule, layer_entity)
+    }
+
+    // Runs frames until the chunks are meshed and the batches have caught up with them.
+    fn run_frames(world: &mut World, schedule: &mut Schedule) {
+        for _ in 0..1000 {
+            schedule.run(world);
+            if world.query::<&ChunkMeshTask>().iter(world).next().is_none() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        for _ in 0..2 {
+            schedule.run(world);
+        }
+    }
+
+    // The number of entities the render pipelines system would draw.
+    fn draw_count(world: &mut World) -> usize {
+        world
+            .query_filtered::<&Visible, (With<Handle<Mesh>>, With<RenderPipelines>)>()
+            .iter(world)
+            .filter(|visible| visible.is_visible)
+            .count()
+    }
+
+    fn batch_meshes(world: &mut World) -> Vec<Mesh> {
+        let handles: Vec<Handle<Mesh>> = world
+            .query_filtered::<&Handle<Mesh>, With<ChunkBatch>>()
+            .iter(world)
+            .cloned()
+            .collect();
+        let meshes = world.get_resource::<Assets<Mesh>>().unwrap();
+        handles
+            .iter()
+            .map(|handle| meshes.get(handle).unwrap().clone())
+            .collect()
+    }
+
+    #[test]
+    fn batching_reduces_draws() {
+        let (mut world, mut schedule, layer_entity) = setup();
+        run_frames(&mut world, &mut schedule);
+        assert_eq!(draw_count(&mut world), 16);
+
+        world.entity_mut(layer_entity).insert(TilemapBatching);
+        run_frames(&mut world, &mut schedule);
+        assert_eq!(draw_count(&mut world), 1);
+
+        // The batch holds every tile, offset by the position of its chunk.
+        let meshes = batch_meshes(&mut world);
+        assert_eq!(meshes.len(), 1);
+        assert_eq!(meshes[0].count_vertices(), 16 * 16 * 4);
+        match meshes[0].attribute("Vertex_Position") {
+            Some(VertexAttributeValues::Float3(positions)) => {
+                let max = positions.iter().fold(Vec2::ZERO, |max, position| {
+                    max.max(Vec2::new(position[0], position[1]))
+                });
+                assert_eq!(max, Vec2::new(15.0, 15.0));
+            }
+            _ => panic!("the batch has no positions"),
+        }
+        match meshes[0].indices() {
+            Some(Indices::U32(indices)) => {
+                assert_eq!(indices.len(), 16 * 16 * 6);
+                assert_eq!(*indices.iter().max().unwrap(), 16 * 16 * 4 - 1);
+            }
+            _ => panic!("the batch has no indices"),
+        }
+
+        // Chunks with another material get a batch of their own.
+        let other_material = world
+            .get_resource_mut::<Assets<ColorMaterial>>()
+            .unwrap()
+            .add(ColorMaterial::default());
+        let layer = world.get::<Layer>(layer_entity).unwrap();
+        let chunks: Vec<Entity> = layer
+            .iter_chunks()
+            .take(2)
+            .map(|(_, chunk_entity)| chunk_entity)
+            .collect();
+        for chunk_entity in chunks {
+            world
+                .entity_mut(chunk_entity)
+                .insert(other_material.clone());
+        }
+        run_frames(&mut world, &mut schedule);
+        assert_eq!(draw_count(&mut world), 2);
+
+        // Hidden chunks hide their batch once all of them are hidden.
+        world.entity_mut(layer_entity).insert(Visible {
+            is_visible: false,
+            is_transparent: true,
+        });
+        run_frames(&mut world, &mut schedule);
+        assert_eq!(draw_count(&mut world), 0);
+        world.get_mut::<Visible>(layer_entity).unwrap().is_visible = true;
+        run_frames(&mut world, &mut schedule);
+        assert_eq!(draw_count(&mut world), 2);
+
+        // Without batching the chunks are drawn on their own again.
+        world.entity_mut(layer_entity).remove::<TilemapBatching>();
+        run_frames(&mut world, &mut schedule);
+        assert_eq!(draw_count(&mut world), 16);
+        assert!(batch_meshes(&mut world).is_empty());
+    }
+}
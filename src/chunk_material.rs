@@ -0,0 +1,295 @@
+use crate::{
+    chunk::ChunkShaderFeatures, chunk_batch::ChunkBatched, mesher::ChunkMeshData,
+    render::TilemapData, Chunk,
+};
+use bevy::{prelude::*, render::render_graph::base::MainPass};
+
+// A mesh holding the tiles of a chunk with one `TileMaterial`, a child of the chunk.
+pub(crate) struct ChunkSubMesh {
+    chunk_entity: Entity,
+    features: ChunkShaderFeatures,
+}
+
+#[derive(Bundle)]
+struct ChunkSubMeshBundle {
+    sub_mesh: ChunkSubMesh,
+    main_pass: MainPass,
+    material: Handle<ColorMaterial>,
+    render_pipeline: RenderPipelines,
+    visible: Visible,
+    draw: Draw,
+    mesh: Handle<Mesh>,
+    transform: Transform,
+    global_transform: GlobalTransform,
+    tilemap_data: TilemapData,
+}
+
+// Writes the sub-meshes built along with the mesh of a chunk. Materials new to the chunk get a
+// child entity drawing their sub-mesh, the ones the chunk no longer has are despawned.
+pub(crate) fn apply_chunk_sub_meshes(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &Assets<ColorMaterial>,
+    chunk_entity: Entity,
+    chunk: &mut Chunk,
+    sub_meshes: Vec<(Handle<ColorMaterial>, ChunkMeshData)>,
+) {
+    let topology = match meshes.get(&chunk.mesh_handle) {
+        Some(mesh) => mesh.primitive_topology(),
+        None => return,
+    };
+    let mut previous = std::mem::take(&mut chunk.sub_meshes);
+    for (material, mesh_data) in sub_meshes {
+        let (sub_mesh_entity, mesh_handle) = match previous
+            .iter()
+            .position(|(previous_material, ..)| *previous_material == material)
+        {
+            Some(index) => {
+                let (_, sub_mesh_entity, mesh_handle) = previous.swap_remove(index);
+                (sub_mesh_entity, mesh_handle)
+            }
+            None => {
+                let mesh_handle = meshes.add(Mesh::new(topology));
+                let sub_mesh = ChunkSubMeshBundle {
+                    sub_mesh: ChunkSubMesh {
+                        chunk_entity,
+                        features: ChunkShaderFeatures::default(),
+                    },
+                    main_pass: MainPass,
+                    material: materials.get_handle(&material),
+                    // The pipelines of the chunk are copied once the sub-mesh is spawned.
+                    render_pipeline: RenderPipelines::default(),
+                    visible: Visible {
+                        is_visible: false,
+                        is_transparent: true,
+                    },
+                    draw: Draw::default(),
+                    mesh: mesh_handle.clone(),
+                    transform: Transform::default(),
+                    global_transform: GlobalTransform::default(),
+                    tilemap_data: TilemapData::default(),
+                };
+                let mut sub_mesh_entity = None;
+                commands
+                    .entity(chunk_entity)
+                    .with_children(|child_builder| {
+                        sub_mesh_entity = Some(child_builder.spawn_bundle(sub_mesh).id());
+                    });
+                (sub_mesh_entity.unwrap(), mesh_handle)
+            }
+        };
+
+        if let Some(mesh) = meshes.get_mut(&mesh_handle) {
+            let (features, _) = mesh_data.apply(mesh);
+            commands.entity(sub_mesh_entity).insert(ChunkSubMesh {
+                chunk_entity,
+                features,
+            });
+        }
+        chunk
+            .sub_meshes
+            .push((material, sub_mesh_entity, mesh_handle));
+    }
+
+    for (_, sub_mesh_entity, _) in previous {
+        commands.entity(sub_mesh_entity).despawn_recursive();
+    }
+}
+
+type SubMeshSyncQuery<'a> = Query<
+    'a,
+    (
+        Entity,
+        &'static ChunkSubMesh,
+        &'static mut RenderPipelines,
+        &'static mut TilemapData,
+        &'static mut Visible,
+    ),
+    Without<Chunk>,
+>;
+
+type SubMeshSourceQuery<'a> = Query<
+    'a,
+    (
+        &'static Chunk,
+        &'static RenderPipelines,
+        &'static TilemapData,
+        &'static Visible,
+        Option<&'static ChunkBatched>,
+    ),
+    Without<ChunkSubMesh>,
+>;
+
+// Keeps the pipelines, uniforms and visibility of the sub-meshes in step with their chunks.
+// The shader defs of a sub-mesh are the ones of its chunk with the features of its own tiles.
+pub(crate) fn update_chunk_sub_mesh_data(
+    mut commands: Commands,
+    mut sub_mesh_query: SubMeshSyncQuery,
+    chunk_query: SubMeshSourceQuery,
+) {
+    for (sub_mesh_entity, sub_mesh, mut render_pipelines, mut tilemap_data, mut visible) in
+        sub_mesh_query.iter_mut()
+    {
+        let (chunk, chunk_pipelines, chunk_data, chunk_visible, batched) =
+            match chunk_query.get(sub_mesh.chunk_entity) {
+                Ok(chunk) => chunk,
+                Err(_) => {
+                    commands.entity(sub_mesh_entity).despawn_recursive();
+                    continue;
+                }
+            };
+
+        // The chunk pipelines are swapped by materials, wireframes and target formats.
+        let same_pipelines = render_pipelines.pipelines.len() == chunk_pipelines.pipelines.len()
+            && render_pipelines
+                .pipelines
+                .iter()
+                .zip(chunk_pipelines.pipelines.iter())
+                .all(|(pipeline, chunk_pipeline)| pipeline.pipeline == chunk_pipeline.pipeline);
+        if !same_pipelines {
+            render_pipelines.pipelines = chunk_pipelines.pipelines.clone();
+        }
+        let chunk_feature_defs = chunk.shader_features.shader_defs();
+        let feature_defs = sub_mesh.features.shader_defs();
+        for (pipeline, chunk_pipeline) in render_pipelines
+            .pipelines
+            .iter_mut()
+            .zip(chunk_pipelines.pipelines.iter())
+        {
+            let shader_defs = chunk_pipeline
+                .specialization
+                .shader_specialization
+                .shader_defs
+                .iter()
+                .filter(|shader_def| !chunk_feature_defs.contains(shader_def))
+                .chain(feature_defs.iter())
+                .cloned()
+                .collect();
+            pipeline.specialization.shader_specialization.shader_defs = shader_defs;
+        }
+
+        *tilemap_data = chunk_data.clone();
+        // The sub-meshes of batched chunks aren't part of the batch.
+        let is_visible = batched.map_or(chunk_visible.is_visible, |batched| batched.is_visible);
+        if visible.is_visible != is_visible {
+            visible.is_visible = is_visible;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::{apply_chunk_meshes, update_chunk_mesh, ChunkMeshTask};
+    use crate::prelude::*;
+    use crate::test_util::{layer_settings, new_world, spawn_layer};
+    use bevy::ecs::schedule::{Schedule, SystemStage};
+
+    // Runs frames until the chunks are meshed and their sub-meshes have caught up with them.
+    fn run_frames(world: &mut World, schedule: &mut Schedule) {
+        for _ in 0..1000 {
+            schedule.run(world);
+            if world.query::<&ChunkMeshTask>().iter(world).next().is_none() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        for _ in 0..2 {
+            schedule.run(world);
+        }
+    }
+
+    // The texture and vertex count of each entity the render pipelines system would draw.
+    fn draw_items(world: &mut World) -> Vec<(Handle<Texture>, usize)> {
+        let items: Vec<(Handle<ColorMaterial>, Handle<Mesh>)> = world
+            .query_filtered::<(&Handle<ColorMaterial>, &Handle<Mesh>, &Visible), With<RenderPipelines>>()
+            .iter(world)
+            .filter(|(_, _, visible)| visible.is_visible)
+            .map(|(material, mesh, _)| (material.clone(), mesh.clone()))
+            .collect();
+        let materials = world.get_resource::<Assets<ColorMaterial>>().unwrap();
+        let meshes = world.get_resource::<Assets<Mesh>>().unwrap();
+        items
+            .iter()
+            .map(|(material, mesh)| {
+                (
+                    materials.get(material).unwrap().texture.clone().unwrap(),
+                    meshes.get(mesh).unwrap().count_vertices(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tile_materials_get_their_own_draws() {
+        let mut world = new_world();
+
+        let mut textures = world.get_resource_mut::<Assets<Texture>>().unwrap();
+        let layer_texture = textures.add(Texture::default());
+        let other_texture = textures.add(Texture::default());
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+        let layer_material = materials.add(layer_texture.clone().into());
+        let other_material = materials.add(other_texture.clone().into());
+        let settings = layer_settings(MapSize(1, 1), ChunkSize(4, 4));
+        spawn_layer(&mut world, settings, layer_material, |layer_builder| {
+            layer_builder.set_all(Tile::default().into());
+        });
+
+        // The left half of the chunk is drawn with the other material.
+        let tiles: Vec<(Entity, TilePos)> = world
+            .query::<(Entity, &TilePos)>()
+            .iter(&world)
+            .map(|(entity, tile_pos)| (entity, *tile_pos))
+            .collect();
+        for (tile_entity, tile_pos) in tiles.iter() {
+            if tile_pos.0 < 2 {
+                world
+                    .entity_mut(*tile_entity)
+                    .insert(TileMaterial(other_material.clone()));
+            }
+        }
+
+        let mut schedule = Schedule::default();
+        schedule
+            .add_stage(
+                "mesh",
+                SystemStage::single_threaded().with_system(update_chunk_mesh.system()),
+            )
+            .add_stage(
+                "apply",
+                SystemStage::single_threaded().with_system(apply_chunk_meshes.system()),
+            )
+            .add_stage(
+                "sync",
+                SystemStage::single_threaded().with_system(update_chunk_sub_mesh_data.system()),
+            );
+        run_frames(&mut world, &mut schedule);
+
+        let mut items = draw_items(&mut world);
+        items.sort_by_key(|(texture, _)| *texture != layer_texture);
+        assert_eq!(
+            items,
+            vec![(layer_texture.clone(), 8 * 4), (other_texture, 8 * 4)]
+        );
+        let (render_pipelines, tilemap_data) = world
+            .query_filtered::<(&RenderPipelines, &TilemapData), With<ChunkSubMesh>>()
+            .iter(&world)
+            .next()
+            .unwrap();
+        assert_eq!(render_pipelines.pipelines.len(), 1);
+        assert_eq!(tilemap_data.texture_size, Vec2::new(96.0, 16.0));
+
+        // Without any tile materials the sub-mesh is gone.
+        for (tile_entity, _) in tiles.iter() {
+            world.entity_mut(*tile_entity).remove::<TileMaterial>();
+        }
+        let chunk_entity = world
+            .query_filtered::<Entity, With<Chunk>>()
+            .iter(&world)
+            .next()
+            .unwrap();
+        world.get_mut::<Chunk>(chunk_entity).unwrap().needs_remesh = true;
+        run_frames(&mut world, &mut schedule);
+        assert_eq!(draw_items(&mut world), vec![(layer_texture, 16 * 4)]);
+    }
+}
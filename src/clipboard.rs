@@ -0,0 +1,268 @@
+use crate::layer::LayerId;
+use crate::map::MapId;
+use crate::map_query::MapQuery;
+use crate::tile::Tile;
+use crate::TilePos;
+use bevy::prelude::*;
+
+/// A rectangle of tiles copied with `MapQuery::copy_region`, which can be pasted into any layer
+/// with `MapQuery::paste_region`.
+///
+/// It doubles as a brush for editors, a pattern of tiles built with `new` and `set` can be
+/// rotated and mirrored as a whole and then stamped into a layer with `paste_region`.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileClipboard {
+    /// Width of the copied rectangle in tiles.
+    pub width: u32,
+    /// Height of the copied rectangle in tiles.
+    pub height: u32,
+    /// The copied tiles in rows starting at the bottom left, `None` for empty positions.
+    pub tiles: Vec<Option<Tile>>,
+}
+
+impl TileClipboard {
+    /// Creates an empty rectangle of tiles.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            tiles: vec![None; (width * height) as usize],
+        }
+    }
+
+    /// Gets the tile copied at a position relative to the bottom left of the rectangle.
+    pub fn get(&self, pos: TilePos) -> Option<&Tile> {
+        if pos.0 >= self.width || pos.1 >= self.height {
+            return None;
+        }
+        self.tiles[(pos.1 * self.width + pos.0) as usize].as_ref()
+    }
+
+    /// Sets the tile at a position relative to the bottom left of the rectangle, `None` empties
+    /// it. Positions outside of the rectangle are ignored.
+    pub fn set(&mut self, pos: TilePos, tile: Option<Tile>) {
+        if pos.0 >= self.width || pos.1 >= self.height {
+            return;
+        }
+        self.tiles[(pos.1 * self.width + pos.0) as usize] = tile;
+    }
+
+    /// Returns the tiles rotated a quarter turn clockwise. The flip flags of each tile are
+    /// updated so the tiles are rotated along with the rectangle.
+    /// ```
+    /// use bevy_ecs_tilemap::prelude::*;
+    ///
+    /// // A 2x3 L shape.
+    /// let mut brush = TileClipboard::new(2, 3);
+    /// for (pos, texture_index) in [(TilePos(0, 0), 0), (TilePos(1, 0), 1), (TilePos(0, 2), 2)] {
+    ///     brush.set(pos, Some(Tile { texture_index, ..Default::default() }));
+    /// }
+    ///
+    /// let rotated = brush.rotate_cw();
+    /// assert_eq!((rotated.width, rotated.height), (3, 2));
+    /// let tile = rotated.get(TilePos(0, 1)).unwrap();
+    /// assert_eq!(tile.texture_index, 0);
+    /// assert_eq!((tile.flip_x, tile.flip_y, tile.flip_d), (true, false, true));
+    /// assert_eq!(rotated.get(TilePos(0, 0)).unwrap().texture_index, 1);
+    /// assert_eq!(rotated.get(TilePos(2, 1)).unwrap().texture_index, 2);
+    /// assert!(rotated.get(TilePos(1, 1)).is_none());
+    /// ```
+    pub fn rotate_cw(&self) -> Self {
+        // The tile at (x, y) moves to (y, width - 1 - x).
+        self.transform(self.height, self.width, [[0, 1], [-1, 0]], |pos| {
+            TilePos(pos.1, self.width - 1 - pos.0)
+        })
+    }
+
+    /// Returns the tiles mirrored along the x axis, so the left and right are swapped.
+    pub fn mirror_x(&self) -> Self {
+        self.transform(self.width, self.height, [[-1, 0], [0, 1]], |pos| {
+            TilePos(self.width - 1 - pos.0, pos.1)
+        })
+    }
+
+    /// Returns the tiles mirrored along the y axis, so the top and bottom are swapped.
+    pub fn mirror_y(&self) -> Self {
+        self.transform(self.width, self.height, [[1, 0], [0, -1]], |pos| {
+            TilePos(pos.0, self.height - 1 - pos.1)
+        })
+    }
+
+    // Moves every tile to a new position and applies the transform to its flip flags.
+    fn transform(
+        &self,
+        width: u32,
+        height: u32,
+        transform: [[i32; 2]; 2],
+        move_tile: impl Fn(TilePos) -> TilePos,
+    ) -> Self {
+        let mut transformed = Self::new(width, height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if let Some(tile) = self.get(TilePos(x, y)) {
+                    let mut tile = *tile;
+                    transform_flips(&mut tile, transform);
+                    transformed.set(move_tile(TilePos(x, y)), Some(tile));
+                }
+            }
+        }
+        transformed
+    }
+}
+
+// The flips of a tile as the matrix mapping a point of the drawn tile, relative to its center, to
+// a point of its texture, indexed by `flip_x | flip_y << 1 | flip_d << 2` like in the shaders.
+const FLIP_MATRICES: [[[i32; 2]; 2]; 8] = [
+    [[1, 0], [0, 1]],
+    [[-1, 0], [0, 1]],
+    [[1, 0], [0, -1]],
+    [[-1, 0], [0, -1]],
+    [[0, -1], [-1, 0]],
+    [[0, -1], [1, 0]],
+    [[0, 1], [-1, 0]],
+    [[0, 1], [1, 0]],
+];
+
+// Updates the flips of a tile for a rotation or mirroring of the drawn tiles, where `transform`
+// maps every point of a tile to the point it's drawn at afterwards.
+fn transform_flips(tile: &mut Tile, transform: [[i32; 2]; 2]) {
+    let flips = tile.flip_x as usize | (tile.flip_y as usize) << 1 | (tile.flip_d as usize) << 2;
+    let matrix = FLIP_MATRICES[flips];
+    // The transform is a rotation or reflection, so its inverse is its transpose.
+    let mut flipped = [[0; 2]; 2];
+    for (row, flipped_row) in flipped.iter_mut().enumerate() {
+        for (column, value) in flipped_row.iter_mut().enumerate() {
+            *value = matrix[row][0] * transform[column][0] + matrix[row][1] * transform[column][1];
+        }
+    }
+    if let Some(flips) = FLIP_MATRICES.iter().position(|matrix| *matrix == flipped) {
+        tile.flip_x = flips & 1 != 0;
+        tile.flip_y = flips & 2 != 0;
+        tile.flip_d = flips & 4 != 0;
+    }
+}
+
+impl<'a> MapQuery<'a> {
+    /// Copies the tiles within the rectangle defined by the `[start`, `end`) positions.
+    /// Positions outside of the layer or without a tile are copied as empty.
+    pub fn copy_region(
+        &self,
+        tile_query: &Query<&Tile>,
+        start: TilePos,
+        end: TilePos,
+        map_id: impl MapId,
+        layer_id: impl LayerId,
+    ) -> TileClipboard {
+        let width = end.0.saturating_sub(start.0);
+        let height = end.1.saturating_sub(start.1);
+        let mut tiles = vec![None; (width * height) as usize];
+        for (tile_pos, tile) in self.tiles_in_rect(tile_query, start, end, map_id, layer_id) {
+            let index = (tile_pos.1 - start.1) * width + (tile_pos.0 - start.0);
+            tiles[index as usize] = Some(*tile);
+        }
+
+        TileClipboard {
+            width,
+            height,
+            tiles,
+        }
+    }
+
+    /// Pastes copied tiles with the bottom left of the rectangle at `target`, see `set_tile`.
+    ///
+    /// Empty positions in the clipboard keep the tiles already in the layer and tiles which
+    /// would land outside of the layer, or past `u32::MAX`, are skipped.
+    /// The clipboard holds a copy of the tiles, so pasting over the copied region is fine.
+    pub fn paste_region(
+        &mut self,
+        commands: &mut Commands,
+        clipboard: &TileClipboard,
+        target: TilePos,
+        map_id: impl MapId,
+        layer_id: impl LayerId,
+    ) {
+        let map_id = map_id.into();
+        let layer_id = layer_id.into();
+        for y in 0..clipboard.height {
+            for x in 0..clipboard.width {
+                let tile_pos = match (target.0.checked_add(x), target.1.checked_add(y)) {
+                    (Some(tile_x), Some(tile_y)) => TilePos(tile_x, tile_y),
+                    _ => continue,
+                };
+                if let Some(tile) = clipboard.get(TilePos(x, y)) {
+                    // Ignore out of bounds errors.
+                    let _ = self.set_tile(commands, tile_pos, *tile, map_id, layer_id);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use crate::test_util::{layer_settings, new_world, run_system, spawn_layer};
+
+    struct Copied(TileClipboard);
+
+    fn copy(mut commands: Commands, map_query: MapQuery, tile_query: Query<&Tile>) {
+        let clipboard =
+            map_query.copy_region(&tile_query, TilePos(0, 0), TilePos(3, 3), 0u16, 0u16);
+        commands.insert_resource(Copied(clipboard));
+    }
+
+    // Pastes across the chunk boundaries at x = 4 and y = 4, and past the end of the positions.
+    fn paste(mut commands: Commands, mut map_query: MapQuery, copied: Res<Copied>) {
+        map_query.paste_region(&mut commands, &copied.0, TilePos(3, 3), 0u16, 0u16);
+        let end = TilePos(u32::MAX - 1, u32::MAX - 1);
+        map_query.paste_region(&mut commands, &copied.0, end, 0u16, 0u16);
+    }
+
+    #[test]
+    fn paste_across_chunks() {
+        let mut world = new_world();
+        let settings = layer_settings(MapSize(2, 2), ChunkSize(4, 4));
+        spawn_layer(&mut world, settings, Handle::default(), |layer_builder| {
+            for x in 0..3 {
+                for y in 0..3 {
+                    let tile = Tile {
+                        texture_index: (1 + x + y * 3) as u16,
+                        ..Default::default()
+                    };
+                    layer_builder.set_tile(TilePos(x, y), tile.into()).unwrap();
+                }
+            }
+        });
+        run_system(&mut world, copy.system());
+        assert_eq!(world.get_resource::<Copied>().unwrap().0.tiles.len(), 9);
+        run_system(&mut world, paste.system());
+
+        let pasted: Vec<(Entity, TilePos, u16, Entity)> = world
+            .query::<(Entity, &TilePos, &Tile, &TileParent)>()
+            .iter(&world)
+            .filter(|(_, tile_pos, ..)| tile_pos.0 >= 3 && tile_pos.1 >= 3)
+            .map(|(tile_entity, tile_pos, tile, tile_parent)| {
+                (
+                    tile_entity,
+                    *tile_pos,
+                    tile.texture_index,
+                    tile_parent.chunk,
+                )
+            })
+            .collect();
+        assert_eq!(pasted.len(), 9);
+        for (tile_entity, tile_pos, texture_index, chunk_entity) in pasted {
+            let (x, y) = (tile_pos.0 - 3, tile_pos.1 - 3);
+            assert_eq!(texture_index, (1 + x + y * 3) as u16);
+            // Each tile belongs to the chunk it landed in.
+            let chunk = world.get::<Chunk>(chunk_entity).unwrap();
+            assert_eq!(chunk.position, ChunkPos(tile_pos.0 / 4, tile_pos.1 / 4));
+            assert_eq!(
+                chunk.get_tile_entity(chunk.to_chunk_pos(tile_pos)),
+                Some(tile_entity)
+            );
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::layer::LayerId;
+use crate::map::MapId;
+use crate::map_query::MapQuery;
+use crate::tile::Tile;
+use crate::TilePos;
+use bevy::{prelude::*, sprite::Rect};
+
+/// How `MapQuery::collision_rects` turns solid tiles into rectangles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColliderMerge {
+    /// A rectangle for every solid tile.
+    PerTile,
+    /// Solid tiles are merged into as few rectangles as possible by growing each rectangle
+    /// along the x axis first and then along the y axis.
+    Greedy,
+}
+
+impl<'a> MapQuery<'a> {
+    /// Builds collision rectangles from the tiles of a layer for which `is_solid` returns true,
+    /// to be used as colliders by a physics engine.
+    ///
+    /// The rectangles are in tiles, where the tile at (x, y) covers (x, y) to (x + 1, y + 1).
+    /// Multiply them by `LayerSettings::grid_size` for pixels in square layers.
+    /// Returns no rectangles if the layer doesn't exist.
+    pub fn collision_rects<F>(
+        &self,
+        tile_query: &Query<&Tile>,
+        is_solid: F,
+        merge: ColliderMerge,
+        map_id: impl MapId,
+        layer_id: impl LayerId,
+    ) -> Vec<Rect>
+    where
+        F: Fn(&Tile) -> bool,
+    {
+        let map_id = map_id.into();
+        let layer_id = layer_id.into();
+        let layer_size = match self.get_layer(map_id, layer_id) {
+            Some((_, layer)) => layer.get_layer_size_in_tiles(),
+            None => return Vec::new(),
+        };
+        let (width, height) = (layer_size.0 as usize, layer_size.1 as usize);
+
+        let mut solid = vec![false; width * height];
+        let end = TilePos(layer_size.0, layer_size.1);
+        for (tile_pos, tile) in self.tiles_in_rect(tile_query, TilePos(0, 0), end, map_id, layer_id)
+        {
+            if is_solid(tile) {
+                solid[tile_pos.1 as usize * width + tile_pos.0 as usize] = true;
+            }
+        }
+
+        merge_colliders(&solid, width, merge)
+    }
+}
+
+/// Turns a grid of solid tiles into collision rectangles, the way `MapQuery::collision_rects`
+/// does with the tiles of a layer.
+///
+/// `solid` is in row major order starting with the bottom left tile, `width` tiles per row.
+/// The rectangles are in tiles, where the tile at (x, y) covers (x, y) to (x + 1, y + 1).
+/// ```
+/// use bevy::{math::Vec2, sprite::Rect};
+/// use bevy_ecs_tilemap::prelude::*;
+///
+/// // The bottom row comes first.
+/// let solid = [
+///     true, true, true, false,
+///     true, true, false, false,
+///     true, true, false, true,
+/// ];
+/// assert_eq!(merge_colliders(&solid, 4, ColliderMerge::PerTile).len(), 8);
+///
+/// let extents: Vec<(Vec2, Vec2)> = merge_colliders(&solid, 4, ColliderMerge::Greedy)
+///     .iter()
+///     .map(|rect: &Rect| (rect.min, rect.max))
+///     .collect();
+/// assert_eq!(
+///     extents,
+///     vec![
+///         (Vec2::new(0.0, 0.0), Vec2::new(3.0, 1.0)),
+///         (Vec2::new(0.0, 1.0), Vec2::new(2.0, 3.0)),
+///         (Vec2::new(3.0, 2.0), Vec2::new(4.0, 3.0)),
+///     ]
+/// );
+/// ```
+pub fn merge_colliders(solid: &[bool], width: usize, merge: ColliderMerge) -> Vec<Rect> {
+    if width == 0 {
+        return Vec::new();
+    }
+    let height = solid.len() / width;
+    let rect = |x: usize, y: usize, rect_width: usize, rect_height: usize| Rect {
+        min: Vec2::new(x as f32, y as f32),
+        max: Vec2::new((x + rect_width) as f32, (y + rect_height) as f32),
+    };
+
+    let mut rects = Vec::new();
+    match merge {
+        ColliderMerge::PerTile => {
+            for (index, _) in solid.iter().enumerate().filter(|(_, solid)| **solid) {
+                rects.push(rect(index % width, index / width, 1, 1));
+            }
+        }
+        ColliderMerge::Greedy => {
+            let mut solid = solid[..width * height].to_vec();
+            for y in 0..height {
+                for x in 0..width {
+                    if !solid[y * width + x] {
+                        continue;
+                    }
+
+                    let mut rect_width = 1;
+                    while x + rect_width < width && solid[y * width + x + rect_width] {
+                        rect_width += 1;
+                    }
+                    let mut rect_height = 1;
+                    while y + rect_height < height
+                        && solid[(y + rect_height) * width + x..][..rect_width]
+                            .iter()
+                            .all(|solid| *solid)
+                    {
+                        rect_height += 1;
+                    }
+
+                    // Merged tiles are cleared so they aren't part of another rectangle.
+                    for row in y..y + rect_height {
+                        for cell in solid[row * width + x..][..rect_width].iter_mut() {
+                            *cell = false;
+                        }
+                    }
+                    rects.push(rect(x, y, rect_width, rect_height));
+                }
+            }
+        }
+    }
+    rects
+}
@@ -0,0 +1,303 @@
+use crate::prelude::*;
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+};
+
+/// Adds the `.csv` asset loader and the system which turns loaded CSV maps into layers.
+#[derive(Default)]
+pub struct CsvMapPlugin;
+
+impl Plugin for CsvMapPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_asset::<CsvMap>()
+            .add_asset_loader(CsvLoader)
+            .add_system(process_loaded_csv_maps.system());
+    }
+}
+
+/// A grid of texture indices loaded from a `.csv` file, the quickest way to author simple
+/// levels in a spreadsheet or text editor.
+///
+/// Every line is a row of tiles with the first line at the top of the layer. Cells are
+/// separated by commas, or by whitespace in lines without a comma. Empty cells and negative
+/// numbers like `-1` are left without a tile.
+/// ```
+/// use bevy_ecs_tilemap::prelude::*;
+///
+/// let csv_map = CsvMap::parse("0,1,2\n-1,,5\n").unwrap();
+/// assert_eq!((csv_map.width, csv_map.height), (3, 2));
+/// assert_eq!(csv_map.get(TilePos(0, 1)), Some(0));
+/// assert_eq!(csv_map.get(TilePos(2, 1)), Some(2));
+/// assert_eq!(csv_map.get(TilePos(0, 0)), None);
+/// assert_eq!(csv_map.get(TilePos(1, 0)), None);
+/// assert_eq!(csv_map.get(TilePos(2, 0)), Some(5));
+/// assert_eq!(csv_map.get(TilePos(3, 0)), None);
+///
+/// let csv_map = CsvMap::parse("3 3\n4 -1\n").unwrap();
+/// assert_eq!(csv_map.get(TilePos(0, 0)), Some(4));
+///
+/// // Every row needs the same number of cells.
+/// assert!(CsvMap::parse("0,1,2\n3,4\n").is_err());
+/// assert!(CsvMap::parse("0,a\n").is_err());
+/// assert!(CsvMap::parse("\n").is_err());
+/// ```
+#[derive(Debug, Clone, TypeUuid)]
+#[uuid = "0b7b4e3c-6a3d-4b8e-9f53-2c1d8e7a5f14"]
+pub struct CsvMap {
+    /// The number of cells in each row.
+    pub width: u32,
+    /// The number of rows.
+    pub height: u32,
+    /// The texture indices of the cells, row by row from the top left.
+    pub tiles: Vec<Option<u16>>,
+}
+
+impl CsvMap {
+    /// Parses a grid of texture indices, inferring its size from the rows.
+    /// Returns an error for rows with a different number of cells than the first row, cells
+    /// which aren't numbers and grids without any rows.
+    /// ```
+    /// use bevy_ecs_tilemap::prelude::*;
+    ///
+    /// let csv_map = CsvMap::parse(include_str!("../assets/map.csv")).unwrap();
+    /// assert_eq!((csv_map.width, csv_map.height), (16, 10));
+    /// // The last row of the file is the bottom row of the layer.
+    /// assert_eq!(csv_map.get(TilePos(0, 0)), Some(1));
+    /// assert_eq!(csv_map.get(TilePos(2, 7)), Some(2));
+    /// assert_eq!(csv_map.get(TilePos(12, 2)), Some(2));
+    /// assert_eq!(csv_map.get(TilePos(6, 4)), Some(5));
+    /// assert_eq!(csv_map.get(TilePos(7, 8)), None);
+    /// ```
+    pub fn parse(text: &str) -> Result<Self, anyhow::Error> {
+        let mut width = None;
+        let mut height = 0;
+        let mut tiles = Vec::new();
+
+        let rows = text
+            .trim_start_matches('\u{feff}')
+            .lines()
+            .map(str::trim)
+            .filter(|row| !row.is_empty());
+        for (row_index, row) in rows.enumerate() {
+            let cells: Vec<&str> = if row.contains(',') {
+                row.split(',').map(str::trim).collect()
+            } else {
+                row.split_whitespace().collect()
+            };
+
+            let row_width = *width.get_or_insert(cells.len());
+            if cells.len() != row_width {
+                anyhow::bail!(
+                    "row {} has {} cells but the first row has {}",
+                    row_index + 1,
+                    cells.len(),
+                    row_width
+                );
+            }
+
+            for (column, cell) in cells.into_iter().enumerate() {
+                if cell.is_empty() {
+                    tiles.push(None);
+                    continue;
+                }
+                let index: i64 = cell.parse().map_err(|_| {
+                    anyhow::anyhow!(
+                        "cell {} of row {} isn't a number: {:?}",
+                        column + 1,
+                        row_index + 1,
+                        cell
+                    )
+                })?;
+                if index > u16::MAX as i64 {
+                    anyhow::bail!(
+                        "cell {} of row {} is larger than a texture index: {}",
+                        column + 1,
+                        row_index + 1,
+                        index
+                    );
+                }
+                tiles.push(if index < 0 { None } else { Some(index as u16) });
+            }
+            height += 1;
+        }
+
+        let width = match width {
+            Some(width) if width > 0 => width as u32,
+            _ => anyhow::bail!("the map has no tiles"),
+        };
+        Ok(Self {
+            width,
+            height,
+            tiles,
+        })
+    }
+
+    /// Gets the texture index at a tile position, where (0, 0) is the bottom left cell.
+    /// Returns `None` for positions outside of the grid and cells without a tile.
+    pub fn get(&self, tile_pos: TilePos) -> Option<u16> {
+        if tile_pos.0 >= self.width || tile_pos.1 >= self.height {
+            return None;
+        }
+        let row = self.height - 1 - tile_pos.1;
+        self.tiles[(row * self.width + tile_pos.0) as usize]
+    }
+}
+
+/// Controls how a CSV map is turned into a layer. The CSV only holds texture indices, so the
+/// tile and texture sizes come from here.
+pub struct CsvMapConfig {
+    /// The material drawing the layer.
+    pub material: Handle<ColorMaterial>,
+    /// The id of the layer built from the map.
+    pub layer_id: u16,
+    /// Size in tiles of each chunk, the number of chunks is picked to fit the map.
+    pub chunk_size: ChunkSize,
+    /// Size in pixels of each tile.
+    pub tile_size: TileSize,
+    /// Size in pixels of the material's texture.
+    pub texture_size: TextureSize,
+}
+
+impl Default for CsvMapConfig {
+    fn default() -> Self {
+        Self {
+            material: Handle::default(),
+            layer_id: 0,
+            chunk_size: ChunkSize(64, 64),
+            tile_size: TileSize::default(),
+            texture_size: TextureSize::default(),
+        }
+    }
+}
+
+/// Spawn this bundle on a map entity to build a layer from a CSV map.
+#[derive(Default, Bundle)]
+pub struct CsvMapBundle {
+    pub csv_map: Handle<CsvMap>,
+    pub map: Map,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub csv_map_config: CsvMapConfig,
+}
+
+pub struct CsvLoader;
+
+impl AssetLoader for CsvLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let csv_map = CsvMap::parse(std::str::from_utf8(bytes)?)?;
+            load_context.set_default_asset(LoadedAsset::new(csv_map));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        static EXTENSIONS: &[&str] = &["csv"];
+        EXTENSIONS
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn process_loaded_csv_maps(
+    mut commands: Commands,
+    mut map_events: EventReader<AssetEvent<CsvMap>>,
+    maps: Res<Assets<CsvMap>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut query: Query<(&Handle<CsvMap>, &CsvMapConfig, &mut Map)>,
+    new_maps: Query<&Handle<CsvMap>, Added<Handle<CsvMap>>>,
+    layer_query: Query<&Layer>,
+    chunk_query: Query<&Chunk>,
+) {
+    let mut changed_maps = Vec::<Handle<CsvMap>>::default();
+    for event in map_events.iter() {
+        match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                changed_maps.push(handle.clone());
+            }
+            AssetEvent::Removed { handle } => {
+                // if the map was modified and removed in the same update, ignore the modification
+                // events are ordered so future modification events are ok
+                changed_maps = changed_maps
+                    .into_iter()
+                    .filter(|changed_handle| changed_handle != handle)
+                    .collect();
+            }
+        }
+    }
+
+    // If we have new map entities add them to the changed_maps list.
+    for new_map_handle in new_maps.iter() {
+        changed_maps.push(new_map_handle.clone());
+    }
+
+    for changed_map in changed_maps.iter() {
+        for (map_handle, config, mut map) in query.iter_mut() {
+            // only deal with currently changed map
+            if map_handle != changed_map {
+                continue;
+            }
+            let csv_map = match maps.get(map_handle) {
+                Some(csv_map) => csv_map,
+                None => continue,
+            };
+
+            // Despawn the layer built from the previous version of the map.
+            if let Some(layer_entity) = map.get_layer_entity(config.layer_id) {
+                if let Ok(layer) = layer_query.get(*layer_entity) {
+                    for (_, chunk_entity) in layer.iter_chunks() {
+                        if let Ok(chunk) = chunk_query.get(chunk_entity) {
+                            for tile_entity in chunk.tiles.iter().flatten() {
+                                commands.entity(*tile_entity).despawn_recursive();
+                            }
+                        }
+                        commands.entity(chunk_entity).despawn_recursive();
+                    }
+                }
+                map.remove_layer(&mut commands, config.layer_id);
+            }
+
+            let chunk_size = config.chunk_size;
+            let mut settings = LayerSettings::new(
+                MapSize(
+                    (csv_map.width + chunk_size.0 - 1) / chunk_size.0,
+                    (csv_map.height + chunk_size.1 - 1) / chunk_size.1,
+                ),
+                chunk_size,
+                config.tile_size,
+                config.texture_size,
+            );
+            settings.set_layer_id(config.layer_id);
+
+            let layer_entity = LayerBuilder::<TileBundle>::new_batch(
+                &mut commands,
+                settings,
+                &mut meshes,
+                config.material.clone(),
+                map.id,
+                config.layer_id,
+                None,
+                |tile_pos| {
+                    csv_map.get(tile_pos).map(|texture_index| {
+                        Tile {
+                            texture_index,
+                            ..Default::default()
+                        }
+                        .into()
+                    })
+                },
+            );
+            commands.entity(layer_entity).insert(Transform::from_xyz(
+                0.0,
+                0.0,
+                settings.get_layer_depth(),
+            ));
+            map.add_layer(&mut commands, config.layer_id, layer_entity);
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use crate::layer::MapTileError;
+use crate::neighbors::neighbor_positions;
+use crate::{
+    chunk_to_tile, morton_index, morton_pos, round_to_power_of_two, tile_to_chunk, ChunkPos,
+    ChunkSize, LayerSettings, MapSize, TilePos, TilemapMeshType,
+};
+
+/// A grid with a value per tile which isn't drawn, for gameplay data like height maps or path
+/// costs that should stay aligned with a tilemap layer.
+///
+/// The values are stored in chunks the same way as the tiles of a layer built from the same
+/// `LayerSettings`, and neighbors follow the layer's mesh type and `wrap`. It can be inserted as a
+/// component or a resource.
+/// ```
+/// use bevy_ecs_tilemap::prelude::*;
+///
+/// let settings = LayerSettings::new(
+///     MapSize(2, 2),
+///     ChunkSize(8, 8),
+///     TileSize(16.0, 16.0),
+///     TextureSize(96.0, 16.0),
+/// );
+/// let mut costs = DataLayer::<u8>::new(&settings);
+/// assert_eq!(costs.get(TilePos(3, 4)), Some(&0));
+///
+/// costs.set(TilePos(3, 4), 5).unwrap();
+/// costs.set(TilePos(8, 15), 7).unwrap();
+/// assert_eq!(costs.get(TilePos(3, 4)), Some(&5));
+/// assert_eq!(costs.get(TilePos(8, 15)), Some(&7));
+/// *costs.get_mut(TilePos(3, 4)).unwrap() += 1;
+/// assert_eq!(costs.get(TilePos(3, 4)), Some(&6));
+///
+/// // Positions outside of the layer have no value.
+/// assert_eq!(costs.get(TilePos(16, 0)), None);
+/// assert!(matches!(costs.set(TilePos(0, 16), 1), Err(MapTileError::OutOfBounds)));
+///
+/// assert_eq!(costs.iter().count(), 16 * 16);
+/// assert_eq!(costs.iter().map(|(_, cost)| *cost as u32).sum::<u32>(), 13);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DataLayer<T> {
+    map_size: MapSize,
+    chunk_size: ChunkSize,
+    mesh_type: TilemapMeshType,
+    wrap: bool,
+    chunks: Vec<Vec<T>>,
+}
+
+impl<T: Clone + Default> DataLayer<T> {
+    /// Creates a data layer the size of a layer built from `settings`, with a default value for
+    /// every tile.
+    pub fn new(settings: &LayerSettings) -> Self {
+        let map_size = round_to_power_of_two(settings.map_size.0 as f32)
+            .max(round_to_power_of_two(settings.map_size.1 as f32));
+        let chunk_size = round_to_power_of_two(settings.chunk_size.0 as f32)
+            .max(round_to_power_of_two(settings.chunk_size.1 as f32));
+
+        let mut chunks = vec![Vec::new(); map_size * map_size];
+        for y in 0..settings.map_size.1 {
+            for x in 0..settings.map_size.0 {
+                chunks[morton_index(ChunkPos(x, y))] = vec![T::default(); chunk_size * chunk_size];
+            }
+        }
+
+        Self {
+            map_size: settings.map_size,
+            chunk_size: settings.chunk_size,
+            mesh_type: settings.mesh_type,
+            wrap: settings.wrap,
+            chunks,
+        }
+    }
+}
+
+impl<T> DataLayer<T> {
+    /// Gets the size of the data layer in tiles.
+    pub fn get_layer_size_in_tiles(&self) -> MapSize {
+        MapSize(
+            self.map_size.0 * self.chunk_size.0,
+            self.map_size.1 * self.chunk_size.1,
+        )
+    }
+
+    /// Gets the value of a tile, `None` if the position is outside of the layer.
+    pub fn get(&self, tile_pos: TilePos) -> Option<&T> {
+        let (chunk_index, index) = self.get_index(tile_pos)?;
+        self.chunks[chunk_index].get(index)
+    }
+
+    /// Gets the value of a tile mutably, `None` if the position is outside of the layer.
+    pub fn get_mut(&mut self, tile_pos: TilePos) -> Option<&mut T> {
+        let (chunk_index, index) = self.get_index(tile_pos)?;
+        self.chunks[chunk_index].get_mut(index)
+    }
+
+    /// Sets the value of a tile.
+    /// Returns an error if the position is outside of the layer.
+    pub fn set(&mut self, tile_pos: TilePos, value: T) -> Result<(), MapTileError> {
+        let stored = self.get_mut(tile_pos).ok_or(MapTileError::OutOfBounds)?;
+        *stored = value;
+        Ok(())
+    }
+
+    /// Retrieves the values of the neighbors of a tile along with their positions, in the same
+    /// order as `MapQuery::get_tile_neighbors` for the layer's mesh type.
+    /// None is returned for neighbors outside of the layer, unless the layer wraps.
+    /// ```
+    /// use bevy_ecs_tilemap::prelude::*;
+    ///
+    /// let settings = LayerSettings::new(
+    ///     MapSize(2, 2),
+    ///     ChunkSize(8, 8),
+    ///     TileSize(16.0, 16.0),
+    ///     TextureSize(96.0, 16.0),
+    /// );
+    /// let mut heights = DataLayer::<u8>::new(&settings);
+    /// heights.set(TilePos(7, 8), 1).unwrap(); // N
+    /// heights.set(TilePos(8, 7), 2).unwrap(); // E, in the next chunk.
+    ///
+    /// // N, S, W, E, NW, NE, SW, SE
+    /// let neighbors = heights.get_neighbors(TilePos(7, 7));
+    /// assert_eq!(neighbors[0], Some((TilePos(7, 8), &1)));
+    /// assert_eq!(neighbors[3], Some((TilePos(8, 7), &2)));
+    /// assert_eq!(neighbors[1], Some((TilePos(7, 6), &0)));
+    ///
+    /// // Neighbors past the edges of the layer don't exist.
+    /// let neighbors = heights.get_neighbors(TilePos(0, 15));
+    /// assert_eq!(neighbors[0], None);
+    /// assert_eq!(neighbors[2], None);
+    /// assert_eq!(neighbors[1], Some((TilePos(0, 14), &0)));
+    ///
+    /// // Wrapping layers continue on the other side.
+    /// let mut settings = settings;
+    /// settings.wrap = true;
+    /// let mut heights = DataLayer::<u8>::new(&settings);
+    /// heights.set(TilePos(0, 3), 4).unwrap();
+    /// let neighbors = heights.get_neighbors(TilePos(15, 3));
+    /// assert_eq!(neighbors[3], Some((TilePos(0, 3), &4)));
+    /// let neighbors = heights.get_neighbors(TilePos(0, 15));
+    /// assert_eq!(neighbors[0], Some((TilePos(0, 0), &0)));
+    /// assert_eq!(neighbors[2], Some((TilePos(15, 15), &0)));
+    /// ```
+    pub fn get_neighbors(&self, tile_pos: TilePos) -> Vec<Option<(TilePos, &T)>> {
+        self.get_neighbor_values(tile_pos, true)
+    }
+
+    /// Retrieves the values of the neighbors which share an edge with the tile, in the same
+    /// order as `MapQuery::get_tile_orthogonal_neighbors` for the layer's mesh type.
+    pub fn get_orthogonal_neighbors(&self, tile_pos: TilePos) -> Vec<Option<(TilePos, &T)>> {
+        self.get_neighbor_values(tile_pos, false)
+    }
+
+    /// Iterates over the position and value of every tile, in the order they are stored.
+    pub fn iter(&self) -> impl Iterator<Item = (TilePos, &T)> {
+        let chunk_size = self.chunk_size;
+        self.chunks
+            .iter()
+            .enumerate()
+            .flat_map(move |(chunk_index, values)| {
+                let chunk_pos = morton_pos(chunk_index);
+                let chunk_pos = ChunkPos(chunk_pos.x, chunk_pos.y);
+                // Chunks are stored in squares with a power of two size, skip the padding.
+                values
+                    .iter()
+                    .enumerate()
+                    .filter(move |(index, _)| {
+                        let local_pos = morton_pos(*index);
+                        local_pos.x < chunk_size.0 && local_pos.y < chunk_size.1
+                    })
+                    .map(move |(index, value)| (chunk_to_tile(chunk_pos, index, chunk_size), value))
+            })
+    }
+
+    fn get_neighbor_values(
+        &self,
+        tile_pos: TilePos,
+        diagonals: bool,
+    ) -> Vec<Option<(TilePos, &T)>> {
+        let wrap_size = self.wrap.then(|| self.get_layer_size_in_tiles());
+        neighbor_positions(tile_pos, self.mesh_type, diagonals, wrap_size)
+            .into_iter()
+            .map(|pos| pos.and_then(|pos| self.get(pos).map(|value| (pos, value))))
+            .collect()
+    }
+
+    // Gets the index of the chunk and the index of the tile in the chunk.
+    fn get_index(&self, tile_pos: TilePos) -> Option<(usize, usize)> {
+        let size = self.get_layer_size_in_tiles();
+        if tile_pos.0 >= size.0 || tile_pos.1 >= size.1 {
+            return None;
+        }
+        let (chunk_pos, index) = tile_to_chunk(tile_pos, self.chunk_size);
+        Some((morton_index(chunk_pos), index))
+    }
+}
@@ -0,0 +1,298 @@
+use crate::{tile::TileParent, Chunk, Layer, Tile, TilePos};
+use bevy::{
+    prelude::*,
+    render::pipeline::{PipelineCompiler, PipelineDescriptor, PolygonMode, RenderPipeline},
+};
+use std::collections::{HashMap, HashSet};
+
+/// Debug rendering options for every tilemap chunk.
+///
+/// Setting `wireframe` draws the outline of every tile triangle instead of the tiles, which shows
+/// the tile grid and where chunks end.
+///
+/// Setting `tile_labels` to a font draws the `TilePos` of every tile at the center of its cell,
+/// which helps with checking coordinate math like picking tiles in isometric layers. The labels
+/// are spawned once per tile as children of the layer, see `LayerSettings::get_tile_center`.
+/// Note: Hex layers aren't labeled.
+/// Note: Line rendering needs the `NonFillPolygonMode` wgpu feature:
+/// ```ignore
+/// app.insert_resource(WgpuOptions {
+///     features: WgpuFeatures {
+///         features: vec![WgpuFeature::NonFillPolygonMode],
+///     },
+///     ..Default::default()
+/// })
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct TilemapDebug {
+    pub wireframe: bool,
+    pub tile_labels: Option<Handle<Font>>,
+}
+
+// The pipelines of a chunk from before it was switched to wireframe rendering.
+pub(crate) struct WireframePipelines(Vec<RenderPipeline>);
+
+// Swaps the pipelines of chunks for line rendering copies while wireframe rendering is enabled.
+// Pipelines swapped by other systems while it's enabled, like `apply_tilemap_material`, replace
+// the ones restored once it's disabled.
+pub(crate) fn update_chunk_wireframe(
+    mut commands: Commands,
+    debug: Res<TilemapDebug>,
+    mut wireframe_pipelines: Local<HashMap<Handle<PipelineDescriptor>, Handle<PipelineDescriptor>>>,
+    mut pipelines: ResMut<Assets<PipelineDescriptor>>,
+    mut chunk_query: Query<
+        (Entity, &mut RenderPipelines, Option<&WireframePipelines>),
+        With<Chunk>,
+    >,
+) {
+    for (chunk_entity, mut render_pipelines, original) in chunk_query.iter_mut() {
+        let is_wireframe = render_pipelines.pipelines.iter().all(|render_pipeline| {
+            wireframe_pipelines
+                .values()
+                .any(|wireframe| *wireframe == render_pipeline.pipeline)
+        });
+        match (debug.wireframe, original) {
+            (true, Some(_)) if is_wireframe => {}
+            (true, _) => {
+                let original = render_pipelines.pipelines.clone();
+                for render_pipeline in render_pipelines.pipelines.iter_mut() {
+                    let handle = &render_pipeline.pipeline;
+                    if let Some(wireframe) = wireframe_pipelines.get(handle) {
+                        render_pipeline.pipeline = wireframe.clone();
+                    } else if let Some(descriptor) = pipelines.get(handle) {
+                        let mut descriptor = descriptor.clone();
+                        descriptor.primitive.polygon_mode = PolygonMode::Line;
+                        let wireframe = pipelines.add(descriptor);
+                        wireframe_pipelines.insert(handle.clone(), wireframe.clone());
+                        render_pipeline.pipeline = wireframe;
+                    }
+                }
+                commands
+                    .entity(chunk_entity)
+                    .insert(WireframePipelines(original));
+            }
+            (false, Some(original)) => {
+                render_pipelines.pipelines = original.0.clone();
+                commands.entity(chunk_entity).remove::<WireframePipelines>();
+            }
+            _ => {}
+        }
+    }
+}
+
+// Marks the text entities spawned for `TilemapDebug::tile_labels`.
+pub(crate) struct TileLabel;
+
+// Spawns a label for every tile while tile labels are enabled and despawns them once they are
+// disabled or their tile is removed.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn update_tile_labels(
+    mut commands: Commands,
+    debug: Res<TilemapDebug>,
+    mut labels: Local<HashMap<Entity, Entity>>,
+    tile_query: Query<(Entity, &TilePos, &TileParent)>,
+    new_tile_query: Query<Entity, Added<Tile>>,
+    removed_tiles: RemovedComponents<Tile>,
+    chunk_query: Query<&Chunk>,
+    layer_query: Query<&Layer>,
+    label_query: Query<(), With<TileLabel>>,
+) {
+    let despawn_label = |commands: &mut Commands, label: Entity| {
+        // Labels are already gone when their layer was despawned.
+        if label_query.get(label).is_ok() {
+            commands.entity(label).despawn_recursive();
+        }
+    };
+    for tile_entity in removed_tiles.iter() {
+        if let Some(label) = labels.remove(&tile_entity) {
+            despawn_label(&mut commands, label);
+        }
+    }
+    if debug.is_changed() {
+        for (_, label) in labels.drain() {
+            despawn_label(&mut commands, label);
+        }
+    }
+
+    let font = match &debug.tile_labels {
+        Some(font) => font,
+        None => return,
+    };
+    let new_tiles: Vec<Entity> = if debug.is_changed() {
+        tile_query
+            .iter()
+            .map(|(tile_entity, ..)| tile_entity)
+            .collect()
+    } else {
+        new_tile_query.iter().collect()
+    };
+    for tile_entity in new_tiles {
+        let (tile_pos, tile_parent) = match tile_query.get(tile_entity) {
+            Ok((_, tile_pos, tile_parent)) => (tile_pos, tile_parent),
+            Err(_) => continue,
+        };
+        // The chunk's `map_entity` is the layer it was built in.
+        let layer_entity = match chunk_query.get(tile_parent.chunk) {
+            Ok(chunk) => chunk.map_entity,
+            Err(_) => continue,
+        };
+        let settings = match layer_query.get(layer_entity) {
+            Ok(layer) => &layer.settings,
+            Err(_) => continue,
+        };
+        let center = match settings.get_tile_center(*tile_pos) {
+            Some(center) => center,
+            None => continue,
+        };
+
+        let label = commands
+            .spawn_bundle(Text2dBundle {
+                text: Text::with_section(
+                    format!("{},{}", tile_pos.0, tile_pos.1),
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: settings.grid_size.y / 3.0,
+                        color: Color::WHITE,
+                    },
+                    TextAlignment {
+                        vertical: VerticalAlign::Center,
+                        horizontal: HorizontalAlign::Center,
+                    },
+                ),
+                // In front of the tiles of the layer and the layers right above it.
+                transform: Transform::from_translation(center.extend(100.0)),
+                ..Default::default()
+            })
+            .insert(TileLabel)
+            .id();
+        commands.entity(layer_entity).push_children(&[label]);
+        labels.insert(tile_entity, label);
+    }
+}
+
+/// Counts of the tilemap entities drawn each frame, useful for profiling and debug overlays.
+/// Updated in the `TilemapStage` after chunk culling.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TilemapStats {
+    /// The number of chunks in every layer.
+    pub chunks: usize,
+    /// The number of chunks that weren't culled, each one is drawn with a single draw call.
+    pub visible_chunks: usize,
+    /// The number of tiles in every layer, including hidden tiles.
+    pub tiles: usize,
+    /// The number of distinct pipelines the chunks are drawn with.
+    pub pipelines: usize,
+    /// The number of specialized variants bevy compiled of those pipelines, for example one
+    /// per combination of shader defs. This should settle after the first frames of a map which
+    /// doesn't change, if it keeps growing the specialization of the chunks keeps changing and
+    /// their pipelines are recompiled instead of reused.
+    /// Note: This lags a frame behind, pipelines are compiled while drawing.
+    pub specialized_pipelines: usize,
+}
+
+pub(crate) fn update_tilemap_stats(
+    mut stats: ResMut<TilemapStats>,
+    pipeline_compiler: Option<Res<PipelineCompiler>>,
+    chunk_query: Query<(&Visible, &RenderPipelines), With<Chunk>>,
+    tile_query: Query<(), With<Tile>>,
+) {
+    let pipelines: HashSet<_> = chunk_query
+        .iter()
+        .flat_map(|(_, render_pipelines)| render_pipelines.pipelines.iter())
+        .map(|render_pipeline| render_pipeline.pipeline.clone_weak())
+        .collect();
+    let specialized_pipelines = pipeline_compiler.map_or(0, |pipeline_compiler| {
+        pipelines
+            .iter()
+            .filter_map(|pipeline| pipeline_compiler.iter_compiled_pipelines(pipeline.clone_weak()))
+            .map(|compiled_pipelines| compiled_pipelines.count())
+            .sum()
+    });
+
+    let new_stats = TilemapStats {
+        chunks: chunk_query.iter().count(),
+        visible_chunks: chunk_query
+            .iter()
+            .filter(|(visible, _)| visible.is_visible)
+            .count(),
+        tiles: tile_query.iter().count(),
+        pipelines: pipelines.len(),
+        specialized_pipelines,
+    };
+    // Only write when something changed, so `is_changed` can be used to update overlays.
+    if *stats != new_stats {
+        *stats = new_stats;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::new_world;
+    use bevy::{
+        ecs::schedule::{Schedule, SystemStage},
+        render::shader::ShaderStages,
+    };
+
+    fn polygon_modes(world: &mut World, chunk_entity: Entity) -> Vec<PolygonMode> {
+        let handles: Vec<Handle<PipelineDescriptor>> = world
+            .get::<RenderPipelines>(chunk_entity)
+            .unwrap()
+            .pipelines
+            .iter()
+            .map(|render_pipeline| render_pipeline.pipeline.clone())
+            .collect();
+        let pipelines = world.get_resource::<Assets<PipelineDescriptor>>().unwrap();
+        handles
+            .iter()
+            .map(|handle| pipelines.get(handle).unwrap().primitive.polygon_mode)
+            .collect()
+    }
+
+    #[test]
+    fn wireframe_follows_swapped_pipelines() {
+        let mut world = new_world();
+        world.insert_resource(TilemapDebug {
+            wireframe: true,
+            ..Default::default()
+        });
+
+        let mut pipelines = world
+            .get_resource_mut::<Assets<PipelineDescriptor>>()
+            .unwrap();
+        let descriptor = PipelineDescriptor::default_config(ShaderStages {
+            vertex: Handle::default(),
+            fragment: None,
+        });
+        let chunk_pipeline = pipelines.add(descriptor.clone());
+        let material_pipeline = pipelines.add(descriptor);
+        let chunk_entity = world
+            .spawn()
+            .insert(Chunk::default())
+            .insert(RenderPipelines::from_handles(&[chunk_pipeline]))
+            .id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage(
+            "wireframe",
+            SystemStage::single_threaded().with_system(update_chunk_wireframe.system()),
+        );
+        schedule.run(&mut world);
+        assert_eq!(polygon_modes(&mut world, chunk_entity), [PolygonMode::Line]);
+
+        // A material swaps the pipelines while the wireframe is shown.
+        world
+            .get_mut::<RenderPipelines>(chunk_entity)
+            .unwrap()
+            .pipelines = RenderPipelines::from_handles(&[material_pipeline.clone()]).pipelines;
+        schedule.run(&mut world);
+        assert_eq!(polygon_modes(&mut world, chunk_entity), [PolygonMode::Line]);
+
+        // Disabling the wireframe restores the material's pipelines.
+        world.get_resource_mut::<TilemapDebug>().unwrap().wireframe = false;
+        schedule.run(&mut world);
+        assert_eq!(polygon_modes(&mut world, chunk_entity), [PolygonMode::Fill]);
+        let render_pipelines = world.get::<RenderPipelines>(chunk_entity).unwrap();
+        assert_eq!(render_pipelines.pipelines[0].pipeline, material_pipeline);
+    }
+}
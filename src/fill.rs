@@ -0,0 +1,245 @@
+use crate::layer::{LayerId, MapTileError};
+use crate::map::MapId;
+use crate::map_query::MapQuery;
+use crate::tile::Tile;
+use crate::{ChunkPos, TilePos};
+use bevy::prelude::*;
+use std::collections::{HashSet, VecDeque};
+
+impl<'a> MapQuery<'a> {
+    /// Fills a section of the layer with new tiles, see `set_tile`.
+    ///
+    /// All tiles within the rectangle defined by
+    /// the `[start`, `stop`) positions will be filled.
+    /// This includes the `start` position, but not the `stop` position.
+    /// Positions outside of the layer are skipped. The tiles are written a chunk at a time, so
+    /// every chunk overlapping the rectangle is looked up and flagged for remeshing once.
+    /// ```
+    /// use bevy::prelude::*;
+    /// use bevy_ecs_tilemap::prelude::*;
+    ///
+    /// // Paves a road across the chunk boundaries, the part past the edge of the layer is
+    /// // skipped.
+    /// fn pave_road(mut commands: Commands, mut map_query: MapQuery) {
+    ///     let road = Tile {
+    ///         texture_index: 2,
+    ///         ..Default::default()
+    ///     };
+    ///     map_query.fill_rect(&mut commands, TilePos(2, 3), TilePos(20, 6), road, 0u16, 0u16);
+    /// }
+    /// ```
+    pub fn fill_rect(
+        &mut self,
+        commands: &mut Commands,
+        start: TilePos,
+        end: TilePos,
+        tile: Tile,
+        map_id: impl MapId,
+        layer_id: impl LayerId,
+    ) {
+        let layer_id = layer_id.into();
+        let layer = match self.get_layer(map_id, layer_id) {
+            Some((_, layer)) => layer,
+            None => return,
+        };
+        let layer_size = layer.get_layer_size_in_tiles();
+        let end = TilePos(end.0.min(layer_size.0), end.1.min(layer_size.1));
+        if start.0 >= end.0 || start.1 >= end.1 {
+            return;
+        }
+
+        let chunk_size = layer.settings.chunk_size;
+        let mut chunk_rects = Vec::new();
+        for chunk_y in start.1 / chunk_size.1..=(end.1 - 1) / chunk_size.1 {
+            for chunk_x in start.0 / chunk_size.0..=(end.0 - 1) / chunk_size.0 {
+                if let Some(chunk_entity) = layer.get_chunk(ChunkPos(chunk_x, chunk_y)) {
+                    let chunk_start = TilePos(chunk_x * chunk_size.0, chunk_y * chunk_size.1);
+                    let chunk_rect = (
+                        TilePos(start.0.max(chunk_start.0), start.1.max(chunk_start.1)),
+                        TilePos(
+                            end.0.min(chunk_start.0 + chunk_size.0),
+                            end.1.min(chunk_start.1 + chunk_size.1),
+                        ),
+                    );
+                    chunk_rects.push((chunk_entity, chunk_rect));
+                }
+            }
+        }
+
+        for (chunk_entity, (chunk_start, chunk_end)) in chunk_rects {
+            self.fill_chunk_rect(
+                commands,
+                chunk_entity,
+                chunk_start,
+                chunk_end,
+                tile,
+                layer_id,
+            );
+        }
+    }
+
+    /// Replaces the contiguous region of tiles which have the same texture index as the tile at
+    /// `start` with `tile`. Empty positions end the region.
+    ///
+    /// Tiles are connected to the neighbors returned by `get_tile_orthogonal_neighbors`, or by
+    /// `get_tile_neighbors` when `diagonals` is set.
+    /// The existing tile components are updated in place, so other components on the tile
+    /// entities are kept and only the chunks with changed tiles are remeshed.
+    /// Returns an error if there is no tile at `start`.
+    /// ```
+    /// use bevy::prelude::*;
+    /// use bevy_ecs_tilemap::prelude::*;
+    ///
+    /// // Repaints the floor of the room around the bottom left tile.
+    /// fn paint_room(mut tile_query: Query<&mut Tile>, map_query: MapQuery) {
+    ///     let floor = Tile {
+    ///         texture_index: 2,
+    ///         ..Default::default()
+    ///     };
+    ///     map_query
+    ///         .flood_fill(&mut tile_query, TilePos(0, 0), floor, false, 0u16, 0u16)
+    ///         .unwrap();
+    /// }
+    /// ```
+    pub fn flood_fill(
+        &self,
+        tile_query: &mut Query<&mut Tile>,
+        start: TilePos,
+        tile: Tile,
+        diagonals: bool,
+        map_id: impl MapId,
+        layer_id: impl LayerId,
+    ) -> Result<(), MapTileError> {
+        let map_id = map_id.into();
+        let layer_id = layer_id.into();
+        let start_entity = self.get_tile_entity(start, map_id, layer_id)?;
+        let texture_index = tile_query
+            .get_mut(start_entity)
+            .map_err(|_| MapTileError::NonExistent)?
+            .texture_index;
+
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(start);
+        queue.push_back((start, start_entity));
+
+        while let Some((tile_pos, tile_entity)) = queue.pop_front() {
+            match tile_query.get_mut(tile_entity) {
+                Ok(mut current) if current.texture_index == texture_index => *current = tile,
+                _ => continue,
+            }
+
+            let neighbors = self.get_neighbor_positions(tile_pos, map_id, layer_id, diagonals);
+            for neighbor_pos in neighbors.into_iter().flatten() {
+                if !visited.insert(neighbor_pos) {
+                    continue;
+                }
+                if let Ok(neighbor_entity) = self.get_tile_entity(neighbor_pos, map_id, layer_id) {
+                    queue.push_back((neighbor_pos, neighbor_entity));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::test_util::{layer_settings, new_world, run_system, spawn_layer};
+    use bevy::prelude::*;
+
+    fn meshed(mut chunks: Query<&mut Chunk>) {
+        for mut chunk in chunks.iter_mut() {
+            chunk.needs_remesh = false;
+        }
+    }
+
+    // A rectangle across the chunk boundaries, sticking out of the layer.
+    fn fill(mut commands: Commands, mut map_query: MapQuery) {
+        let tile = Tile {
+            texture_index: 2,
+            ..Default::default()
+        };
+        map_query.fill_rect(
+            &mut commands,
+            TilePos(2, 3),
+            TilePos(20, 6),
+            tile,
+            0u16,
+            0u16,
+        );
+    }
+
+    fn flood_fill(mut tile_query: Query<&mut Tile>, map_query: MapQuery) {
+        let tile = Tile {
+            texture_index: 2,
+            ..Default::default()
+        };
+        map_query
+            .flood_fill(&mut tile_query, TilePos(0, 0), tile, false, 0u16, 0u16)
+            .unwrap();
+    }
+
+    #[test]
+    fn fill_rect_remeshes_overlapping_chunks() {
+        let mut world = new_world();
+        let settings = layer_settings(MapSize(3, 3), ChunkSize(4, 4));
+        spawn_layer(&mut world, settings, Handle::default(), |_| {});
+        run_system(&mut world, meshed.system());
+        run_system(&mut world, fill.system());
+
+        let mut tiles: Vec<(u32, u32)> = world
+            .query::<(&TilePos, &Tile)>()
+            .iter(&world)
+            .map(|(tile_pos, tile)| {
+                assert_eq!(tile.texture_index, 2);
+                (tile_pos.0, tile_pos.1)
+            })
+            .collect();
+        tiles.sort_unstable();
+        let expected: Vec<(u32, u32)> = (2..12).flat_map(|x| (3..6).map(move |y| (x, y))).collect();
+        assert_eq!(tiles, expected);
+
+        // Only the chunks overlapping the rectangle are remeshed.
+        let mut remeshed: Vec<(u32, u32)> = world
+            .query::<&Chunk>()
+            .iter(&world)
+            .filter(|chunk| chunk.needs_remesh)
+            .map(|chunk| (chunk.position.0, chunk.position.1))
+            .collect();
+        remeshed.sort_unstable();
+        assert_eq!(
+            remeshed,
+            vec![(0, 0), (0, 1), (1, 0), (1, 1), (2, 0), (2, 1)]
+        );
+    }
+
+    #[test]
+    fn flood_fill_stops_at_other_tiles() {
+        let mut world = new_world();
+        // A layer of 2x2 chunks, split by a wall at x = 3 running through two of them.
+        let settings = layer_settings(MapSize(2, 2), ChunkSize(4, 4));
+        spawn_layer(&mut world, settings, Handle::default(), |layer_builder| {
+            layer_builder.set_all(TileBundle::default());
+            for y in 0..8 {
+                let wall = Tile {
+                    texture_index: 1,
+                    ..Default::default()
+                };
+                layer_builder.set_tile(TilePos(3, y), wall.into()).unwrap();
+            }
+        });
+        run_system(&mut world, flood_fill.system());
+
+        for (tile_pos, tile) in world.query::<(&TilePos, &Tile)>().iter(&world) {
+            let expected = match tile_pos.0 {
+                0..=2 => 2,
+                3 => 1,
+                _ => 0,
+            };
+            assert_eq!(tile.texture_index, expected, "{:?}", tile_pos);
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use crate::layer_builder::LayerBuilder;
+use crate::tile::TileBundleTrait;
+use crate::TilePos;
+
+impl<T> LayerBuilder<T>
+where
+    T: TileBundleTrait,
+{
+    /// Fills the whole layer with a checkerboard of two tiles, starting with `tile_a` at the
+    /// bottom left. Handy for getting something on screen or as a fixture for tests.
+    /// ```
+    /// use bevy::{ecs::system::CommandQueue, prelude::*};
+    /// use bevy_ecs_tilemap::prelude::*;
+    ///
+    /// let world = World::default();
+    /// let mut command_queue = CommandQueue::default();
+    /// let mut commands = Commands::new(&mut command_queue, &world);
+    /// let settings = LayerSettings::new(
+    ///     MapSize(1, 1),
+    ///     ChunkSize(4, 4),
+    ///     TileSize(16.0, 16.0),
+    ///     TextureSize(32.0, 16.0),
+    /// );
+    /// let (mut layer_builder, _) =
+    ///     LayerBuilder::<TileBundle>::new(&mut commands, settings, 0u16, 0u16, None);
+    ///
+    /// let tile = |texture_index| Tile { texture_index, ..Default::default() }.into();
+    /// layer_builder.fill_checkerboard(tile(0), tile(1));
+    /// for x in 0..4 {
+    ///     for y in 0..4 {
+    ///         let texture_index = layer_builder.get_tile(TilePos(x, y)).unwrap().tile.texture_index;
+    ///         assert_eq!(texture_index, ((x + y) % 2) as u16);
+    ///     }
+    /// }
+    /// ```
+    pub fn fill_checkerboard(&mut self, tile_a: T, tile_b: T) {
+        let (width, height) = self.size_in_tiles();
+        for x in 0..width {
+            for y in 0..height {
+                let tile = if (x + y) % 2 == 0 {
+                    tile_a.clone()
+                } else {
+                    tile_b.clone()
+                };
+                // Ignore fill errors.
+                let _ = self.set_tile(TilePos(x, y), tile);
+            }
+        }
+    }
+
+    /// Sets the tiles along the edges of the layer, leaving the tiles inside it untouched.
+    /// ```
+    /// use bevy::{ecs::system::CommandQueue, prelude::*};
+    /// use bevy_ecs_tilemap::prelude::*;
+    ///
+    /// let world = World::default();
+    /// let mut command_queue = CommandQueue::default();
+    /// let mut commands = Commands::new(&mut command_queue, &world);
+    /// let settings = LayerSettings::new(
+    ///     MapSize(1, 1),
+    ///     ChunkSize(4, 3),
+    ///     TileSize(16.0, 16.0),
+    ///     TextureSize(16.0, 16.0),
+    /// );
+    /// let (mut layer_builder, _) =
+    ///     LayerBuilder::<TileBundle>::new(&mut commands, settings, 0u16, 0u16, None);
+    ///
+    /// layer_builder.fill_border(TileBundle::default());
+    /// for x in 0..4 {
+    ///     for y in 0..3 {
+    ///         let edge = x == 0 || y == 0 || x == 3 || y == 2;
+    ///         assert_eq!(layer_builder.get_tile(TilePos(x, y)).is_ok(), edge);
+    ///     }
+    /// }
+    /// ```
+    pub fn fill_border(&mut self, tile: T) {
+        let (width, height) = self.size_in_tiles();
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.fill(TilePos(0, 0), TilePos(width, 1), tile.clone());
+        self.fill(TilePos(0, height - 1), TilePos(width, height), tile.clone());
+        self.fill(TilePos(0, 1), TilePos(1, height - 1), tile.clone());
+        self.fill(TilePos(width - 1, 1), TilePos(width, height - 1), tile);
+    }
+
+    fn size_in_tiles(&self) -> (u32, u32) {
+        (
+            self.settings.map_size.0 * self.settings.chunk_size.0,
+            self.settings.map_size.1 * self.settings.chunk_size.1,
+        )
+    }
+}
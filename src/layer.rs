@@ -1,15 +1,22 @@
 use crate::{
     chunk::Chunk,
     map::MapId,
-    morton_index,
+    morton_index, morton_pos,
     prelude::{ChunkMesher, Tile},
     round_to_power_of_two,
     tile::TileParent,
-    ChunkPos, ChunkSize, MapSize, TextureSize, TilePos, TileSize, TilemapMeshType,
+    ChunkPos, ChunkSize, IsoType, MapSize, TextureSize, TilePos, TileSize, TilemapAnchor,
+    TilemapBlendMode, TilemapMeshType, TilemapSampling, TilemapTextureMode, TilemapTopology,
 };
-use bevy::prelude::*;
+use bevy::{prelude::*, render::texture::TextureFormat};
 use std::hash::Hash;
 
+// The distance between the depths of neighboring chunks in a layer, large enough to stay apart
+// after the depth is subtracted from the camera's.
+const CHUNK_DEPTH_STEP: f32 = 1.0 / 8192.0;
+// The number of chunk depths, which keeps the depths of a layer's chunks below 0.5.
+const CHUNK_DEPTHS: usize = 4096;
+
 /// A bevy bundle which contains: Map, Transform, and GlobalTransform components.
 #[derive(Bundle, Default)]
 pub struct LayerBundle {
@@ -22,7 +29,8 @@ pub struct LayerBundle {
 }
 
 /// Various settings used to define the tilemap.
-#[derive(Debug, Default, Copy, Clone)]
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct LayerSettings {
     /// Size of the tilemap in chunks
     pub map_size: MapSize,
@@ -30,24 +38,136 @@ pub struct LayerSettings {
     pub chunk_size: ChunkSize,
     /// Size in pixels of each tile.
     pub tile_size: TileSize,
+    /// Size in world units tiles are drawn at, which decouples the resolution of the texture from
+    /// the scale of the world. Set `grid_size` in world units as well, for example both to 1.0
+    /// for tiles one unit wide no matter how many pixels the tiles of the texture have.
+    /// Tiles are drawn at `tile_size` when this is `None`.
+    /// Note: Only square layers draw their tiles at this size. Has to be set before the layer is
+    /// built.
+    pub tile_world_size: Option<Vec2>,
+    /// Offset of each tile from the bottom left corner of its cell on the grid, for example to
+    /// center tiles wider than the grid on their cell. Tiles taller than the grid overhang the
+    /// rows above them, combine with `y_sort` to keep them in front of those rows.
+    /// Note: Only square layers offset their tiles. Has to be set before the layer is built.
+    pub tile_offset: Vec2,
     /// Size in pixels of the grid(default is tile_size)
+    /// Note: Square layers place their tiles on the grid, tiles taller than the grid overlap
+    /// the row above them. Combine with `y_sort` to draw them in front of that row.
     pub grid_size: Vec2,
     /// Size in pixels of the tilemap texture.
     pub texture_size: TextureSize,
     /// The layer id associated with this map.
     pub layer_id: u16,
+    /// The z of the layer's transform when the layer is built, `None` uses the `layer_id`.
+    /// The chunks and y sorted tiles of a layer are drawn between its depth and its depth plus
+    /// one, so sprites at a z of `depth + 1.0` or above are drawn over the layer and the next
+    /// layer should be at least one above that. Higher depths are drawn in front, and the bevy
+    /// 2d camera sees depths from 0 up to just below 1000.
+    /// Has to be set before the layer is built, afterwards the layer's `Transform` can be moved.
+    pub depth: Option<f32>,
     /// The map id associated with this map.
     pub map_id: u16,
     /// The meshing algorithm used for the tilemap.
     pub mesh_type: TilemapMeshType,
+    /// How the layer is blended with what was drawn before it.
+    pub blend_mode: TilemapBlendMode,
+    /// Whether the layer's texture is an atlas or an array texture.
+    pub texture_mode: TilemapTextureMode,
+    /// Overrides the filtering of the layer texture's sampler when set.
+    /// The chunks are drawn with a copy of their material and texture with this sampler, so
+    /// other layers and sprites using the texture keep their own filtering. Changing it on the
+    /// `Layer` component only swaps the copy, for example for a smooth graphics option, the
+    /// chunks keep their pipelines and aren't remeshed. Setting it back to `None` draws the
+    /// chunks with their own material again.
+    pub sampling: Option<TilemapSampling>,
     /// Cull the chunks in the map when they are off screen.
     pub cull: bool,
     /// Spacing around each tile in the atlas
     /// Note: This is ignored in array mode.
     pub tile_spacing: Vec2,
+    /// Margin in pixels between the top left corner of the atlas and its first tile, like the
+    /// `margin` of a Tiled tileset. The spacing only applies between tiles.
+    /// Note: This is ignored in array mode.
+    pub tile_margin: Vec2,
+    /// Amount in texels the texture coordinates of each tile are inset by, which stops
+    /// neighboring atlas tiles from bleeding into each other. Half a texel is usually enough.
+    /// Note: This is ignored in array mode.
+    pub texture_padding: f32,
+    /// Opacity of the whole layer. Changing it on the `Layer` component only updates the
+    /// chunk uniforms, the chunks aren't remeshed.
+    pub alpha: f32,
+    /// Color multiplied with the colors of all tiles in the layer, to tint a whole layer like a
+    /// blue green underwater layer without changing every tile. Its alpha is ignored, see
+    /// `alpha`. Changing it on the `Layer` component only updates the chunk uniforms.
+    pub tint: Color,
+    /// Texels with an alpha below this are discarded when the `blend_mode` is
+    /// `TilemapBlendMode::AlphaMask`. Has to be set before the layer is built.
+    pub alpha_cutoff: f32,
+    /// Gives every tile a depth within the layer based on its row, so tiles further down the
+    /// map are drawn in front of the ones above them. Sprites can be sorted against the tiles
+    /// with `MapQuery::get_zindex_for_pixel_pos`.
+    /// Note: Isometric layers are always sorted like this. Has to be set before the layer is built.
+    pub y_sort: bool,
+    /// Which point of the layer is placed at the layer's transform.
+    /// Has to be set before the layer is built.
+    pub anchor: TilemapAnchor,
+    /// Samples the layer's texture upside down, for textures stored bottom row first which
+    /// otherwise show up with vertically flipped tiles. Texture index 0 is then the bottom left
+    /// tile of the image.
+    /// Note: `get_texture_rect` and `get_texture_uv_rect` don't apply this.
+    /// Has to be set before the layer is built.
+    pub flip_v: bool,
+    /// A tile drawn at every position of the layer without a tile, like a void or background
+    /// texture, empty positions aren't drawn when this is `None`.
+    /// Note: The default tile is only part of the chunk meshes, it doesn't have a tile entity.
+    /// Has to be set before the layer is built.
+    pub default_tile: Option<Tile>,
+    /// Snaps the corners of tiles to whole pixels of the primary window, which stops pixel art
+    /// tiles from shimmering when the camera is at a fractional position.
+    /// Has to be set before the layer is built.
+    pub pixel_snap: bool,
+    /// Scrolls the tiles of the layer by this many pixels per second, wrapping them around at
+    /// the edges of the layer, for parallax and drifting backgrounds that don't move the layer
+    /// entity or remesh it. Changing it on the `Layer` component continues from the current
+    /// scroll position.
+    /// Note: Only square layers are scrolled and the chunks of scrolled layers aren't culled.
+    pub scroll_speed: Vec2,
+    /// Merges runs of identical tiles in a chunk into larger quads with a repeating texture,
+    /// which makes the meshes of maps with large uniform areas much smaller.
+    /// Animated and hidden tiles and tiles with `TileVertexData` aren't merged, changing a
+    /// merged tile remeshes its chunk.
+    /// Note: Only square layers without `y_sort` or a `scroll_speed` and with a `grid_size` equal
+    /// to the `tile_size` are merged. Custom material fragment shaders need to repeat the texture
+    /// of merged quads, see `TilemapMaterial`. Has to be set before the layer is built.
+    pub greedy_meshing: bool,
+    /// Draws the tiles as triangles, or as their outlines or corners for grid overlays over
+    /// another layer. The chunk pipelines are specialized with the topology of the chunk meshes.
+    /// Note: Merged quads are outlined as a whole, see `greedy_meshing`.
+    /// Has to be set before the layer is built.
+    pub topology: TilemapTopology,
+    /// Wraps the layer around at its edges for toroidal worlds. Neighbors past an edge are the
+    /// tiles on the other side, world positions outside of the layer pick the wrapped tile, and
+    /// every chunk is moved to the copy of the layer closest to the main 2d camera, so the layer
+    /// looks continuous while the camera crosses its edges.
+    /// Note: The camera's view has to be at least a chunk smaller than the layer. Hex and
+    /// staggered isometric layers need an even size in tiles. Has to be set before the layer is
+    /// built.
+    pub wrap: bool,
+    #[cfg_attr(feature = "serialize", serde(skip))]
     pub(crate) mesher: ChunkMesher,
 }
 
+impl Default for LayerSettings {
+    fn default() -> Self {
+        Self::new(
+            MapSize::default(),
+            ChunkSize::default(),
+            TileSize::default(),
+            TextureSize::default(),
+        )
+    }
+}
+
 impl LayerSettings {
     pub fn new(
         map_size: MapSize,
@@ -59,13 +179,33 @@ impl LayerSettings {
             map_size,
             chunk_size,
             tile_size,
+            tile_world_size: None,
+            tile_offset: Vec2::ZERO,
             grid_size: tile_size.into(),
             texture_size,
             layer_id: 0,
+            depth: None,
             map_id: 0,
             cull: true,
             mesh_type: TilemapMeshType::Square,
+            blend_mode: TilemapBlendMode::AlphaBlend,
+            texture_mode: TilemapTextureMode::Atlas,
+            sampling: None,
             tile_spacing: Vec2::ZERO,
+            tile_margin: Vec2::ZERO,
+            texture_padding: 0.0,
+            alpha: 1.0,
+            tint: Color::WHITE,
+            alpha_cutoff: 0.5,
+            y_sort: false,
+            anchor: TilemapAnchor::BottomLeft,
+            flip_v: false,
+            default_tile: None,
+            pixel_snap: false,
+            scroll_speed: Vec2::ZERO,
+            greedy_meshing: false,
+            topology: TilemapTopology::Triangles,
+            wrap: false,
             mesher: ChunkMesher,
         }
     }
@@ -78,10 +218,384 @@ impl LayerSettings {
         self.map_id = id.into();
     }
 
+    /// Gets the offset of the anchor from the bottom left anchor of the layer in pixels.
+    /// Note: Anchors are ignored for staggered isometric layers.
+    pub fn get_anchor_offset(&self) -> Vec2 {
+        if self.mesh_type == TilemapMeshType::Isometric(IsoType::Staggered) {
+            return Vec2::ZERO;
+        }
+
+        match self.anchor {
+            TilemapAnchor::BottomLeft => Vec2::ZERO,
+            // Halfway to the corner of the chunk one past the last one.
+            TilemapAnchor::Center => {
+                self.get_chunk_origin(ChunkPos(self.map_size.0, self.map_size.1)) / 2.0
+            }
+            TilemapAnchor::Custom(offset) => offset,
+        }
+    }
+
+    /// Gets the z of the layer's transform, see `depth`.
+    /// ```
+    /// use bevy_ecs_tilemap::prelude::*;
+    ///
+    /// let settings = |layer_id: u16, depth: Option<f32>| {
+    ///     let mut settings = LayerSettings::new(
+    ///         MapSize(1, 1),
+    ///         ChunkSize(8, 8),
+    ///         TileSize(16.0, 16.0),
+    ///         TextureSize(96.0, 16.0),
+    ///     );
+    ///     settings.layer_id = layer_id;
+    ///     settings.depth = depth;
+    ///     settings
+    /// };
+    ///
+    /// // A background behind everything, the ground and an overlay above the ground's sprites.
+    /// let background = settings(2, Some(0.0));
+    /// let ground = settings(0, Some(10.0));
+    /// let overlay = settings(1, None);
+    /// assert_eq!(overlay.get_layer_depth(), 1.0);
+    /// assert_eq!(ground.get_layer_depth(), 10.0);
+    /// assert!(background.get_layer_depth() + 1.0 <= overlay.get_layer_depth());
+    /// ```
+    pub fn get_layer_depth(&self) -> f32 {
+        self.depth.unwrap_or(self.layer_id as f32)
+    }
+
+    /// Gets the depth of a chunk within the layer, which is the z of the chunk's transform.
+    /// The chunks of a layer are spread over a small range of depths in morton order of their
+    /// positions, so they are always drawn in the same order and overlapping transparent chunks
+    /// don't flicker. Chunks with a higher depth are drawn in front.
+    /// Note: The depths repeat after 4096 chunks, so very large layers can have chunks far apart
+    /// from each other at the same depth. Layers with `y_sort` and diamond isometric layers
+    /// already give every tile a depth by its row, their chunks are all at a depth of 0.
+    /// ```
+    /// use bevy_ecs_tilemap::prelude::*;
+    ///
+    /// let settings = LayerSettings::new(
+    ///     MapSize(4, 4),
+    ///     ChunkSize(8, 8),
+    ///     TileSize(16.0, 16.0),
+    ///     TextureSize(96.0, 16.0),
+    /// );
+    /// let depths: Vec<f32> = [(0, 0), (0, 1), (1, 0), (1, 1), (0, 2)]
+    ///     .iter()
+    ///     .map(|(x, y)| settings.get_chunk_depth(ChunkPos(*x, *y)))
+    ///     .collect();
+    /// assert!(depths.windows(2).all(|pair| pair[0] < pair[1]));
+    /// assert_eq!(depths[3], settings.get_chunk_depth(ChunkPos(1, 1)));
+    /// ```
+    pub fn get_chunk_depth(&self, chunk_pos: ChunkPos) -> f32 {
+        if self.y_sort || self.mesh_type == TilemapMeshType::Isometric(IsoType::Diamond) {
+            return 0.0;
+        }
+        (morton_index(chunk_pos) % CHUNK_DEPTHS) as f32 * CHUNK_DEPTH_STEP
+    }
+
+    /// Gets the size in world units tiles are drawn at, see `tile_world_size`.
+    /// ```
+    /// use bevy::prelude::*;
+    /// use bevy_ecs_tilemap::prelude::*;
+    ///
+    /// let settings = |tile_size: f32| {
+    ///     let mut settings = LayerSettings::new(
+    ///         MapSize(1, 1),
+    ///         ChunkSize(8, 8),
+    ///         TileSize(tile_size, tile_size),
+    ///         TextureSize(tile_size * 6.0, tile_size),
+    ///     );
+    ///     settings.grid_size = Vec2::ONE;
+    ///     settings.tile_world_size = Some(Vec2::ONE);
+    ///     settings
+    /// };
+    /// assert_eq!(settings(16.0).get_quad_size(), Vec2::ONE);
+    /// assert_eq!(settings(16.0).get_quad_size(), settings(64.0).get_quad_size());
+    /// ```
+    pub fn get_quad_size(&self) -> Vec2 {
+        match self.tile_world_size {
+            Some(tile_world_size) if self.mesh_type == TilemapMeshType::Square => tile_world_size,
+            _ => self.tile_size.into(),
+        }
+    }
+
+    /// Gets the rectangle a tile is drawn in relative to the layer's transform, with the size
+    /// from `get_quad_size` and the offset from `tile_offset`.
+    /// Returns `None` for layers which aren't square.
+    /// ```
+    /// use bevy::prelude::*;
+    /// use bevy_ecs_tilemap::prelude::*;
+    ///
+    /// // A tree two cells tall and centered on a cell 16 pixels wide.
+    /// let mut settings = LayerSettings::new(
+    ///     MapSize(1, 1),
+    ///     ChunkSize(8, 8),
+    ///     TileSize(32.0, 32.0),
+    ///     TextureSize(96.0, 32.0),
+    /// );
+    /// settings.grid_size = Vec2::new(16.0, 16.0);
+    /// settings.tile_offset = Vec2::new(-8.0, 0.0);
+    ///
+    /// let rect = settings.get_tile_rect(TilePos(2, 1)).unwrap();
+    /// assert_eq!(rect.min, Vec2::new(24.0, 16.0));
+    /// assert_eq!(rect.max, Vec2::new(56.0, 48.0));
+    /// ```
+    pub fn get_tile_rect(&self, tile_pos: TilePos) -> Option<bevy::sprite::Rect> {
+        if self.mesh_type != TilemapMeshType::Square {
+            return None;
+        }
+        let min = Vec2::new(tile_pos.0 as f32, tile_pos.1 as f32) * self.grid_size
+            + self.tile_offset
+            - self.get_anchor_offset();
+        Some(bevy::sprite::Rect {
+            min,
+            max: min + self.get_quad_size(),
+        })
+    }
+
+    /// Gets the center of a tile's cell on the grid relative to the layer's transform, the point
+    /// `MapQuery::get_tile_pos_at_world_pos` maps back to the tile.
+    /// Returns `None` for hex layers.
+    /// ```
+    /// use bevy::prelude::*;
+    /// use bevy_ecs_tilemap::prelude::*;
+    ///
+    /// let mut settings = LayerSettings::new(
+    ///     MapSize(2, 2),
+    ///     ChunkSize(8, 8),
+    ///     TileSize(16.0, 16.0),
+    ///     TextureSize(96.0, 16.0),
+    /// );
+    /// assert_eq!(settings.get_tile_center(TilePos(0, 0)), Some(Vec2::new(8.0, 8.0)));
+    /// assert_eq!(settings.get_tile_center(TilePos(15, 2)), Some(Vec2::new(248.0, 40.0)));
+    ///
+    /// settings.anchor = TilemapAnchor::Center;
+    /// assert_eq!(settings.get_tile_center(TilePos(8, 8)), Some(Vec2::new(8.0, 8.0)));
+    /// ```
+    pub fn get_tile_center(&self, tile_pos: TilePos) -> Option<Vec2> {
+        let tile_pos = Vec2::new(tile_pos.0 as f32, tile_pos.1 as f32);
+        let half_size = self.grid_size / 2.0;
+        let center = match self.mesh_type {
+            TilemapMeshType::Square => (tile_pos + Vec2::splat(0.5)) * self.grid_size,
+            // Tiles hang below their projected position.
+            TilemapMeshType::Isometric(IsoType::Diamond) => Vec2::new(
+                (tile_pos.x - tile_pos.y) * half_size.x,
+                -(tile_pos.x + tile_pos.y + 1.0) * half_size.y,
+            ),
+            // Even rows are shifted right by a quarter of a tile, odd rows left.
+            TilemapMeshType::Isometric(IsoType::Staggered) => {
+                let offset = (0.25 * self.grid_size.x).floor();
+                let offset = if tile_pos.y as u32 % 2 == 0 {
+                    offset
+                } else {
+                    -offset
+                };
+                Vec2::new(
+                    tile_pos.x * self.grid_size.x + half_size.x + offset,
+                    (tile_pos.y + 1.0) * half_size.y,
+                )
+            }
+            TilemapMeshType::Hexagon(_) => return None,
+        };
+        Some(center - self.get_anchor_offset())
+    }
+
+    /// Whether runs of identical tiles are merged into larger quads when meshing.
+    pub(crate) fn merges_tiles(&self) -> bool {
+        self.greedy_meshing
+            && self.mesh_type == TilemapMeshType::Square
+            && !self.y_sort
+            && self.scroll_speed == Vec2::ZERO
+            && self.grid_size == self.get_quad_size()
+    }
+
+    fn project_iso_diamond(
+        x: f32,
+        y: f32,
+        chunk_pixel_width: f32,
+        chunk_pixel_height: f32,
+    ) -> Vec2 {
+        let new_x = (x - y) * chunk_pixel_width / 2.0;
+        let new_y = (x + y) * chunk_pixel_height / 2.0;
+        Vec2::new(new_x, -new_y)
+    }
+
+    fn project_iso_staggered(
+        x: f32,
+        y: f32,
+        chunk_pixel_width: f32,
+        chunk_pixel_height: f32,
+    ) -> Vec2 {
+        let new_x = x * chunk_pixel_width;
+        let new_y = y * chunk_pixel_height;
+        Vec2::new(new_x, new_y)
+    }
+
+    /// Gets the position of a chunk relative to the bottom left anchor of the layer.
+    pub(crate) fn get_chunk_origin(&self, chunk_pos: ChunkPos) -> Vec2 {
+        match self.mesh_type {
+            TilemapMeshType::Square => {
+                let chunk_pos_x = chunk_pos.0 as f32 * self.chunk_size.0 as f32 * self.grid_size.x;
+                let chunk_pos_y = chunk_pos.1 as f32 * self.chunk_size.1 as f32 * self.grid_size.y;
+                Vec2::new(chunk_pos_x, chunk_pos_y)
+            }
+            TilemapMeshType::Hexagon(crate::HexType::Row) => {
+                let chunk_pos_x = (chunk_pos.1 as f32
+                    * self.chunk_size.0 as f32
+                    * (0.5 * self.tile_size.0).floor())
+                    + (chunk_pos.0 as f32 * self.chunk_size.0 as f32 * self.tile_size.0);
+                let chunk_pos_y = chunk_pos.1 as f32
+                    * self.chunk_size.1 as f32
+                    * (0.75 * self.tile_size.1).floor();
+                Vec2::new(chunk_pos_x, chunk_pos_y)
+            }
+            TilemapMeshType::Hexagon(crate::HexType::Column) => {
+                let chunk_pos_x = chunk_pos.0 as f32
+                    * self.chunk_size.0 as f32
+                    * (0.75 * self.tile_size.0).floor();
+                let chunk_pos_y = (chunk_pos.0 as f32
+                    * self.chunk_size.1 as f32
+                    * (0.5 * self.tile_size.1).ceil())
+                    + chunk_pos.1 as f32 * self.chunk_size.1 as f32 * self.tile_size.1;
+                Vec2::new(chunk_pos_x, chunk_pos_y)
+            }
+            TilemapMeshType::Hexagon(crate::HexType::RowOdd)
+            | TilemapMeshType::Hexagon(crate::HexType::RowEven) => {
+                let chunk_pos_x = chunk_pos.0 as f32 * self.chunk_size.0 as f32 * self.tile_size.0;
+                let chunk_pos_y = chunk_pos.1 as f32
+                    * self.chunk_size.1 as f32
+                    * (0.75 * self.tile_size.1).floor();
+                Vec2::new(chunk_pos_x, chunk_pos_y)
+            }
+            TilemapMeshType::Hexagon(crate::HexType::ColumnOdd)
+            | TilemapMeshType::Hexagon(crate::HexType::ColumnEven) => {
+                let chunk_pos_x = chunk_pos.0 as f32
+                    * self.chunk_size.0 as f32
+                    * (0.75 * self.tile_size.0).floor();
+                let chunk_pos_y = chunk_pos.1 as f32 * self.chunk_size.1 as f32 * self.tile_size.1;
+                Vec2::new(chunk_pos_x, chunk_pos_y)
+            }
+            TilemapMeshType::Isometric(IsoType::Diamond) => Self::project_iso_diamond(
+                chunk_pos.0 as f32,
+                chunk_pos.1 as f32,
+                self.chunk_size.0 as f32 * self.grid_size.x,
+                self.chunk_size.1 as f32 * self.grid_size.y,
+            ),
+            TilemapMeshType::Isometric(IsoType::Staggered) => Self::project_iso_staggered(
+                chunk_pos.0 as f32,
+                chunk_pos.1 as f32,
+                self.chunk_size.0 as f32 * self.grid_size.x,
+                self.chunk_size.1 as f32,
+            ),
+        }
+    }
+
+    /// Gets the rectangle of a tile in the layer's texture in pixels, measured from the top left
+    /// corner of the texture, exactly like the vertex shaders pick it.
+    /// In array mode every tile covers a whole layer of the texture.
+    /// ```
+    /// use bevy::prelude::*;
+    /// use bevy_ecs_tilemap::prelude::*;
+    ///
+    /// // Three columns of 16x16 tiles with a margin of 2 and a spacing of 1 pixel.
+    /// let mut settings = LayerSettings::new(
+    ///     MapSize(1, 1),
+    ///     ChunkSize(8, 8),
+    ///     TileSize(16.0, 16.0),
+    ///     TextureSize(54.0, 54.0),
+    /// );
+    /// settings.tile_margin = Vec2::splat(2.0);
+    /// settings.tile_spacing = Vec2::splat(1.0);
+    ///
+    /// let first = settings.get_texture_rect(0);
+    /// assert_eq!(first.min, Vec2::new(2.0, 2.0));
+    /// assert_eq!(first.max, Vec2::new(18.0, 18.0));
+    /// assert_eq!(settings.get_texture_rect(1).min, Vec2::new(19.0, 2.0));
+    /// assert_eq!(settings.get_texture_rect(4).min, Vec2::new(19.0, 19.0));
+    ///
+    /// let uv = settings.get_texture_uv_rect(0);
+    /// assert_eq!(uv.min, Vec2::new(2.0, 2.0) / 54.0);
+    /// ```
+    pub fn get_texture_rect(&self, texture_index: u16) -> bevy::sprite::Rect {
+        if self.texture_mode == TilemapTextureMode::Array {
+            return bevy::sprite::Rect {
+                min: Vec2::ZERO,
+                max: self.texture_size.into(),
+            };
+        }
+
+        let tile_size: Vec2 = self.tile_size.into();
+        let texture_size: Vec2 = self.texture_size.into();
+        let stride = tile_size + self.tile_spacing;
+        let columns = (((texture_size.x - self.tile_margin.x + self.tile_spacing.x) / stride.x)
+            as u16)
+            .max(1);
+        let min = self.tile_margin
+            + Vec2::new(
+                (texture_index % columns) as f32 * stride.x,
+                (texture_index / columns) as f32 * stride.y,
+            );
+        bevy::sprite::Rect {
+            min,
+            max: min + tile_size,
+        }
+    }
+
+    /// Gets the texture coordinates of a tile's rectangle in the layer's texture, inset by
+    /// `texture_padding` exactly like the vertex shaders do.
+    /// Useful for drawing tiles outside of the map, like in a tile palette.
+    pub fn get_texture_uv_rect(&self, texture_index: u16) -> bevy::sprite::Rect {
+        if self.texture_mode == TilemapTextureMode::Array {
+            return bevy::sprite::Rect {
+                min: Vec2::ZERO,
+                max: Vec2::ONE,
+            };
+        }
+
+        let texture_size: Vec2 = self.texture_size.into();
+        let rect = self.get_texture_rect(texture_index);
+        let padding = Vec2::splat(self.texture_padding);
+        bevy::sprite::Rect {
+            min: (rect.min + padding) / texture_size,
+            max: (rect.max - padding) / texture_size,
+        }
+    }
+
+    /// Gets the color a tile's texels are multiplied with, the tile's color times the layer's
+    /// `tint` and `alpha` exactly like the vertex shaders combine them.
+    /// ```
+    /// use bevy::prelude::*;
+    /// use bevy_ecs_tilemap::prelude::*;
+    ///
+    /// let mut settings = LayerSettings::new(
+    ///     MapSize(1, 1),
+    ///     ChunkSize(8, 8),
+    ///     TileSize(16.0, 16.0),
+    ///     TextureSize(96.0, 16.0),
+    /// );
+    /// settings.tint = Color::rgb(0.0, 0.5, 1.0);
+    /// settings.alpha = 0.5;
+    ///
+    /// let tile = Tile {
+    ///     color: Color::rgba(0.5, 1.0, 0.5, 0.5),
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(settings.get_tile_color(&tile), Color::rgba(0.0, 0.5, 0.5, 0.25));
+    /// assert_eq!(settings.get_tile_color(&Tile::default()), Color::rgba(0.0, 0.5, 1.0, 0.5));
+    /// ```
+    pub fn get_tile_color(&self, tile: &Tile) -> Color {
+        Color::rgba(
+            tile.color.r() * self.tint.r(),
+            tile.color.g() * self.tint.g(),
+            tile.color.b() * self.tint.b(),
+            tile.color.a() * self.alpha,
+        )
+    }
+
     pub fn get_pixel_center(&self) -> Vec2 {
         Vec2::new(
-            ((self.map_size.0 * self.chunk_size.0) as f32 * self.tile_size.0) / 2.0,
-            ((self.map_size.1 * self.chunk_size.1) as f32 * self.tile_size.1) / 2.0,
+            ((self.map_size.0 * self.chunk_size.0) as f32 * self.grid_size.x) / 2.0,
+            ((self.map_size.1 * self.chunk_size.1) as f32 * self.grid_size.y) / 2.0,
         )
     }
 
@@ -93,7 +607,105 @@ impl LayerSettings {
     }
 }
 
+/// A component which restricts drawing a layer to the chunks overlapping the tiles within the
+/// [`start`, `end`) rectangle, for example to show only part of a map in a minimap.
+/// Insert it on a layer entity, the other chunks are hidden regardless of the camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TilemapCrop {
+    /// The first tile included in the rectangle.
+    pub start: TilePos,
+    /// The tile one past the last tile included in the rectangle.
+    pub end: TilePos,
+}
+
+impl TilemapCrop {
+    pub fn new(start: TilePos, end: TilePos) -> Self {
+        Self { start, end }
+    }
+
+    /// Returns true if any tile of the chunk is inside the rectangle.
+    pub(crate) fn overlaps_chunk(&self, chunk: &Chunk) -> bool {
+        let chunk_size = chunk.settings.chunk_size;
+        let chunk_start = TilePos(
+            chunk.position.0 * chunk_size.0,
+            chunk.position.1 * chunk_size.1,
+        );
+        let chunk_end = TilePos(chunk_start.0 + chunk_size.0, chunk_start.1 + chunk_size.1);
+        chunk_start.0 < self.end.0
+            && self.start.0 < chunk_end.0
+            && chunk_start.1 < self.end.1
+            && self.start.1 < chunk_end.1
+    }
+}
+
+/// A component which draws the chunks of a layer with pipelines that output to another texture
+/// format than the window, for rendering the layer into a texture from a render graph pass with
+/// its own color attachment, like a minimap or a thumbnail.
+/// Insert it on a layer entity along with `RenderLayers` to only draw the layer from the camera
+/// of that pass. Removing it switches the chunks back to the window format.
+/// HDR passes work the same way with a float format like `TextureFormat::Rgba16Float`.
+/// Note: The pass also needs a `Depth32Float` depth attachment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TilemapTargetFormat(pub TextureFormat);
+
+impl Default for TilemapTargetFormat {
+    fn default() -> Self {
+        Self(TextureFormat::default())
+    }
+}
+
+/// A component which overrides the depth of a layer's chunks within the layer, which decides the
+/// order they are drawn in, for custom draw orders like sorting chunks by their row. The function
+/// gets each chunk and returns the z of its transform, relative to the layer. Chunks with a
+/// higher depth are drawn in front.
+/// Insert it on a layer entity, the chunks go back to `LayerSettings::get_chunk_depth` once it's
+/// removed. Keep the depths well below the distance to the next layer, so the chunks stay
+/// between the layers.
+/// ```
+/// use bevy_ecs_tilemap::prelude::*;
+///
+/// // Chunks further down the map are drawn in front of the ones above them.
+/// let sort_key = TilemapSortKey(|chunk| -(chunk.position.1 as f32) / 1024.0);
+/// ```
+#[derive(Clone, Copy)]
+pub struct TilemapSortKey(pub fn(&Chunk) -> f32);
+
+/// A component which fades the tiles of a layer towards a fog color with their distance from a
+/// point in world space, for example to limit the view around the player in top down games.
+/// Insert it on a layer entity, changing it only updates the chunk uniforms so the fog can
+/// follow a moving point every frame. Removing it clears the fog.
+/// Note: Custom `TilemapMaterial` fragment shaders don't apply the fog.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TilemapFog {
+    /// The fog color, its alpha is how strongly tiles are covered by it at full distance.
+    pub color: Color,
+    /// The point in world space the fog is centered on.
+    pub center: Vec2,
+    /// Distance from the center within which tiles are clear of fog.
+    pub radius: f32,
+    /// Distance past the radius over which tiles fade into the fog.
+    pub falloff: f32,
+}
+
+impl Default for TilemapFog {
+    fn default() -> Self {
+        Self {
+            color: Color::BLACK,
+            center: Vec2::ZERO,
+            radius: 0.0,
+            falloff: 0.0,
+        }
+    }
+}
+
 /// A component which keeps information and a cache of tile/chunk entities for convenience.
+///
+/// Insert a `Visible` component on the layer entity to show or hide all of its chunks, culling
+/// still applies to the chunks of a visible layer. See `TilemapCrop` to only show a part of it.
+/// Insert a `RenderLayers` component on the layer entity to only draw it with the cameras on
+/// those render layers.
+/// Note: Chunks are culled against the main 2d camera, disable `cull` for layers drawn by
+/// other cameras.
 #[derive(Default)]
 pub struct Layer {
     /// The map information for the tilemap entity.
@@ -110,6 +722,8 @@ pub enum MapTileError {
     AlreadyExists,
     /// Doesn't exist
     NonExistent,
+    /// The number of tiles doesn't match the number of positions they are written to.
+    WrongTileCount,
 }
 
 impl Layer {
@@ -126,10 +740,27 @@ impl Layer {
         }
     }
 
+    /// Gets the chunk entity at the given chunk position.
+    /// Returns None if the position is outside of the layer.
     pub fn get_chunk(&self, chunk_pos: ChunkPos) -> Option<Entity> {
+        if chunk_pos.0 >= self.settings.map_size.0 || chunk_pos.1 >= self.settings.map_size.1 {
+            return None;
+        }
         self.chunks[morton_index(chunk_pos)]
     }
 
+    /// Iterates over the positions and entities of the layer's chunks, in the order they are
+    /// stored. Each chunk's `Chunk::map_entity` links it back to the layer entity.
+    pub fn iter_chunks(&self) -> impl Iterator<Item = (ChunkPos, Entity)> + '_ {
+        self.chunks
+            .iter()
+            .enumerate()
+            .filter_map(|(index, chunk_entity)| {
+                let chunk_pos = morton_pos(index);
+                chunk_entity.map(|chunk_entity| (ChunkPos(chunk_pos.x, chunk_pos.y), chunk_entity))
+            })
+    }
+
     /// Gets the map's size in tiles just for convenience.
     pub fn get_layer_size_in_tiles(&self) -> MapSize {
         MapSize(
@@ -159,6 +790,29 @@ pub(crate) fn update_chunk_hashmap_for_added_tiles(
 ///
 /// These are ultimately converted to u16; if you're using more than one type with this trait in your game,
 /// ensure that their u16 conversions do not unintentionally overlap.
+/// The id of a built layer can be read back from `LayerSettings::layer_id`.
+/// ```
+/// use bevy_ecs_tilemap::prelude::*;
+///
+/// #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// enum GameLayer {
+///     Ground,
+///     Walls,
+/// }
+///
+/// impl From<GameLayer> for u16 {
+///     fn from(layer: GameLayer) -> u16 {
+///         layer as u16
+///     }
+/// }
+///
+/// impl LayerId for GameLayer {}
+///
+/// let mut settings = LayerSettings::default();
+/// settings.set_layer_id(GameLayer::Walls);
+/// assert_eq!(settings.layer_id, u16::from(GameLayer::Walls));
+/// assert_ne!(settings.layer_id, u16::from(GameLayer::Ground));
+/// ```
 pub trait LayerId: Clone + Copy + PartialEq + Eq + Hash + Into<u16> {}
 
 impl LayerId for u16 {}
@@ -3,18 +3,14 @@ use crate::{
     layer::LayerId,
     map::MapId,
     morton_index,
-    render::TilemapData,
+    render::{pipeline::get_render_pipelines, TilemapData},
     round_to_power_of_two,
     tile::{TileBundleTrait, TileParent},
-    Chunk, ChunkPos, IsoType, Layer, LayerBundle, LayerSettings, MapTileError, TilePos,
-    TilemapMeshType,
+    Chunk, ChunkPos, Layer, LayerBundle, LayerSettings, MapTileError, TilePos,
 };
 use bevy::{
     prelude::*,
-    render::{
-        mesh::{Indices, VertexAttributeValues},
-        pipeline::PrimitiveTopology,
-    },
+    render::mesh::{Indices, VertexAttributeValues},
 };
 
 /// Useful for creating and modifying a layer in the same system.
@@ -49,11 +45,13 @@ where
         settings.set_map_id(map_id);
         settings.set_layer_id(layer_id);
 
-        let pipeline = if pipeline.is_some() {
-            pipeline.unwrap()
-        } else {
-            settings.mesh_type.into()
-        };
+        let pipeline = pipeline.unwrap_or_else(|| {
+            get_render_pipelines(
+                settings.mesh_type,
+                settings.blend_mode,
+                settings.texture_mode,
+            )
+        });
         (
             Self {
                 settings,
@@ -69,6 +67,7 @@ where
     /// Note: Limited to T(Bundle + TileBundleTrait) for what gets spawned.
     /// The `pipeline` parameter allows you to pass in a custom RenderPipelines
     /// which will be used for rendering each chunk entity.
+    #[allow(clippy::too_many_arguments)]
     pub fn new_batch<F: FnMut(TilePos) -> Option<T>>(
         commands: &mut Commands,
         mut settings: LayerSettings,
@@ -84,16 +83,18 @@ where
         let size_x = settings.map_size.0 * settings.chunk_size.0;
         let size_y = settings.map_size.1 * settings.chunk_size.1;
 
-        let pipeline = if pipeline.is_some() {
-            pipeline.unwrap()
-        } else {
-            settings.mesh_type.into()
-        };
+        let pipeline = pipeline.unwrap_or_else(|| {
+            get_render_pipelines(
+                settings.mesh_type,
+                settings.blend_mode,
+                settings.texture_mode,
+            )
+        });
 
         settings.set_map_id(map_id);
         settings.set_layer_id(layer_id);
 
-        let mut layer = Layer::new(settings.clone());
+        let mut layer = Layer::new(settings);
         for x in 0..layer.settings.map_size.0 {
             for y in 0..layer.settings.map_size.1 {
                 let mut chunk_entity = None;
@@ -105,17 +106,12 @@ where
                 let chunk_entity = chunk_entity.unwrap();
 
                 let chunk_pos = ChunkPos(x, y);
-                let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+                let mut mesh = Mesh::new(settings.topology.primitive_topology());
                 mesh.set_attribute("Vertex_Position", VertexAttributeValues::Float3(vec![]));
                 mesh.set_attribute("Vertex_Texture", VertexAttributeValues::Int4(vec![]));
                 mesh.set_indices(Some(Indices::U32(vec![])));
                 let mesh_handle = meshes.add(mesh);
-                let chunk = Chunk::new(
-                    layer_entity,
-                    settings.clone(),
-                    chunk_pos,
-                    mesh_handle.clone(),
-                );
+                let chunk = Chunk::new(layer_entity, settings, chunk_pos, mesh_handle.clone());
 
                 let index = morton_index(chunk_pos);
                 layer.chunks[index] = Some(chunk_entity);
@@ -166,14 +162,14 @@ where
 
         let layer_bundle = LayerBundle {
             layer,
-            transform: Transform::from_xyz(0.0, 0.0, settings.layer_id as f32),
+            transform: Transform::from_xyz(0.0, 0.0, settings.get_layer_depth()),
             ..LayerBundle::default()
         };
 
-        let mut layer = layer_bundle.layer;
+        let layer = layer_bundle.layer;
         let mut transform = layer_bundle.transform;
-        layer.settings.layer_id = layer.settings.layer_id;
-        transform.translation.z = 0.0; //layer.settings.layer_id as f32;
+        // Layers built in a batch stay at a depth of 0 unless they have a depth.
+        transform.translation.z = settings.depth.unwrap_or(0.0);
         commands.entity(layer_entity).insert_bundle(LayerBundle {
             layer,
             transform,
@@ -202,8 +198,7 @@ where
         let morton_tile_index = morton_index(tile_pos);
         if morton_tile_index < self.tiles.capacity() {
             let tile_entity = if self.tiles[morton_tile_index].0.is_some() {
-                let tile_entity = self.tiles[morton_tile_index].0;
-                tile_entity
+                self.tiles[morton_tile_index].0
             } else {
                 let tile_entity = Some(commands.spawn().id());
                 self.tiles[morton_tile_index].0 = tile_entity;
@@ -219,10 +214,8 @@ where
     /// Returns an existing tile entity if it exists
     pub fn look_up_tile_entity(&self, tile_pos: TilePos) -> Option<Entity> {
         let morton_tile_index = morton_index(tile_pos);
-        if morton_tile_index < self.tiles.capacity() {
-            if self.tiles[morton_tile_index].0.is_some() {
-                return self.tiles[morton_tile_index].0;
-            }
+        if morton_tile_index < self.tiles.capacity() && self.tiles[morton_tile_index].0.is_some() {
+            return self.tiles[morton_tile_index].0;
         }
 
         None
@@ -244,7 +237,7 @@ where
         let morton_tile_index = morton_index(tile_pos);
         if morton_tile_index < self.tiles.capacity() {
             if let Some(tile) = &self.tiles[morton_tile_index].1 {
-                return Ok(&tile);
+                return Ok(tile);
             } else {
                 return Err(MapTileError::NonExistent);
             }
@@ -315,7 +308,7 @@ where
         meshes: &mut ResMut<Assets<Mesh>>,
         material: Handle<ColorMaterial>,
     ) -> LayerBundle {
-        let mut layer = Layer::new(self.settings.clone());
+        let mut layer = Layer::new(self.settings);
         for x in 0..layer.settings.map_size.0 {
             for y in 0..layer.settings.map_size.1 {
                 let mut chunk_entity = None;
@@ -327,14 +320,14 @@ where
                 let chunk_entity = chunk_entity.unwrap();
 
                 let chunk_pos = ChunkPos(x, y);
-                let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+                let mut mesh = Mesh::new(self.settings.topology.primitive_topology());
                 mesh.set_attribute("Vertex_Position", VertexAttributeValues::Float3(vec![]));
                 mesh.set_attribute("Vertex_Texture", VertexAttributeValues::Int4(vec![]));
                 mesh.set_indices(Some(Indices::U32(vec![])));
                 let mesh_handle = meshes.add(mesh);
                 let mut chunk = Chunk::new(
                     self.layer_entity,
-                    self.settings.clone(),
+                    self.settings,
                     chunk_pos,
                     mesh_handle.clone(),
                 );
@@ -386,94 +379,60 @@ where
 
         LayerBundle {
             layer,
-            transform: Transform::from_xyz(0.0, 0.0, self.settings.layer_id as f32),
+            transform: Transform::from_xyz(0.0, 0.0, self.settings.get_layer_depth()),
             ..LayerBundle::default()
         }
     }
 
-    fn project_iso_diamond(
-        x: f32,
-        y: f32,
-        chunk_pixel_width: f32,
-        chunk_pixel_height: f32,
-    ) -> Vec2 {
-        let new_x = (x - y) * chunk_pixel_width / 2.0;
-        let new_y = (x + y) * chunk_pixel_height / 2.0;
-        Vec2::new(new_x, -new_y)
+    pub(crate) fn get_chunk_coords(chunk_pos: ChunkPos, settings: &LayerSettings) -> Transform {
+        let depth = settings.get_chunk_depth(chunk_pos);
+        let chunk_pos = settings.get_chunk_origin(chunk_pos) - settings.get_anchor_offset();
+        Transform::from_xyz(chunk_pos.x, chunk_pos.y, depth)
     }
+}
 
-    fn project_iso_staggered(
-        x: f32,
-        y: f32,
-        chunk_pixel_width: f32,
-        chunk_pixel_height: f32,
-    ) -> Vec2 {
-        let new_x = x * chunk_pixel_width;
-        let new_y = y * chunk_pixel_height;
-        Vec2::new(new_x, new_y)
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::test_util::{layer_settings, new_world, spawn_layer};
+    use bevy::prelude::*;
+
+    // Builds a 50x50 tile layer, rounding the chunk count up and leaving the rest empty.
+    fn build_map(chunk_size: u32) -> World {
+        let mut world = new_world();
+        let chunks = (50 + chunk_size - 1) / chunk_size;
+        let settings = layer_settings(MapSize(chunks, chunks), ChunkSize(chunk_size, chunk_size));
+        spawn_layer(&mut world, settings, Handle::default(), |layer_builder| {
+            layer_builder.fill(TilePos(0, 0), TilePos(50, 50), TileBundle::default());
+        });
+        world
     }
 
-    fn get_chunk_coords(chunk_pos: ChunkPos, settings: &LayerSettings) -> Transform {
-        let chunk_pos = match settings.mesh_type {
-            TilemapMeshType::Square => {
-                let chunk_pos_x =
-                    chunk_pos.0 as f32 * settings.chunk_size.0 as f32 * settings.tile_size.0;
-                let chunk_pos_y =
-                    chunk_pos.1 as f32 * settings.chunk_size.1 as f32 * settings.tile_size.1;
-                Vec2::new(chunk_pos_x, chunk_pos_y)
-            }
-            TilemapMeshType::Hexagon(crate::HexType::Row) => {
-                let chunk_pos_x = (chunk_pos.1 as f32
-                    * settings.chunk_size.0 as f32
-                    * (0.5 * settings.tile_size.0).floor())
-                    + (chunk_pos.0 as f32 * settings.chunk_size.0 as f32 * settings.tile_size.0);
-                let chunk_pos_y = chunk_pos.1 as f32
-                    * settings.chunk_size.1 as f32
-                    * (0.75 * settings.tile_size.1).floor();
-                Vec2::new(chunk_pos_x, chunk_pos_y)
-            }
-            TilemapMeshType::Hexagon(crate::HexType::Column) => {
-                let chunk_pos_x = chunk_pos.0 as f32
-                    * settings.chunk_size.0 as f32
-                    * (0.75 * settings.tile_size.0).floor();
-                let chunk_pos_y = (chunk_pos.0 as f32
-                    * settings.chunk_size.1 as f32
-                    * (0.5 * settings.tile_size.1).ceil())
-                    + chunk_pos.1 as f32 * settings.chunk_size.1 as f32 * settings.tile_size.1;
-                Vec2::new(chunk_pos_x, chunk_pos_y)
-            }
-            TilemapMeshType::Hexagon(crate::HexType::RowOdd)
-            | TilemapMeshType::Hexagon(crate::HexType::RowEven) => {
-                let chunk_pos_x =
-                    chunk_pos.0 as f32 * settings.chunk_size.0 as f32 * settings.tile_size.0;
-                let chunk_pos_y = chunk_pos.1 as f32
-                    * settings.chunk_size.1 as f32
-                    * (0.75 * settings.tile_size.1).floor();
-                Vec2::new(chunk_pos_x, chunk_pos_y)
-            }
-            TilemapMeshType::Hexagon(crate::HexType::ColumnOdd)
-            | TilemapMeshType::Hexagon(crate::HexType::ColumnEven) => {
-                let chunk_pos_x = chunk_pos.0 as f32
-                    * settings.chunk_size.0 as f32
-                    * (0.75 * settings.tile_size.0).floor();
-                let chunk_pos_y =
-                    chunk_pos.1 as f32 * settings.chunk_size.1 as f32 * settings.tile_size.1;
-                Vec2::new(chunk_pos_x, chunk_pos_y)
-            }
-            TilemapMeshType::Isometric(IsoType::Diamond) => Self::project_iso_diamond(
-                chunk_pos.0 as f32,
-                chunk_pos.1 as f32,
-                settings.chunk_size.0 as f32 * settings.grid_size.x,
-                settings.chunk_size.1 as f32 * settings.grid_size.y,
-            ),
-            TilemapMeshType::Isometric(IsoType::Staggered) => Self::project_iso_staggered(
-                chunk_pos.0 as f32,
-                chunk_pos.1 as f32,
-                settings.chunk_size.0 as f32 * settings.grid_size.x,
-                settings.chunk_size.1 as f32,
-            ),
-        };
-
-        Transform::from_xyz(chunk_pos.x, chunk_pos.y, 0.0)
+    #[test]
+    fn chunk_size_is_set_per_layer() {
+        for (chunk_size, chunk_count) in [(10, 25), (32, 4)] {
+            let mut world = build_map(chunk_size);
+            let chunks: Vec<(ChunkSize, usize)> = world
+                .query::<&Chunk>()
+                .iter(&world)
+                .map(|chunk| {
+                    let tile_count = chunk.iter_tile_entities().count();
+                    (chunk.settings.chunk_size, tile_count)
+                })
+                .collect();
+            assert_eq!(chunks.len(), chunk_count);
+            assert!(chunks
+                .iter()
+                .all(|(size, _)| *size == ChunkSize(chunk_size, chunk_size)));
+            // The chunks on the edges only hold the tiles inside of the map.
+            let chunk_tiles: usize = chunks.iter().map(|(_, tile_count)| tile_count).sum();
+            assert_eq!(chunk_tiles, 50 * 50);
+
+            let tiles: Vec<TilePos> = world.query::<&TilePos>().iter(&world).copied().collect();
+            assert_eq!(tiles.len(), 50 * 50);
+            assert!(tiles
+                .iter()
+                .all(|tile_pos| tile_pos.0 < 50 && tile_pos.1 < 50));
+        }
     }
 }
@@ -0,0 +1,509 @@
+use crate::prelude::*;
+use bevy::{
+    asset::{AssetLoader, AssetPath, BoxedFuture, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+};
+use std::collections::HashMap;
+
+/// Adds the `.ldtk` asset loader and the systems which turn loaded LDtk projects into layers.
+#[derive(Default)]
+pub struct LdtkPlugin;
+
+impl Plugin for LdtkPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_asset::<LdtkMap>()
+            .add_asset_loader(LdtkLoader)
+            .add_system(process_loaded_ldtk_maps.system());
+    }
+}
+
+/// A project loaded from an LDtk `.ldtk` file.
+#[derive(TypeUuid)]
+#[uuid = "abd7b6d9-633f-4322-a8f4-e5f011cae9c6"]
+pub struct LdtkMap {
+    pub project: ldtk_rust::Project,
+    /// Tileset textures keyed by the LDtk tileset uid.
+    pub tilesets: HashMap<i64, Handle<Texture>>,
+}
+
+/// Controls how an LDtk project is turned into layers.
+#[derive(Default)]
+pub struct LdtkMapConfig {
+    /// The index of the level in the project to build.
+    pub selected_level: usize,
+    /// Maps IntGrid values to texture indices. IntGrid cells without an entry are left empty.
+    /// When empty, IntGrid layers are built from their auto layer tiles instead.
+    pub int_grid_tiles: HashMap<i64, u16>,
+    /// The tileset uid used for IntGrid layers which don't have a tileset of their own.
+    pub int_grid_tileset: Option<i64>,
+}
+
+/// Spawn this bundle on a map entity to build its layers from an LDtk project.
+#[derive(Default, Bundle)]
+pub struct LdtkMapBundle {
+    pub ldtk_map: Handle<LdtkMap>,
+    pub map: Map,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub ldtk_map_config: LdtkMapConfig,
+}
+
+/// An instance from an LDtk entity layer. These are spawned as children of the map entity
+/// instead of being turned into tiles.
+pub struct LdtkEntity {
+    pub identifier: String,
+    pub def_uid: i64,
+    /// The layer id the entity would have had if it were a tile layer.
+    pub layer_id: u16,
+    /// The grid position of the entity using the same orientation as tile positions.
+    pub tile_pos: TilePos,
+    pub size: Vec2,
+    pub fields: HashMap<String, serde_json::Value>,
+}
+
+pub struct LdtkLoader;
+
+impl AssetLoader for LdtkLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let project: ldtk_rust::Project = serde_json::from_slice(bytes)?;
+            let project_dir = load_context
+                .path()
+                .parent()
+                .ok_or_else(|| anyhow::anyhow!("{:?} isn't in a directory", load_context.path()))?;
+            let dependencies: Vec<(i64, AssetPath)> = project
+                .defs
+                .tilesets
+                .iter()
+                .map(|tileset| (tileset.uid, project_dir.join(&tileset.rel_path).into()))
+                .collect();
+
+            let loaded_asset = LoadedAsset::new(LdtkMap {
+                project,
+                tilesets: dependencies
+                    .iter()
+                    .map(|dep| (dep.0, load_context.get_handle(dep.1.clone())))
+                    .collect(),
+            });
+            load_context.set_default_asset(
+                loaded_asset.with_dependencies(dependencies.iter().map(|x| x.1.clone()).collect()),
+            );
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        static EXTENSIONS: &[&str] = &["ldtk"];
+        EXTENSIONS
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn process_loaded_ldtk_maps(
+    mut commands: Commands,
+    mut map_events: EventReader<AssetEvent<LdtkMap>>,
+    maps: Res<Assets<LdtkMap>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut query: Query<(Entity, &Handle<LdtkMap>, &LdtkMapConfig, &mut Map)>,
+    new_maps: Query<&Handle<LdtkMap>, Added<Handle<LdtkMap>>>,
+    layer_query: Query<&Layer>,
+    chunk_query: Query<&Chunk>,
+    ldtk_entity_query: Query<(Entity, &Parent), With<LdtkEntity>>,
+) {
+    let mut changed_maps = Vec::<Handle<LdtkMap>>::default();
+    for event in map_events.iter() {
+        match event {
+            AssetEvent::Created { handle } => {
+                log::info!("Map added!");
+                changed_maps.push(handle.clone());
+            }
+            AssetEvent::Modified { handle } => {
+                log::info!("Map changed!");
+                changed_maps.push(handle.clone());
+            }
+            AssetEvent::Removed { handle } => {
+                log::info!("Map removed!");
+                // if mesh was modified and removed in the same update, ignore the modification
+                // events are ordered so future modification events are ok
+                changed_maps = changed_maps
+                    .into_iter()
+                    .filter(|changed_handle| changed_handle != handle)
+                    .collect();
+            }
+        }
+    }
+
+    // If we have new map entities add them to the changed_maps list.
+    for new_map_handle in new_maps.iter() {
+        changed_maps.push(new_map_handle.clone());
+    }
+
+    for changed_map in changed_maps.iter() {
+        for (map_entity, map_handle, map_config, mut map) in query.iter_mut() {
+            // only deal with currently changed map
+            if map_handle != changed_map {
+                continue;
+            }
+            if let Some(ldtk_map) = maps.get(map_handle) {
+                // Despawn all tiles/chunks/layers.
+                for (layer_id, layer_entity) in map.get_layers() {
+                    if let Ok(layer) = layer_query.get(layer_entity) {
+                        for x in 0..layer.get_layer_size_in_tiles().0 {
+                            for y in 0..layer.get_layer_size_in_tiles().1 {
+                                let tile_pos = TilePos(x, y);
+                                let chunk_pos = ChunkPos(
+                                    tile_pos.0 / layer.settings.chunk_size.0,
+                                    tile_pos.1 / layer.settings.chunk_size.1,
+                                );
+                                if let Some(chunk_entity) = layer.get_chunk(chunk_pos) {
+                                    if let Ok(chunk) = chunk_query.get(chunk_entity) {
+                                        let chunk_tile_pos = chunk.to_chunk_pos(tile_pos);
+                                        if let Some(tile) = chunk.get_tile_entity(chunk_tile_pos) {
+                                            commands.entity(tile).despawn_recursive();
+                                        }
+                                    }
+
+                                    commands.entity(chunk_entity).despawn_recursive();
+                                }
+                            }
+                        }
+                    }
+                    map.remove_layer(&mut commands, layer_id);
+                }
+
+                // Despawn the entities spawned from entity layers.
+                for (ldtk_entity, parent) in ldtk_entity_query.iter() {
+                    if parent.0 == map_entity {
+                        commands.entity(ldtk_entity).despawn_recursive();
+                    }
+                }
+
+                // Pull out tilesets.
+                let mut tilesets = HashMap::new();
+                ldtk_map.project.defs.tilesets.iter().for_each(|tileset| {
+                    if let Some(texture) = ldtk_map.tilesets.get(&tileset.uid) {
+                        tilesets.insert(tileset.uid, (texture.clone(), tileset));
+                    }
+                });
+
+                let level = match ldtk_map.project.levels.get(map_config.selected_level) {
+                    Some(level) => level,
+                    None => {
+                        log::warn!(
+                            "Level {} doesn't exist, the project has {} levels.",
+                            map_config.selected_level,
+                            ldtk_map.project.levels.len()
+                        );
+                        continue;
+                    }
+                };
+                // Levels saved in separate files don't have their layers in the project.
+                let layer_instances = match level.layer_instances.as_ref() {
+                    Some(layer_instances) => layer_instances,
+                    None => {
+                        log::warn!(
+                            "Level {} has no layers, levels saved in separate files aren't supported.",
+                            level.identifier
+                        );
+                        continue;
+                    }
+                };
+
+                for (layer_id, layer) in layer_instances.iter().rev().enumerate() {
+                    let layer_id = layer_id as u16;
+                    let grid_size = layer.grid_size;
+                    let map_tile_count_x = layer.c_wid as u32;
+                    let map_tile_count_y = layer.c_hei as u32;
+
+                    if layer.layer_instance_type == "Entities" {
+                        let mut children = Vec::new();
+                        for entity in layer.entity_instances.iter() {
+                            let tile_pos = TilePos(
+                                entity.grid[0] as u32,
+                                map_tile_count_y - entity.grid[1] as u32 - 1,
+                            );
+                            let translation = Vec3::new(
+                                (entity.px[0] + layer.px_total_offset_x) as f32,
+                                -(entity.px[1] + layer.px_total_offset_y) as f32,
+                                layer_id as f32,
+                            );
+                            let fields = entity
+                                .field_instances
+                                .iter()
+                                .filter_map(|field| {
+                                    field
+                                        .value
+                                        .clone()
+                                        .map(|value| (field.identifier.clone(), value))
+                                })
+                                .collect();
+
+                            let child = commands
+                                .spawn()
+                                .insert(LdtkEntity {
+                                    identifier: entity.identifier.clone(),
+                                    def_uid: entity.def_uid,
+                                    layer_id,
+                                    tile_pos,
+                                    size: Vec2::new(entity.width as f32, entity.height as f32),
+                                    fields,
+                                })
+                                .insert(Transform::from_translation(translation))
+                                .insert(GlobalTransform::default())
+                                .id();
+                            children.push(child);
+                        }
+                        commands.entity(map_entity).push_children(&children);
+                        continue;
+                    }
+
+                    let use_int_grid = layer.layer_instance_type == "IntGrid"
+                        && !map_config.int_grid_tiles.is_empty();
+                    let tileset_uid = if use_int_grid {
+                        layer.tileset_def_uid.or(map_config.int_grid_tileset)
+                    } else {
+                        layer.tileset_def_uid
+                    };
+                    let (texture, tileset) = match tileset_uid.map(|uid| (uid, tilesets.get(&uid)))
+                    {
+                        Some((_, Some(tileset))) => tileset.clone(),
+                        Some((uid, None)) => {
+                            log::warn!(
+                                "Skipping layer {}, tileset {} doesn't exist.",
+                                layer.identifier,
+                                uid
+                            );
+                            continue;
+                        }
+                        None => continue,
+                    };
+
+                    let mut settings = LayerSettings::new(
+                        MapSize(
+                            (map_tile_count_x as f32 / 32.0).ceil() as u32,
+                            (map_tile_count_y as f32 / 32.0).ceil() as u32,
+                        ),
+                        ChunkSize(32, 32),
+                        TileSize(tileset.tile_grid_size as f32, tileset.tile_grid_size as f32),
+                        TextureSize(tileset.px_wid as f32, tileset.px_hei as f32),
+                    );
+                    settings.grid_size = Vec2::new(grid_size as f32, grid_size as f32);
+                    settings.tile_spacing = Vec2::splat(tileset.spacing as f32);
+                    settings.tile_margin = Vec2::splat(tileset.padding as f32);
+                    settings.set_layer_id(layer_id);
+
+                    let (mut layer_builder, layer_entity) = LayerBuilder::<TileBundle>::new(
+                        &mut commands,
+                        settings,
+                        map.id,
+                        layer_id,
+                        None,
+                    );
+
+                    let color = Color::rgba(1.0, 1.0, 1.0, layer.opacity as f32);
+
+                    if use_int_grid {
+                        for (index, value) in layer.int_grid_csv.iter().enumerate() {
+                            let texture_index = match map_config.int_grid_tiles.get(value) {
+                                Some(texture_index) => *texture_index,
+                                None => continue,
+                            };
+                            let pos = TilePos(
+                                index as u32 % map_tile_count_x,
+                                map_tile_count_y - index as u32 / map_tile_count_x - 1,
+                            );
+
+                            layer_builder
+                                .set_tile(
+                                    pos,
+                                    Tile {
+                                        texture_index,
+                                        visible: layer.visible,
+                                        color,
+                                        ..Default::default()
+                                    }
+                                    .into(),
+                                )
+                                .unwrap();
+                        }
+                    } else {
+                        let tileset_width_in_tiles = tileset.c_wid as u32;
+                        let stride = tileset.tile_grid_size + tileset.spacing;
+                        let tiles = if layer.layer_instance_type == "Tiles" {
+                            &layer.grid_tiles
+                        } else {
+                            &layer.auto_layer_tiles
+                        };
+
+                        for tile in tiles.iter() {
+                            let tileset_x = ((tile.src[0] - tileset.padding) / stride) as u32;
+                            let tileset_y = ((tile.src[1] - tileset.padding) / stride) as u32;
+
+                            let pos = TilePos(
+                                (tile.px[0] / grid_size) as u32,
+                                map_tile_count_y - (tile.px[1] / grid_size) as u32 - 1,
+                            );
+
+                            layer_builder
+                                .set_tile(
+                                    pos,
+                                    Tile {
+                                        texture_index: (tileset_y * tileset_width_in_tiles
+                                            + tileset_x)
+                                            as u16,
+                                        flip_x: tile.f & 1 != 0,
+                                        flip_y: tile.f & 2 != 0,
+                                        visible: layer.visible,
+                                        color,
+                                        ..Default::default()
+                                    }
+                                    .into(),
+                                )
+                                .unwrap();
+                        }
+                    }
+
+                    let material_handle = materials.add(ColorMaterial::texture(texture));
+                    let layer_bundle =
+                        layer_builder.build(&mut commands, &mut meshes, material_handle);
+                    let transform = Transform::from_xyz(
+                        layer.px_total_offset_x as f32,
+                        -(level.px_hei + layer.px_total_offset_y) as f32,
+                        settings.get_layer_depth(),
+                    );
+                    let layer = layer_bundle.layer;
+                    map.add_layer(&mut commands, layer.settings.layer_id, layer_entity);
+                    commands.entity(layer_entity).insert_bundle(LayerBundle {
+                        layer,
+                        transform,
+                        ..layer_bundle
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{into_world, new_app};
+    use bevy::ecs::schedule::{Schedule, SystemStage};
+
+    fn load_fixture() -> LdtkMap {
+        let project = serde_json::from_str(include_str!("../assets/map.ldtk")).unwrap();
+        let mut tilesets = HashMap::new();
+        tilesets.insert(2, Handle::<Texture>::default());
+        LdtkMap { project, tilesets }
+    }
+
+    // Builds the layers of `ldtk_map` and returns the world with the map entity.
+    fn build_map(ldtk_map: LdtkMap, ldtk_map_config: LdtkMapConfig) -> (World, Entity) {
+        let mut app = new_app();
+        app.add_asset::<LdtkMap>();
+        let mut world = into_world(app);
+
+        let ldtk_map = world
+            .get_resource_mut::<Assets<LdtkMap>>()
+            .unwrap()
+            .add(ldtk_map);
+        let map_entity = world.spawn().id();
+        world.entity_mut(map_entity).insert_bundle(LdtkMapBundle {
+            ldtk_map,
+            map: Map::new(0u16, map_entity),
+            ldtk_map_config,
+            ..Default::default()
+        });
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage(
+            "process",
+            SystemStage::single_threaded().with_system(process_loaded_ldtk_maps.system()),
+        );
+        schedule.run(&mut world);
+        (world, map_entity)
+    }
+
+    fn tiles(world: &mut World, layer_id: u16) -> HashMap<TilePos, Tile> {
+        world
+            .query::<(&TilePos, &Tile, &TileParent)>()
+            .iter(world)
+            .filter(|(_, _, parent)| parent.layer_id == layer_id)
+            .map(|(tile_pos, tile, _)| (*tile_pos, *tile))
+            .collect()
+    }
+
+    #[test]
+    fn builds_fixture_layers() {
+        let (mut world, map_entity) = build_map(load_fixture(), LdtkMapConfig::default());
+        assert_eq!(world.get::<Map>(map_entity).unwrap().get_layers().len(), 2);
+
+        // Layers are added bottom first, the "Tiles" layer is the bottom one.
+        let bottom = tiles(&mut world, 0);
+        assert_eq!(bottom.len(), 16 * 16);
+        let texture_index = |tile_pos| bottom[&tile_pos].texture_index;
+        // The top row of the level is the last row of the layer.
+        assert_eq!(texture_index(TilePos(0, 15)), 0);
+        assert_eq!(texture_index(TilePos(1, 14)), 1);
+        assert_eq!(texture_index(TilePos(5, 10)), 3);
+        assert_eq!(texture_index(TilePos(6, 9)), 4);
+        assert_eq!(texture_index(TilePos(7, 7)), 5);
+        assert_eq!(
+            bottom
+                .values()
+                .filter(|tile| tile.texture_index == 5)
+                .count(),
+            4
+        );
+
+        // The "Tiles2" layer is empty.
+        assert!(tiles(&mut world, 1).is_empty());
+    }
+
+    #[test]
+    fn skips_missing_levels_and_tilesets() {
+        // The selected level doesn't exist.
+        let config = LdtkMapConfig {
+            selected_level: 1,
+            ..Default::default()
+        };
+        let (world, map_entity) = build_map(load_fixture(), config);
+        assert_eq!(world.get::<Map>(map_entity).unwrap().get_layers().len(), 0);
+
+        // The level is saved in a separate file.
+        let mut ldtk_map = load_fixture();
+        ldtk_map.project.levels[0].layer_instances = None;
+        let (world, map_entity) = build_map(ldtk_map, LdtkMapConfig::default());
+        assert_eq!(world.get::<Map>(map_entity).unwrap().get_layers().len(), 0);
+
+        // The IntGrid tileset doesn't exist.
+        let mut ldtk_map = load_fixture();
+        for layer in ldtk_map.project.levels[0]
+            .layer_instances
+            .iter_mut()
+            .flatten()
+        {
+            layer.layer_instance_type = "IntGrid".to_string();
+            layer.tileset_def_uid = None;
+        }
+        let config = LdtkMapConfig {
+            int_grid_tiles: vec![(1, 0)].into_iter().collect(),
+            int_grid_tileset: Some(99),
+            ..Default::default()
+        };
+        let (world, map_entity) = build_map(ldtk_map, config);
+        assert_eq!(world.get::<Map>(map_entity).unwrap().get_layers().len(), 0);
+
+        // The tileset of the layers wasn't loaded.
+        let mut ldtk_map = load_fixture();
+        ldtk_map.tilesets.clear();
+        let (world, map_entity) = build_map(ldtk_map, LdtkMapConfig::default());
+        assert_eq!(world.get::<Map>(map_entity).unwrap().get_layers().len(), 0);
+    }
+}
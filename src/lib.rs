@@ -10,11 +10,11 @@
 //! ## Upcoming Features
 //!  - ~~Support for isometric and hexagon rendering~~ done
 //!  - ~~Built in animation support.~~ done see animation example
-//!  - Texture array support
+//!  - ~~Texture array support~~ done see `TilemapTextureMode`
 //!  - ~~Layers and add/remove tiles. (High Priority)~~ done
 //!
 //! ## Example
-//! ```
+//! ```ignore
 //! let texture_handle = asset_server.load("tiles.png");
 //! let material_handle = materials.add(ColorMaterial::texture(texture_handle));
 //!
@@ -56,37 +56,95 @@
 //!     .insert(GlobalTransform::default());
 //! ```
 
-use bevy::prelude::*;
-use chunk::{update_chunk_mesh, update_chunk_time, update_chunk_visibility};
+use auto_tile::{update_auto_tiles, AutoTileCache};
+use bevy::{prelude::*, render::pipeline::PrimitiveTopology};
+use chunk::{
+    apply_chunk_meshes, update_chunk_color, update_chunk_fog, update_chunk_for_changed_tiles,
+    update_chunk_mesh, update_chunk_render_layers, update_chunk_scroll, update_chunk_shader_defs,
+    update_chunk_sort_key, update_chunk_texture_sampling, update_chunk_time,
+    update_chunk_viewport_size, update_chunk_visibility, update_chunk_wrap, SampledMaterials,
+};
+use chunk_batch::{update_chunk_batch_data, update_chunk_batches};
+use chunk_material::update_chunk_sub_mesh_data;
+use debug::{update_chunk_wireframe, update_tile_labels, update_tilemap_stats};
 use layer::update_chunk_hashmap_for_added_tiles;
-use render::pipeline::add_tile_map_graph;
+use render::pipeline::{add_tile_map_graph, update_chunk_target_format};
+use streaming::update_streamed_chunks;
 
+mod auto_tile;
+mod bake;
+mod batch;
 mod chunk;
+mod chunk_batch;
+mod chunk_material;
+mod clipboard;
+mod collision;
+mod csv;
+mod data_layer;
+mod debug;
+mod fill;
+mod generators;
 mod layer;
 mod layer_builder;
 mod map;
 mod map_query;
+mod material;
 mod mesher;
 mod neighbors;
+mod palette;
+mod pass;
 mod render;
+mod streaming;
+#[cfg(test)]
+pub(crate) mod test_util;
 mod tile;
 
 #[cfg(feature = "ldtk")]
 mod ldtk;
-
-pub use crate::chunk::Chunk;
-pub use crate::layer::{Layer, LayerBundle, LayerSettings, MapTileError};
+#[cfg(feature = "serialize")]
+mod serialize;
+#[cfg(feature = "tiled_map")]
+mod tiled;
+
+pub use crate::auto_tile::{AutoTile, AutoTileConfig, AutoTileMode};
+pub use crate::bake::{bake_tiles, MapBakeError};
+pub use crate::batch::TileUpdateBatch;
+pub use crate::chunk::{Chunk, ChunkMeshed};
+pub use crate::chunk_batch::TilemapBatching;
+pub use crate::clipboard::TileClipboard;
+pub use crate::collision::{merge_colliders, ColliderMerge};
+pub use crate::csv::{CsvLoader, CsvMap, CsvMapBundle, CsvMapConfig, CsvMapPlugin};
+pub use crate::data_layer::DataLayer;
+pub use crate::debug::{TilemapDebug, TilemapStats};
+pub use crate::layer::{
+    Layer, LayerBundle, LayerSettings, MapTileError, TilemapCrop, TilemapFog, TilemapSortKey,
+    TilemapTargetFormat,
+};
 pub use crate::layer_builder::LayerBuilder;
+#[cfg(feature = "ldtk")]
+pub use crate::ldtk::{LdtkEntity, LdtkLoader, LdtkMap, LdtkMapBundle, LdtkMapConfig, LdtkPlugin};
 pub use crate::map::Map;
-pub use crate::map_query::MapQuery;
-pub use crate::tile::{GPUAnimated, Tile, TileBundle, TileBundleTrait, TileParent};
+pub use crate::map_query::{screen_to_world_pos, MapQuery};
+pub use crate::material::{TilemapMaterial, TilemapMaterialPlugin};
+pub use crate::mesher::{mesh_chunk, ChunkVertex};
+pub use crate::palette::TilemapPalette;
+pub use crate::pass::TilemapPassPlugin;
+#[cfg(feature = "serialize")]
+pub use crate::serialize::MapSerializeError;
+pub use crate::streaming::TilemapStreaming;
+pub use crate::tile::{
+    GPUAnimated, Tile, TileBundle, TileBundleTrait, TileMaterial, TileParent, TileVertexData,
+};
+#[cfg(feature = "tiled_map")]
+pub use crate::tiled::{TiledLoader, TiledMap, TiledMapBundle, TiledMapPlugin};
 
 /// Adds the default systems and pipelines used by bevy_ecs_tilemap.
 #[derive(Default)]
 pub struct TilemapPlugin;
 
 /// Different hex coordinate systems. You can find out more at this link: https://www.redblobgames.com/grids/hexagons/
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub enum HexType {
     RowEven,
     RowOdd,
@@ -97,14 +155,16 @@ pub enum HexType {
 }
 
 /// Different iso coordinate systems.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub enum IsoType {
     Diamond,
     Staggered,
 }
 
 /// The type of tile to be rendered, currently we support: Square, Hex, and Isometric.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub enum TilemapMeshType {
     Square,
     Hexagon(HexType),
@@ -117,32 +177,252 @@ impl Default for TilemapMeshType {
     }
 }
 
+/// How the tiles of a layer are blended with what was drawn before them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum TilemapBlendMode {
+    /// Standard alpha blending.
+    AlphaBlend,
+    /// Adds the tile color on top of the existing color, useful for glowing effects.
+    Additive,
+    /// Multiplies the existing color by the tile color.
+    Multiply,
+    /// Ignores alpha and overwrites the existing color.
+    Opaque,
+    /// Overwrites the existing color like `Opaque`, but discards texels with an alpha below
+    /// `LayerSettings::alpha_cutoff`. Gives hard edged pixel art without sorting or blending
+    /// the transparent parts of tiles.
+    AlphaMask,
+}
+
+impl Default for TilemapBlendMode {
+    fn default() -> Self {
+        Self::AlphaBlend
+    }
+}
+
+/// How tile texture indices are looked up in the layer's texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum TilemapTextureMode {
+    /// The texture is an atlas and the texture index picks a tile out of it.
+    Atlas,
+    /// The texture is an array texture and the texture index picks a layer of it.
+    /// See `Texture::reinterpret_stacked_2d_as_array` for creating one.
+    Array,
+}
+
+impl Default for TilemapTextureMode {
+    fn default() -> Self {
+        Self::Atlas
+    }
+}
+
+/// The primitives the chunk meshes of a layer are drawn with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum TilemapTopology {
+    /// Two triangles per tile.
+    Triangles,
+    /// The outline of every tile, for grid overlays and debug connectivity.
+    Lines,
+    /// The corners of every tile.
+    Points,
+}
+
+impl Default for TilemapTopology {
+    fn default() -> Self {
+        Self::Triangles
+    }
+}
+
+impl TilemapTopology {
+    /// Gets the primitive topology of the chunk meshes, which the chunk pipelines are
+    /// specialized with.
+    /// ```
+    /// use bevy::render::pipeline::PrimitiveTopology;
+    /// use bevy_ecs_tilemap::prelude::*;
+    ///
+    /// assert_eq!(
+    ///     TilemapTopology::Lines.primitive_topology(),
+    ///     PrimitiveTopology::LineList
+    /// );
+    /// ```
+    pub fn primitive_topology(&self) -> PrimitiveTopology {
+        match self {
+            Self::Triangles => PrimitiveTopology::TriangleList,
+            Self::Lines => PrimitiveTopology::LineList,
+            Self::Points => PrimitiveTopology::PointList,
+        }
+    }
+}
+
+/// Which point of a layer is placed at the layer's transform.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum TilemapAnchor {
+    /// The bottom left corner of tile (0, 0), for isometric layers the top of tile (0, 0).
+    BottomLeft,
+    /// The center of the layer.
+    Center,
+    /// A point in pixels relative to `BottomLeft`.
+    Custom(Vec2),
+}
+
+impl Default for TilemapAnchor {
+    fn default() -> Self {
+        Self::BottomLeft
+    }
+}
+
+/// The filtering used when sampling a layer's texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum TilemapSampling {
+    /// Samples the closest texel, this keeps pixel art sharp and stops neighboring atlas tiles
+    /// from bleeding into each other.
+    Nearest,
+    /// Blends between texels.
+    Linear,
+}
+
 /// The tilemap stage which runs before post update.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, StageLabel)]
 pub struct TilemapStage;
 
+/// Labels of the main systems in the `TilemapStage`.
+/// Systems added to the `TilemapStage` can be ordered against them, for example a system that
+/// edits tiles `.before(TilemapLabel::DetectChanges)` has its changes meshed in the same frame.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
+pub enum TilemapLabel {
+    /// Registers newly spawned tiles with their chunks.
+    AddTiles,
+    /// Updates the texture of auto tiles next to changed tiles.
+    AutoTile,
+    /// Flags the chunks of changed tiles for remeshing.
+    DetectChanges,
+    /// Culls the chunks outside of the camera.
+    Visibility,
+    /// Starts building the meshes of flagged chunks.
+    Remesh,
+    /// Applies the chunk meshes which finished building and sends `ChunkMeshed` events.
+    ApplyMeshes,
+}
+
 impl Plugin for TilemapPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.add_stage_before(CoreStage::PostUpdate, TilemapStage, SystemStage::parallel())
             .add_system_to_stage(TilemapStage, update_chunk_time.system())
+            .add_system_to_stage(TilemapStage, update_chunk_viewport_size.system())
+            .init_resource::<SampledMaterials>()
+            .add_system_to_stage(TilemapStage, update_chunk_texture_sampling.system())
+            .add_system_to_stage(TilemapStage, update_chunk_color.system())
+            .add_system_to_stage(TilemapStage, update_chunk_scroll.system())
+            .add_system_to_stage(TilemapStage, update_chunk_render_layers.system())
+            .add_system_to_stage(TilemapStage, update_chunk_fog.system())
+            .add_system_to_stage(TilemapStage, update_chunk_sort_key.system())
+            .add_system_to_stage(
+                TilemapStage,
+                update_streamed_chunks
+                    .system()
+                    .before(TilemapLabel::AddTiles),
+            )
             .add_system_to_stage(
                 TilemapStage,
                 update_chunk_hashmap_for_added_tiles
                     .system()
-                    .label("hash_update_for_tiles"),
+                    .label(TilemapLabel::AddTiles),
+            )
+            .init_resource::<AutoTileCache>()
+            .add_system_to_stage(
+                TilemapStage,
+                update_auto_tiles
+                    .system()
+                    .label(TilemapLabel::AutoTile)
+                    .after(TilemapLabel::AddTiles),
+            )
+            .add_system_to_stage(
+                TilemapStage,
+                update_chunk_for_changed_tiles
+                    .system()
+                    .label(TilemapLabel::DetectChanges)
+                    .after(TilemapLabel::AutoTile),
+            )
+            .add_system_to_stage(
+                TilemapStage,
+                update_chunk_wrap.system().before(TilemapLabel::Visibility),
             )
             .add_system_to_stage(
                 TilemapStage,
                 update_chunk_visibility
                     .system()
-                    .label("update_chunk_visibility"),
+                    .label(TilemapLabel::Visibility),
             )
             .add_system_to_stage(
                 TilemapStage,
                 update_chunk_mesh
                     .system()
-                    .after("hash_update_for_tiles")
-                    .after("update_chunk_visibility"),
+                    .label(TilemapLabel::Remesh)
+                    .after(TilemapLabel::AddTiles)
+                    .after(TilemapLabel::DetectChanges)
+                    .after(TilemapLabel::Visibility),
+            )
+            .add_event::<ChunkMeshed>()
+            .add_system_to_stage(
+                TilemapStage,
+                apply_chunk_meshes.system().label(TilemapLabel::ApplyMeshes),
+            )
+            .add_system_to_stage(
+                TilemapStage,
+                update_chunk_shader_defs
+                    .system()
+                    .label("update_chunk_shader_defs")
+                    .after(TilemapLabel::Remesh)
+                    .after(TilemapLabel::ApplyMeshes)
+                    .after("update_chunk_wireframe"),
+            )
+            .add_system_to_stage(
+                TilemapStage,
+                update_chunk_batches
+                    .system()
+                    .after(TilemapLabel::ApplyMeshes),
+            )
+            .add_system_to_stage(
+                TilemapStage,
+                update_chunk_batch_data
+                    .system()
+                    .after(TilemapLabel::Visibility)
+                    .after("update_chunk_shader_defs")
+                    .after("update_chunk_wireframe"),
+            )
+            .add_system_to_stage(
+                TilemapStage,
+                update_chunk_sub_mesh_data
+                    .system()
+                    .after(TilemapLabel::Visibility)
+                    .after("update_chunk_shader_defs")
+                    .after("update_chunk_wireframe"),
+            )
+            .init_resource::<TilemapDebug>()
+            .add_system_to_stage(
+                TilemapStage,
+                update_chunk_wireframe
+                    .system()
+                    .label("update_chunk_wireframe"),
+            )
+            .add_system_to_stage(
+                TilemapStage,
+                update_chunk_target_format
+                    .system()
+                    .after("update_chunk_wireframe"),
+            )
+            .add_system_to_stage(TilemapStage, update_tile_labels.system())
+            .init_resource::<TilemapStats>()
+            .add_system_to_stage(
+                TilemapStage,
+                update_tilemap_stats
+                    .system()
+                    .after(TilemapLabel::Visibility),
             );
         let world = app.world_mut();
         add_tile_map_graph(world);
@@ -160,21 +440,103 @@ fn morton_pos(index: usize) -> UVec2 {
     UVec2::new(x, y)
 }
 
+/// Returns the chunk containing a tile, and the index of the tile in `Chunk::tiles`.
+///
+/// Tiles are stored in morton order, so the index isn't `y * chunk_size.0 + x`. It goes up to
+/// the square of the chunk size rounded up to a power of two.
+/// ```
+/// use bevy_ecs_tilemap::{tile_to_chunk, ChunkPos, ChunkSize, TilePos};
+///
+/// let chunk_size = ChunkSize(8, 8);
+/// assert_eq!(tile_to_chunk(TilePos(0, 0), chunk_size), (ChunkPos(0, 0), 0));
+/// assert_eq!(tile_to_chunk(TilePos(7, 7), chunk_size), (ChunkPos(0, 0), 63));
+/// assert_eq!(tile_to_chunk(TilePos(8, 7), chunk_size), (ChunkPos(1, 0), 21));
+/// assert_eq!(tile_to_chunk(TilePos(15, 16), chunk_size), (ChunkPos(1, 2), 42));
+///
+/// // Chunk sizes which aren't powers of two leave gaps in the indices.
+/// let chunk_size = ChunkSize(5, 3);
+/// assert_eq!(tile_to_chunk(TilePos(4, 2), chunk_size), (ChunkPos(0, 0), 36));
+/// assert_eq!(tile_to_chunk(TilePos(5, 3), chunk_size), (ChunkPos(1, 1), 0));
+/// ```
+pub fn tile_to_chunk(tile_pos: TilePos, chunk_size: ChunkSize) -> (ChunkPos, usize) {
+    let chunk_pos = ChunkPos(tile_pos.0 / chunk_size.0, tile_pos.1 / chunk_size.1);
+    let local_pos = LocalTilePos(tile_pos.0 % chunk_size.0, tile_pos.1 % chunk_size.1);
+    (chunk_pos, morton_index(local_pos))
+}
+
+/// Returns the tile at an index of `Chunk::tiles`, the inverse of `tile_to_chunk`.
+/// ```
+/// use bevy_ecs_tilemap::{chunk_to_tile, tile_to_chunk, ChunkPos, ChunkSize, TilePos};
+///
+/// let chunk_size = ChunkSize(8, 8);
+/// assert_eq!(chunk_to_tile(ChunkPos(0, 0), 0, chunk_size), TilePos(0, 0));
+/// assert_eq!(chunk_to_tile(ChunkPos(0, 0), 63, chunk_size), TilePos(7, 7));
+/// assert_eq!(chunk_to_tile(ChunkPos(1, 2), 0, chunk_size), TilePos(8, 16));
+/// assert_eq!(chunk_to_tile(ChunkPos(1, 2), 63, chunk_size), TilePos(15, 23));
+///
+/// let chunk_size = ChunkSize(5, 3);
+/// for x in 0..15 {
+///     for y in 0..9 {
+///         let (chunk_pos, index) = tile_to_chunk(TilePos(x, y), chunk_size);
+///         assert_eq!(chunk_to_tile(chunk_pos, index, chunk_size), TilePos(x, y));
+///     }
+/// }
+/// ```
+pub fn chunk_to_tile(chunk_pos: ChunkPos, index: usize, chunk_size: ChunkSize) -> TilePos {
+    let local_pos = morton_pos(index);
+    TilePos(
+        chunk_pos.0 * chunk_size.0 + local_pos.x,
+        chunk_pos.1 * chunk_size.1 + local_pos.y,
+    )
+}
+
 /// use bevy_ecs_tilemap::prelude::*; to import commonly used components, data structures, bundles, and plugins.
 pub mod prelude {
-    pub use crate::chunk::Chunk;
-    pub use crate::layer::{Layer, LayerBundle, LayerSettings, MapTileError, LayerId};
+    pub use crate::auto_tile::{AutoTile, AutoTileConfig, AutoTileMode};
+    pub use crate::bake::{bake_tiles, MapBakeError};
+    pub use crate::batch::TileUpdateBatch;
+    pub use crate::chunk::{Chunk, ChunkMeshed};
+    pub use crate::chunk_batch::TilemapBatching;
+    pub use crate::clipboard::TileClipboard;
+    pub use crate::collision::{merge_colliders, ColliderMerge};
+    pub use crate::csv::{CsvMap, CsvMapBundle, CsvMapConfig, CsvMapPlugin};
+    pub use crate::data_layer::DataLayer;
+    pub use crate::debug::{TilemapDebug, TilemapStats};
+    pub use crate::layer::{
+        Layer, LayerBundle, LayerId, LayerSettings, MapTileError, TilemapCrop, TilemapFog,
+        TilemapSortKey, TilemapTargetFormat,
+    };
     pub use crate::layer_builder::LayerBuilder;
     pub use crate::map::{Map, MapId};
-    pub use crate::map_query::MapQuery;
+    pub use crate::map_query::{screen_to_world_pos, MapQuery};
+    pub use crate::material::{TilemapMaterial, TilemapMaterialPlugin};
     pub(crate) use crate::mesher::ChunkMesher;
-    pub use crate::tile::{GPUAnimated, Tile, TileBundle, TileBundleTrait, TileParent};
-    pub use crate::TilemapPlugin;
-    pub use crate::{HexType, IsoType, TilemapMeshType};
-
+    pub use crate::palette::TilemapPalette;
+    pub use crate::pass::TilemapPassPlugin;
+    pub use crate::streaming::TilemapStreaming;
+    pub use crate::tile::{
+        GPUAnimated, Tile, TileBundle, TileBundleTrait, TileMaterial, TileParent, TileVertexData,
+    };
+    pub use crate::{
+        HexType, IsoType, TilemapAnchor, TilemapBlendMode, TilemapMeshType, TilemapSampling,
+        TilemapTextureMode, TilemapTopology,
+    };
+    pub use crate::{TilemapLabel, TilemapPlugin, TilemapStage};
+
+    pub use crate::{chunk_to_tile, tile_to_chunk};
     pub use crate::{ChunkPos, ChunkSize, LocalTilePos, MapSize, TextureSize, TilePos, TileSize};
 
-    pub use crate::neighbors::get_neighboring_pos;
+    pub use crate::neighbors::{
+        axial_to_tile_pos, get_neighboring_pos, get_neighboring_pos_hex,
+        get_neighboring_pos_staggered, tile_pos_to_axial,
+    };
+
+    #[cfg(feature = "ldtk")]
+    pub use crate::ldtk::{LdtkEntity, LdtkMap, LdtkMapBundle, LdtkMapConfig, LdtkPlugin};
+    #[cfg(feature = "serialize")]
+    pub use crate::serialize::MapSerializeError;
+    #[cfg(feature = "tiled_map")]
+    pub use crate::tiled::{TiledMap, TiledMapBundle, TiledMapPlugin};
 }
 
 pub(crate) fn round_to_power_of_two(value: f32) -> usize {
@@ -183,6 +545,7 @@ pub(crate) fn round_to_power_of_two(value: f32) -> usize {
 
 /// The size of the map, in chunks
 #[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct MapSize(pub u32, pub u32);
 
 impl From<Vec2> for MapSize {
@@ -191,14 +554,19 @@ impl From<Vec2> for MapSize {
     }
 }
 
-impl Into<Vec2> for MapSize {
-    fn into(self) -> Vec2 {
-        Vec2::new(self.0 as f32, self.1 as f32)
+impl From<MapSize> for Vec2 {
+    fn from(size: MapSize) -> Vec2 {
+        Vec2::new(size.0 as f32, size.1 as f32)
     }
 }
 
 /// The size of each chunk, in tiles
+///
+/// Every visible chunk is a single mesh and draw call, so larger chunks mean fewer draw calls
+/// while smaller chunks remesh faster and cull more tightly. `TilemapBatching` draws the chunks
+/// of a layer as fewer meshes.
 #[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct ChunkSize(pub u32, pub u32);
 
 impl From<Vec2> for ChunkSize {
@@ -207,14 +575,15 @@ impl From<Vec2> for ChunkSize {
     }
 }
 
-impl Into<Vec2> for ChunkSize {
-    fn into(self) -> Vec2 {
-        Vec2::new(self.0 as f32, self.1 as f32)
+impl From<ChunkSize> for Vec2 {
+    fn from(size: ChunkSize) -> Vec2 {
+        Vec2::new(size.0 as f32, size.1 as f32)
     }
 }
 
 /// The size of each tile, in pixels
 #[derive(Default, Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct TileSize(pub f32, pub f32);
 
 impl From<Vec2> for TileSize {
@@ -223,19 +592,20 @@ impl From<Vec2> for TileSize {
     }
 }
 
-impl Into<Vec2> for TileSize {
-    fn into(self) -> Vec2 {
-        Vec2::new(self.0, self.1)
+impl From<TileSize> for Vec2 {
+    fn from(size: TileSize) -> Vec2 {
+        Vec2::new(size.0, size.1)
     }
 }
 
 /// The size of a texture in pixels
 #[derive(Default, Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct TextureSize(pub f32, pub f32);
 
-impl Into<Vec2> for TextureSize {
-    fn into(self) -> Vec2 {
-        Vec2::new(self.0, self.1)
+impl From<TextureSize> for Vec2 {
+    fn from(size: TextureSize) -> Vec2 {
+        Vec2::new(size.0, size.1)
     }
 }
 
@@ -243,17 +613,18 @@ impl Into<Vec2> for TextureSize {
 ///
 /// Coordinates start at (0, 0) from the bottom-left tile of the map.
 #[derive(Default, Clone, Copy, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct TilePos(pub u32, pub u32);
 
-impl Into<UVec2> for TilePos {
-    fn into(self) -> UVec2 {
-        UVec2::new(self.0, self.1)
+impl From<TilePos> for UVec2 {
+    fn from(pos: TilePos) -> UVec2 {
+        UVec2::new(pos.0, pos.1)
     }
 }
 
-impl Into<TilePos> for UVec2 {
-    fn into(self) -> TilePos {
-        TilePos(self.x, self.y)
+impl From<UVec2> for TilePos {
+    fn from(pos: UVec2) -> TilePos {
+        TilePos(pos.x, pos.y)
     }
 }
 
@@ -263,9 +634,9 @@ impl Into<TilePos> for UVec2 {
 #[derive(Default, Clone, Copy, PartialEq, Eq, Debug, Hash)]
 pub struct LocalTilePos(pub u32, pub u32);
 
-impl Into<UVec2> for LocalTilePos {
-    fn into(self) -> UVec2 {
-        UVec2::new(self.0, self.1)
+impl From<LocalTilePos> for UVec2 {
+    fn from(pos: LocalTilePos) -> UVec2 {
+        UVec2::new(pos.0, pos.1)
     }
 }
 
@@ -276,14 +647,14 @@ impl Into<UVec2> for LocalTilePos {
 #[derive(Default, Clone, Copy, PartialEq, Eq, Debug, Hash)]
 pub struct ChunkPos(pub u32, pub u32);
 
-impl Into<UVec2> for ChunkPos {
-    fn into(self) -> UVec2 {
-        UVec2::new(self.0, self.1)
+impl From<ChunkPos> for UVec2 {
+    fn from(pos: ChunkPos) -> UVec2 {
+        UVec2::new(pos.0, pos.1)
     }
 }
 
-impl Into<Vec2> for ChunkPos {
-    fn into(self) -> Vec2 {
-        Vec2::new(self.0 as f32, self.1 as f32)
+impl From<ChunkPos> for Vec2 {
+    fn from(pos: ChunkPos) -> Vec2 {
+        Vec2::new(pos.0 as f32, pos.1 as f32)
     }
 }
@@ -1,9 +1,23 @@
+use crate::batch::TileUpdateBatch;
 use crate::layer::LayerId;
 use crate::map::Map;
+#[cfg(feature = "serialize")]
+use crate::serialize::{MapSerializeError, SerializedLayer, SerializedMap, MAP_FORMAT_VERSION};
 use crate::{morton_index, prelude::*};
 use bevy::ecs::system::SystemParam;
 use bevy::math::Vec3Swizzles;
 use bevy::prelude::*;
+use bevy::render::camera::Camera;
+use std::collections::HashMap;
+
+// Scales the y sorted depths within a layer to stay below 1.0, the depth of the layer above,
+// like the vertex shaders do.
+const Y_SORT_DEPTH_SCALE: f32 = 1.0 - 1.0 / 1024.0;
+
+type ReadWriteQuerySet<'a, T> = QuerySet<(
+    Query<'a, (Entity, &'static mut T)>,
+    Query<'a, (Entity, &'static T)>,
+)>;
 
 /// MapQuery is a useful bevy system param that provides a standard API for interacting with tiles.
 /// It's not required that you use this, but it does provide a convenience.
@@ -11,23 +25,17 @@ use bevy::prelude::*;
 /// have your own tile data attached to each tile and a standard tile query wouldn't pull that data in.
 #[derive(SystemParam)]
 pub struct MapQuery<'a> {
-    chunk_query_set: QuerySet<(
-        Query<'a, (Entity, &'static mut Chunk)>,
-        Query<'a, (Entity, &'static Chunk)>,
-    )>,
-    layer_query_set: QuerySet<(
-        Query<'a, (Entity, &'static mut Layer)>,
-        Query<'a, (Entity, &'static Layer)>,
-    )>,
-    map_query_set: QuerySet<(
-        Query<'a, (Entity, &'static mut Map)>,
-        Query<'a, (Entity, &'static Map)>,
-    )>,
+    chunk_query_set: ReadWriteQuerySet<'a, Chunk>,
+    layer_query_set: ReadWriteQuerySet<'a, Layer>,
+    map_query_set: ReadWriteQuerySet<'a, Map>,
+    layer_transform_query: Query<'a, &'static GlobalTransform, With<Layer>>,
     meshes: ResMut<'a, Assets<Mesh>>,
 }
 
 impl<'a> MapQuery<'a> {
     /// Builds the tile map layer and returns the layer's entity.
+    /// The chunks of the layer are drawn with `material_handle`, tiles from other textures can
+    /// share the layer with a `TileMaterial`, which draws them in a sub-mesh of their chunk.
     pub fn build_layer(
         &mut self,
         commands: &mut Commands,
@@ -35,10 +43,9 @@ impl<'a> MapQuery<'a> {
         material_handle: Handle<ColorMaterial>,
     ) -> Entity {
         let layer_bundle = layer_builder.build(commands, &mut self.meshes, material_handle);
-        let mut layer = layer_bundle.layer;
+        let layer = layer_bundle.layer;
         let mut transform = layer_bundle.transform;
-        layer.settings.layer_id = layer.settings.layer_id;
-        transform.translation.z = layer.settings.layer_id as f32;
+        transform.translation.z = layer.settings.get_layer_depth();
         commands
             .entity(layer_builder.layer_entity)
             .insert_bundle(LayerBundle {
@@ -51,10 +58,11 @@ impl<'a> MapQuery<'a> {
 
     /// Adds or sets a new tile for a given layer.
     /// Returns an error if the tile is out of bounds.
+    /// An existing tile at the position is despawned along with any other components on it.
     /// It's important to know that the new tile wont exist until bevy flushes
     /// the commands during a hard sync point(between stages).
     /// A better option for updating existing tiles would be the following:
-    /// ```rust
+    /// ```ignore
     /// ...
     /// mut my_tile_query: Query<&mut Tile>,
     /// mut map_query: MapQuery,
@@ -119,6 +127,219 @@ impl<'a> MapQuery<'a> {
         Err(MapTileError::OutOfBounds)
     }
 
+    // Replaces the tiles of a chunk in the [`start`, `end`) rectangle with new tile entities, like
+    // `set_tile`, and flags the chunk for remeshing.
+    pub(crate) fn fill_chunk_rect(
+        &mut self,
+        commands: &mut Commands,
+        chunk_entity: Entity,
+        start: TilePos,
+        end: TilePos,
+        tile: Tile,
+        layer_id: u16,
+    ) {
+        if let Ok((_, mut chunk)) = self.chunk_query_set.q0_mut().get_mut(chunk_entity) {
+            let tile_parent = TileParent {
+                chunk: chunk_entity,
+                layer_id,
+                map_id: chunk.settings.map_id,
+            };
+            for x in start.0..end.0 {
+                for y in start.1..end.1 {
+                    let tile_pos = TilePos(x, y);
+                    let tile_index = morton_index(chunk.to_chunk_pos(tile_pos));
+                    if let Some(existing) = chunk.tiles[tile_index] {
+                        commands.entity(existing).despawn_recursive();
+                    }
+                    let tile_entity = commands
+                        .spawn()
+                        .insert(tile)
+                        .insert(tile_parent.clone())
+                        .insert(tile_pos)
+                        .id();
+                    chunk.tiles[tile_index] = Some(tile_entity);
+                }
+            }
+            chunk.needs_remesh = true;
+        }
+    }
+
+    /// Applies the tiles queued in the batch and clears it.
+    /// Returns the number of chunks which will be remeshed.
+    ///
+    /// Existing tile components are updated in place, like with `flood_fill`, and positions
+    /// without a tile get a new tile entity, like with `set_tile`.
+    /// The writes are grouped by chunk, so every changed chunk is looked up and remeshed once no
+    /// matter how many of its tiles changed. Positions outside of the layer are skipped.
+    pub fn apply_tile_batch(
+        &mut self,
+        commands: &mut Commands,
+        batch: &mut TileUpdateBatch,
+        tile_query: &mut Query<&mut Tile>,
+        map_id: impl MapId,
+        layer_id: impl LayerId,
+    ) -> usize {
+        let map_id = map_id.into();
+        let layer_id = layer_id.into();
+        let layer_entity = match self
+            .map_query_set
+            .q1()
+            .iter()
+            .find(|(_, map)| map.id == map_id)
+            .and_then(|(_, map)| map.get_layer_entity(layer_id).copied())
+        {
+            Some(layer_entity) => layer_entity,
+            None => {
+                batch.clear();
+                return 0;
+            }
+        };
+        let layer = match self.layer_query_set.q1().get(layer_entity) {
+            Ok((_, layer)) => layer,
+            Err(_) => {
+                batch.clear();
+                return 0;
+            }
+        };
+
+        let mut chunk_updates: HashMap<Entity, Vec<(TilePos, Tile)>> = HashMap::default();
+        for (tile_pos, tile) in batch.updates.drain() {
+            let chunk_pos = ChunkPos(
+                tile_pos.0 / layer.settings.chunk_size.0,
+                tile_pos.1 / layer.settings.chunk_size.1,
+            );
+            if let Some(chunk_entity) = layer.get_chunk(chunk_pos) {
+                chunk_updates
+                    .entry(chunk_entity)
+                    .or_default()
+                    .push((tile_pos, tile));
+            }
+        }
+
+        let mut changed_chunks = 0;
+        for (chunk_entity, updates) in chunk_updates {
+            if let Ok((_, mut chunk)) = self.chunk_query_set.q0_mut().get_mut(chunk_entity) {
+                let tile_parent = TileParent {
+                    chunk: chunk_entity,
+                    layer_id,
+                    map_id: layer.settings.map_id,
+                };
+                write_chunk_tiles(commands, &mut chunk, tile_parent, updates, tile_query);
+                changed_chunks += 1;
+            }
+        }
+        changed_chunks
+    }
+
+    /// Gets the tiles of a chunk in row major order, starting with the bottom left tile of the
+    /// chunk, `None` for positions without a tile.
+    /// Returns `None` if the chunk doesn't exist.
+    pub fn get_chunk_tiles(
+        &self,
+        tile_query: &Query<&Tile>,
+        chunk_pos: ChunkPos,
+        map_id: impl MapId,
+        layer_id: impl LayerId,
+    ) -> Option<Vec<Option<Tile>>> {
+        let (_, layer) = self.get_layer(map_id, layer_id)?;
+        let (_, chunk) = self
+            .chunk_query_set
+            .q1()
+            .get(layer.get_chunk(chunk_pos)?)
+            .ok()?;
+        let chunk_size = chunk.settings.chunk_size;
+        let tiles = (0..chunk_size.1)
+            .flat_map(|y| (0..chunk_size.0).map(move |x| LocalTilePos(x, y)))
+            .map(|local_pos| {
+                chunk
+                    .get_tile_entity(local_pos)
+                    .and_then(|tile_entity| tile_query.get(tile_entity).ok())
+                    .copied()
+            })
+            .collect();
+        Some(tiles)
+    }
+
+    /// Sets every tile of a chunk at once, for generators which compute whole chunks.
+    /// `tiles` are in row major order like with `get_chunk_tiles`, one for every position of
+    /// the chunk, and `None` empties a position. Setting the tiles read with `get_chunk_tiles`
+    /// leaves the chunk as it was.
+    ///
+    /// Existing tile components are updated in place and positions without a tile get a new tile
+    /// entity, like with `apply_tile_batch`. The chunk is flagged for remeshing once.
+    /// Returns `MapTileError::NonExistent` if the layer doesn't exist, `OutOfBounds` if the chunk
+    /// is outside of the layer and `WrongTileCount` if the number of tiles doesn't match the
+    /// chunk size.
+    /// ```
+    /// use bevy::prelude::*;
+    /// use bevy_ecs_tilemap::prelude::*;
+    ///
+    /// // Generates the chunk at (1, 1) of a layer with 4x3 chunks, leaving every other
+    /// // position empty.
+    /// fn generate(
+    ///     mut commands: Commands,
+    ///     mut map_query: MapQuery,
+    ///     mut tile_query: Query<&mut Tile>,
+    /// ) {
+    ///     let tiles: Vec<Option<Tile>> = (0..12)
+    ///         .map(|index| (index % 2 == 1).then(Tile::default))
+    ///         .collect();
+    ///     map_query
+    ///         .set_chunk_tiles(&mut commands, &mut tile_query, ChunkPos(1, 1), &tiles, 0u16, 0u16)
+    ///         .unwrap();
+    /// }
+    /// ```
+    pub fn set_chunk_tiles(
+        &mut self,
+        commands: &mut Commands,
+        tile_query: &mut Query<&mut Tile>,
+        chunk_pos: ChunkPos,
+        tiles: &[Option<Tile>],
+        map_id: impl MapId,
+        layer_id: impl LayerId,
+    ) -> Result<(), MapTileError> {
+        let layer_id = layer_id.into();
+        let (_, layer) = self
+            .get_layer(map_id, layer_id)
+            .ok_or(MapTileError::NonExistent)?;
+        let chunk_size = layer.settings.chunk_size;
+        if tiles.len() != (chunk_size.0 * chunk_size.1) as usize {
+            return Err(MapTileError::WrongTileCount);
+        }
+        let chunk_entity = layer
+            .get_chunk(chunk_pos)
+            .ok_or(MapTileError::OutOfBounds)?;
+        let tile_parent = TileParent {
+            chunk: chunk_entity,
+            layer_id,
+            map_id: layer.settings.map_id,
+        };
+
+        let (_, mut chunk) = self
+            .chunk_query_set
+            .q0_mut()
+            .get_mut(chunk_entity)
+            .map_err(|_| MapTileError::NonExistent)?;
+        let origin = TilePos(chunk_pos.0 * chunk_size.0, chunk_pos.1 * chunk_size.1);
+        let mut updates = Vec::with_capacity(tiles.len());
+        for (index, tile) in tiles.iter().enumerate() {
+            let x = index as u32 % chunk_size.0;
+            let y = index as u32 / chunk_size.0;
+            let tile_pos = TilePos(origin.0 + x, origin.1 + y);
+            match tile {
+                Some(tile) => updates.push((tile_pos, *tile)),
+                None => {
+                    let tile_index = morton_index(LocalTilePos(x, y));
+                    if let Some(tile_entity) = chunk.tiles[tile_index].take() {
+                        commands.entity(tile_entity).despawn_recursive();
+                    }
+                }
+            }
+        }
+        write_chunk_tiles(commands, &mut chunk, tile_parent, updates, tile_query);
+        Ok(())
+    }
+
     pub fn get_layer(
         &self,
         map_id: impl MapId,
@@ -142,7 +363,49 @@ impl<'a> MapQuery<'a> {
         None
     }
 
+    /// Iterates over the chunk entities of a layer, for custom rendering or debugging.
+    /// Returns no chunks if the layer doesn't exist.
+    ///
+    /// ## Example
+    ///
+    /// ```ignore
+    /// for chunk_entity in map_query.get_layer_chunks(0u16, 0u16) {
+    ///     commands.entity(chunk_entity).insert(Wireframe);
+    /// }
+    /// ```
+    pub fn get_layer_chunks(
+        &self,
+        map_id: impl MapId,
+        layer_id: impl LayerId,
+    ) -> impl Iterator<Item = Entity> + '_ {
+        self.get_layer(map_id, layer_id)
+            .into_iter()
+            .flat_map(|(_, layer)| layer.iter_chunks().map(|(_, chunk_entity)| chunk_entity))
+    }
+
+    /// Swaps the material of every chunk in a layer, for example to reskin a level at runtime.
+    /// The tiles and chunk meshes are kept as they are, so the new texture should have the same
+    /// size and tile layout as the old one.
+    pub fn set_layer_material(
+        &self,
+        commands: &mut Commands,
+        material: Handle<ColorMaterial>,
+        map_id: impl MapId,
+        layer_id: impl LayerId,
+    ) {
+        if let Some((_, layer)) = self.get_layer(map_id, layer_id) {
+            for chunk_entity in layer.chunks.iter().flatten() {
+                commands.entity(*chunk_entity).insert(material.clone());
+            }
+        }
+    }
+
     /// Gets a tile entity for the given position and layer_id returns an error if OOB or the tile doesn't exist.
+    ///
+    /// Gameplay components can be inserted on the tile entity and queried alongside `Tile`.
+    /// The entity lives until the tile is despawned or replaced with `set_tile`, remeshing a
+    /// chunk never touches its tile entities. Changing the `Tile` component in place keeps the
+    /// entity and its other components.
     pub fn get_tile_entity(
         &self,
         tile_pos: TilePos,
@@ -180,7 +443,279 @@ impl<'a> MapQuery<'a> {
         Err(MapTileError::OutOfBounds)
     }
 
+    /// Iterates over the tiles within the rectangle defined by the `[start`, `end`) positions.
+    /// The rectangle is clipped to the layer and positions without a tile are skipped.
+    /// Tiles are looked up through the chunks overlapping the rectangle and visited chunk by
+    /// chunk, so the order isn't row major.
+    ///
+    /// ## Example
+    ///
+    /// ```ignore
+    /// for (tile_pos, tile) in map_query.tiles_in_rect(&tile_query, TilePos(0, 0), TilePos(10, 10), 0u16, 0u16) {
+    ///     ...
+    /// }
+    /// ```
+    pub fn tiles_in_rect<'s, 'w: 's, 't: 's>(
+        &'s self,
+        tile_query: &'s Query<'w, &'t Tile>,
+        start: TilePos,
+        end: TilePos,
+        map_id: impl MapId,
+        layer_id: impl LayerId,
+    ) -> Box<dyn Iterator<Item = (TilePos, &'s Tile)> + 's> {
+        let layer = self.get_layer(map_id, layer_id).map(|(_, layer)| layer);
+        let (end, chunk_size) = match layer {
+            Some(layer) => {
+                let layer_size = layer.get_layer_size_in_tiles();
+                (
+                    TilePos(end.0.min(layer_size.0), end.1.min(layer_size.1)),
+                    layer.settings.chunk_size,
+                )
+            }
+            None => (start, ChunkSize(1, 1)),
+        };
+        let chunk_start = ChunkPos(start.0 / chunk_size.0, start.1 / chunk_size.1);
+        let chunk_end = ChunkPos(
+            (end.0 + chunk_size.0 - 1) / chunk_size.0,
+            (end.1 + chunk_size.1 - 1) / chunk_size.1,
+        );
+
+        let tiles = (chunk_start.1..chunk_end.1)
+            .flat_map(move |y| (chunk_start.0..chunk_end.0).map(move |x| ChunkPos(x, y)))
+            .filter_map(move |chunk_pos| layer.and_then(|layer| layer.get_chunk(chunk_pos)))
+            .filter_map(move |chunk_entity| self.chunk_query_set.q1().get(chunk_entity).ok())
+            .flat_map(move |(_, chunk)| {
+                let min = TilePos(
+                    start.0.max(chunk.position.0 * chunk_size.0),
+                    start.1.max(chunk.position.1 * chunk_size.1),
+                );
+                let max = TilePos(
+                    end.0.min((chunk.position.0 + 1) * chunk_size.0),
+                    end.1.min((chunk.position.1 + 1) * chunk_size.1),
+                );
+                (min.1..max.1)
+                    .flat_map(move |y| (min.0..max.0).map(move |x| TilePos(x, y)))
+                    .filter_map(move |tile_pos| {
+                        chunk
+                            .get_tile_entity(chunk.to_chunk_pos(tile_pos))
+                            .map(|tile_entity| (tile_pos, tile_entity))
+                    })
+            })
+            .filter_map(move |(tile_pos, tile_entity)| {
+                tile_query
+                    .get(tile_entity)
+                    .ok()
+                    .map(|tile| (tile_pos, tile))
+            });
+
+        Box::new(tiles)
+    }
+
+    /// Finds the positions of the tiles in a layer for which `predicate` returns true, for
+    /// gameplay scans like finding every door tile.
+    /// The tiles are read straight from the storage of each chunk and visited chunk by chunk,
+    /// so the order isn't row major. Returns no positions if the layer doesn't exist.
+    ///
+    /// ## Example
+    ///
+    /// ```ignore
+    /// let doors = map_query.find_tiles(&tile_query, |tile| tile.texture_index == DOOR, 0u16, 0u16);
+    /// ```
+    pub fn find_tiles<F>(
+        &self,
+        tile_query: &Query<&Tile>,
+        predicate: F,
+        map_id: impl MapId,
+        layer_id: impl LayerId,
+    ) -> Vec<TilePos>
+    where
+        F: Fn(&Tile) -> bool,
+    {
+        self.layer_tiles(tile_query, map_id, layer_id)
+            .filter(|(_, tile)| predicate(tile))
+            .map(|(tile_pos, _)| tile_pos)
+            .collect()
+    }
+
+    /// Counts the tiles in a layer for which `predicate` returns true, like `find_tiles`
+    /// without collecting their positions.
+    pub fn count_tiles<F>(
+        &self,
+        tile_query: &Query<&Tile>,
+        predicate: F,
+        map_id: impl MapId,
+        layer_id: impl LayerId,
+    ) -> usize
+    where
+        F: Fn(&Tile) -> bool,
+    {
+        self.layer_tiles(tile_query, map_id, layer_id)
+            .filter(|(_, tile)| predicate(tile))
+            .count()
+    }
+
+    // Iterates over every tile of a layer in the order they are stored in its chunks.
+    fn layer_tiles<'s, 'w: 's, 't: 's>(
+        &'s self,
+        tile_query: &'s Query<'w, &'t Tile>,
+        map_id: impl MapId,
+        layer_id: impl LayerId,
+    ) -> Box<dyn Iterator<Item = (TilePos, &'s Tile)> + 's> {
+        let layer = self.get_layer(map_id, layer_id).map(|(_, layer)| layer);
+        let tiles = layer
+            .into_iter()
+            .flat_map(|layer| layer.chunks.iter().flatten())
+            .filter_map(move |chunk_entity| self.chunk_query_set.q1().get(*chunk_entity).ok())
+            .flat_map(move |(_, chunk)| {
+                chunk
+                    .tiles
+                    .iter()
+                    .enumerate()
+                    .filter_map(move |(index, tile_entity)| {
+                        let tile = tile_query.get((*tile_entity)?).ok()?;
+                        let tile_pos =
+                            chunk_to_tile(chunk.position, index, chunk.settings.chunk_size);
+                        Some((tile_pos, tile))
+                    })
+            });
+
+        Box::new(tiles)
+    }
+
+    /// Gets the rectangle in world space covering the chunks of every layer in a map, taking
+    /// each layer's `GlobalTransform`, anchor and tile size into account, e.g. to fit a camera to
+    /// the whole level.
+    /// Only chunks with tiles count, so sparse maps get the bounds of the tiles that were placed,
+    /// unless the layer has a `default_tile`. The bounds are as coarse as the chunks.
+    /// Returns `None` if the map doesn't exist or has no tiles.
+    /// Note: Staggered isometric layers are skipped.
+    pub fn map_world_bounds(&self, map_id: impl MapId) -> Option<bevy::sprite::Rect> {
+        let map_id = map_id.into();
+        let (_, map) = self
+            .map_query_set
+            .q1()
+            .iter()
+            .find(|(_, map)| map.id == map_id)?;
+
+        let mut bounds: Option<bevy::sprite::Rect> = None;
+        for layer_entity in map.layers.values() {
+            let layer = match self.layer_query_set.q1().get(*layer_entity) {
+                Ok((_, layer)) => layer,
+                Err(_) => continue,
+            };
+            let transform = match self.layer_transform_query.get(*layer_entity) {
+                Ok(transform) => transform,
+                Err(_) => continue,
+            };
+            let anchor_offset = layer.settings.get_anchor_offset();
+            for chunk_entity in layer.chunks.iter().flatten() {
+                let chunk = match self.chunk_query_set.q1().get(*chunk_entity) {
+                    Ok((_, chunk)) => chunk,
+                    Err(_) => continue,
+                };
+                if layer.settings.default_tile.is_none() && chunk.tiles.iter().all(Option::is_none)
+                {
+                    continue;
+                }
+                let (local_min, local_max) = match chunk.get_local_bounds() {
+                    Some(local_bounds) => local_bounds,
+                    None => continue,
+                };
+
+                let origin = layer.settings.get_chunk_origin(chunk.position) - anchor_offset;
+                let (min, max) = (origin + local_min, origin + local_max);
+                for corner in [min, Vec2::new(min.x, max.y), max, Vec2::new(max.x, min.y)] {
+                    let corner = transform.mul_vec3(corner.extend(0.0)).xy();
+                    bounds = Some(match bounds {
+                        Some(bounds) => bevy::sprite::Rect {
+                            min: bounds.min.min(corner),
+                            max: bounds.max.max(corner),
+                        },
+                        None => bevy::sprite::Rect {
+                            min: corner,
+                            max: corner,
+                        },
+                    });
+                }
+            }
+        }
+        bounds
+    }
+
+    /// Gets the tile position under a world position, e.g. the mouse cursor after it has been
+    /// transformed by the camera. Takes the layer's `GlobalTransform` into account.
+    /// Returns an error if the position is outside of the layer.
+    /// Note: Only square and isometric layers are supported, hex layers always return
+    /// `MapTileError::OutOfBounds`.
+    pub fn get_tile_pos_at_world_pos(
+        &self,
+        world_pos: Vec2,
+        map_id: impl MapId,
+        layer_id: impl LayerId,
+    ) -> Result<TilePos, MapTileError> {
+        if let Some((layer_entity, layer)) = self.get_layer(map_id, layer_id) {
+            if let Ok(transform) = self.layer_transform_query.get(layer_entity) {
+                let local_pos = world_to_local_pos(transform, world_pos);
+                if let Some(tile_pos) = local_pos_to_tile_pos(local_pos, layer) {
+                    return Ok(tile_pos);
+                }
+            }
+        }
+
+        Err(MapTileError::OutOfBounds)
+    }
+
+    /// Gets the tile position and tile entity under a world position.
+    /// Returns an error if the position is outside of the layer or there is no tile there.
+    pub fn get_tile_entity_at_world_pos(
+        &self,
+        world_pos: Vec2,
+        map_id: impl MapId,
+        layer_id: impl LayerId,
+    ) -> Result<(TilePos, Entity), MapTileError> {
+        let map_id = map_id.into();
+        let layer_id = layer_id.into();
+        let tile_pos = self.get_tile_pos_at_world_pos(world_pos, map_id, layer_id)?;
+        let tile_entity = self.get_tile_entity(tile_pos, map_id, layer_id)?;
+        Ok((tile_pos, tile_entity))
+    }
+
+    /// Gets the tile position under a screen position, e.g. `Window::cursor_position`.
+    /// The screen position is in logical pixels from the bottom left corner of the window.
+    /// Inverts the camera projection and transform, so zoomed and rotated cameras are supported.
+    /// Returns `None` if the position is outside of the layer.
+    pub fn screen_to_tile(
+        &self,
+        screen_pos: Vec2,
+        window_size: Vec2,
+        camera: &Camera,
+        camera_transform: &GlobalTransform,
+        map_id: impl MapId,
+        layer_id: impl LayerId,
+    ) -> Option<TilePos> {
+        let world_pos = screen_to_world_pos(screen_pos, window_size, camera, camera_transform);
+        self.get_tile_pos_at_world_pos(world_pos, map_id, layer_id)
+            .ok()
+    }
+
+    /// Adds or sets a new tile under a world position, see `set_tile`.
+    /// The chunk is remeshed once the new tile has been spawned.
+    pub fn set_tile_at_world_pos(
+        &mut self,
+        commands: &mut Commands,
+        world_pos: Vec2,
+        tile: Tile,
+        map_id: impl MapId,
+        layer_id: impl LayerId,
+    ) -> Result<Entity, MapTileError> {
+        let map_id = map_id.into();
+        let layer_id = layer_id.into();
+        let tile_pos = self.get_tile_pos_at_world_pos(world_pos, map_id, layer_id)?;
+        self.set_tile(commands, tile_pos, tile, map_id, layer_id)
+    }
+
     /// Despawns the tile entity and removes it from the layer/chunk cache.
+    /// The chunk is remeshed without the tile.
     pub fn despawn_tile(
         &mut self,
         commands: &mut Commands,
@@ -211,6 +746,7 @@ impl<'a> MapQuery<'a> {
                                 commands.entity(tile).despawn_recursive();
                                 let morton_tile_index = morton_index(chunk_tile_pos);
                                 chunk.tiles[morton_tile_index] = None;
+                                chunk.needs_remesh = true;
                                 return Ok(());
                             } else {
                                 return Err(MapTileError::NonExistent);
@@ -224,6 +760,7 @@ impl<'a> MapQuery<'a> {
     }
 
     /// Despawns all of the tiles in a layer.
+    /// The chunks are kept and remeshed empty, so new tiles can be added to the layer again.
     /// Note: Doesn't despawn the layer.
     pub fn despawn_layer_tiles(
         &mut self,
@@ -257,6 +794,7 @@ impl<'a> MapQuery<'a> {
                                         commands.entity(tile).despawn_recursive();
                                         let morton_tile_index = morton_index(chunk_tile_pos);
                                         chunk.tiles[morton_tile_index] = None;
+                                        chunk.needs_remesh = true;
                                     }
                                 }
                             }
@@ -268,6 +806,8 @@ impl<'a> MapQuery<'a> {
     }
 
     /// Despawns a layer completely including all tiles.
+    /// The chunk entities own their meshes and material handles, so these assets are freed
+    /// once nothing else uses them.
     pub fn despawn_layer(
         &mut self,
         commands: &mut Commands,
@@ -309,7 +849,7 @@ impl<'a> MapQuery<'a> {
             .iter()
             .find(|(_, map)| map.id == map_id)
         {
-            Some(map.layers.keys().map(|layer_id| *layer_id).collect())
+            Some(map.layers.keys().copied().collect())
         } else {
             None
         };
@@ -330,6 +870,100 @@ impl<'a> MapQuery<'a> {
         }
     }
 
+    /// Saves every layer of a map to a versioned binary blob.
+    /// The blob stores the layer settings and every tile's texture index, flip flags,
+    /// visibility and color.
+    #[cfg(feature = "serialize")]
+    pub fn save_map(
+        &self,
+        map_id: impl MapId,
+        tile_query: &Query<&Tile>,
+    ) -> Result<Vec<u8>, MapSerializeError> {
+        let map_id = map_id.into();
+        let (_, map) = self
+            .map_query_set
+            .q1()
+            .iter()
+            .find(|(_, map)| map.id == map_id)
+            .ok_or(MapSerializeError::NonExistent)?;
+
+        let mut layer_ids: Vec<u16> = map.layers.keys().copied().collect();
+        layer_ids.sort_unstable();
+
+        let mut layers = Vec::new();
+        for layer_id in layer_ids {
+            let layer_entity = map.layers[&layer_id];
+            let layer = match self.layer_query_set.q1().get(layer_entity) {
+                Ok((_, layer)) => layer,
+                Err(_) => continue,
+            };
+
+            let mut tiles = Vec::new();
+            for chunk_entity in layer.chunks.iter().flatten() {
+                if let Ok((_, chunk)) = self.chunk_query_set.q1().get(*chunk_entity) {
+                    chunk.for_each_tile_entity(|(chunk_tile_pos, tile_entity)| {
+                        if let Some(tile) = tile_entity.and_then(|e| tile_query.get(e).ok()) {
+                            let tile_pos = TilePos(
+                                chunk.position.0 * chunk.settings.chunk_size.0 + chunk_tile_pos.0,
+                                chunk.position.1 * chunk.settings.chunk_size.1 + chunk_tile_pos.1,
+                            );
+                            tiles.push((tile_pos, *tile));
+                        }
+                    });
+                }
+            }
+            tiles.sort_unstable_by_key(|(tile_pos, _)| (tile_pos.1, tile_pos.0));
+
+            layers.push(SerializedLayer {
+                settings: layer.settings,
+                tiles,
+            });
+        }
+
+        SerializedMap {
+            version: MAP_FORMAT_VERSION,
+            map_id,
+            layers,
+        }
+        .to_bytes()
+    }
+
+    /// Rebuilds the layers of a map saved with `save_map`.
+    /// `material_for_layer` is called with each layer id to get the material the layer is
+    /// built with. Returns the map component which should be inserted on `map_entity`.
+    #[cfg(feature = "serialize")]
+    pub fn load_map(
+        &mut self,
+        commands: &mut Commands,
+        bytes: &[u8],
+        map_entity: Entity,
+        mut material_for_layer: impl FnMut(u16) -> Handle<ColorMaterial>,
+    ) -> Result<Map, MapSerializeError> {
+        let serialized_map = SerializedMap::from_bytes(bytes)?;
+        let mut map = Map::new(serialized_map.map_id, map_entity);
+
+        for serialized_layer in serialized_map.layers {
+            let layer_id = serialized_layer.settings.layer_id;
+            let (mut layer_builder, _) = LayerBuilder::<TileBundle>::new(
+                commands,
+                serialized_layer.settings,
+                serialized_map.map_id,
+                layer_id,
+                None,
+            );
+            for (tile_pos, tile) in serialized_layer.tiles {
+                // Tiles were read from a layer with the same settings so they are in bounds.
+                let _ = layer_builder.set_tile(tile_pos, tile.into());
+            }
+
+            let layer_entity =
+                self.build_layer(commands, layer_builder, material_for_layer(layer_id));
+            map.add_layer(commands, layer_id, layer_entity);
+        }
+
+        Ok(map)
+    }
+
     /// Let's the internal systems know to "remesh" the chunk.
     pub fn notify_chunk(&mut self, chunk_entity: Entity) {
         if let Ok((_, mut chunk)) = self.chunk_query_set.q0_mut().get_mut(chunk_entity) {
@@ -378,6 +1012,9 @@ impl<'a> MapQuery<'a> {
     /// 2. Use tile Y position to calculate z-index.
     /// 3. Z-index is scaled to be 0-1.
     /// 4. Add expected layer id to Z-index
+    /// For square and hex layers with `LayerSettings::y_sort` enabled the z-index is based on
+    /// the tile row under the pixel position instead, taking the layer's transform, anchor and
+    /// `tile_offset` into account.
     /// Note the layer_id in this case is past in by the user as pixel_position.z
     /// The user needs to handle what layer the sprite exists in within their own code.
     /// The primary use case for this function is to allow users to calculate
@@ -403,6 +1040,23 @@ impl<'a> MapQuery<'a> {
                     let grid_size = layer.settings.grid_size;
                     let layer_size_in_tiles: Vec2 = layer.get_layer_size_in_tiles().into();
                     let map_size: Vec2 = layer_size_in_tiles * grid_size;
+
+                    // Y sorted square and hex layers sort by tile row.
+                    if layer.settings.y_sort
+                        && !matches!(layer.settings.mesh_type, TilemapMeshType::Isometric(_))
+                    {
+                        let transform = self
+                            .layer_transform_query
+                            .get(*layer_entity)
+                            .map_or_else(|_| GlobalTransform::default(), |transform| *transform);
+                        let local_pos = world_to_local_pos(&transform, pixel_position.xy())
+                            + layer.settings.get_anchor_offset()
+                            - layer.settings.tile_offset;
+                        let row = (local_pos.y / grid_size.y).floor();
+                        return pixel_position.z
+                            + (1.0 - (row / layer_size_in_tiles.y)) * Y_SORT_DEPTH_SCALE;
+                    }
+
                     let map_pos = unproject_iso(pixel_position.xy(), grid_size.x, grid_size.y);
                     let center = project_iso(
                         Vec2::new(map_pos.x, map_pos.y - 2.0),
@@ -410,7 +1064,11 @@ impl<'a> MapQuery<'a> {
                         grid_size.y,
                     );
 
-                    return pixel_position.z + (1.0 - (center.y / map_size.y));
+                    let depth = 1.0 - (center.y / map_size.y);
+                    if layer.settings.y_sort {
+                        return pixel_position.z + depth * Y_SORT_DEPTH_SCALE;
+                    }
+                    return pixel_position.z + depth;
                 }
             }
         }
@@ -419,6 +1077,124 @@ impl<'a> MapQuery<'a> {
     }
 }
 
+/// Converts a screen position in logical pixels from the bottom left corner of the window into a
+/// world position on the camera's near plane.
+pub fn screen_to_world_pos(
+    screen_pos: Vec2,
+    window_size: Vec2,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> Vec2 {
+    let ndc = (screen_pos / window_size) * 2.0 - Vec2::ONE;
+    let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix.inverse();
+    ndc_to_world.project_point3(ndc.extend(0.0)).xy()
+}
+
+/// Transforms a world position into a position relative to the layer.
+fn world_to_local_pos(transform: &GlobalTransform, world_pos: Vec2) -> Vec2 {
+    let local_pos = transform.rotation.inverse()
+        * (world_pos.extend(transform.translation.z) - transform.translation)
+        / transform.scale;
+    local_pos.xy()
+}
+
+/// Converts a position relative to the layer into a tile position.
+fn local_pos_to_tile_pos(local_pos: Vec2, layer: &Layer) -> Option<TilePos> {
+    let settings = &layer.settings;
+    let local_pos = local_pos + settings.get_anchor_offset();
+    let tile_pos = match settings.mesh_type {
+        TilemapMeshType::Square => local_pos / settings.grid_size,
+        TilemapMeshType::Isometric(IsoType::Diamond) => {
+            // Tiles hang below their projected position, so the center of tile (0, 0) is at
+            // (0.5, 0.5) in unprojected space.
+            let half_width = settings.grid_size.x / 2.0;
+            let half_height = settings.grid_size.y / 2.0;
+            Vec2::new(
+                ((local_pos.x / half_width) - (local_pos.y / half_height)) / 2.0,
+                (-(local_pos.y / half_height) - (local_pos.x / half_width)) / 2.0,
+            )
+        }
+        TilemapMeshType::Isometric(IsoType::Staggered) => {
+            unproject_staggered(local_pos, settings.grid_size)?
+        }
+        _ => return None,
+    }
+    .floor();
+
+    let layer_size = layer.get_layer_size_in_tiles();
+    let tile_pos = if settings.wrap {
+        Vec2::new(
+            tile_pos.x.rem_euclid(layer_size.0 as f32),
+            tile_pos.y.rem_euclid(layer_size.1 as f32),
+        )
+    } else {
+        tile_pos
+    };
+    if tile_pos.x < 0.0
+        || tile_pos.y < 0.0
+        || tile_pos.x >= layer_size.0 as f32
+        || tile_pos.y >= layer_size.1 as f32
+    {
+        return None;
+    }
+
+    Some(TilePos(tile_pos.x as u32, tile_pos.y as u32))
+}
+
+// Writes tiles into a chunk, updating existing tile components in place and spawning tile
+// entities for empty positions.
+fn write_chunk_tiles(
+    commands: &mut Commands,
+    chunk: &mut Chunk,
+    tile_parent: TileParent,
+    tiles: impl IntoIterator<Item = (TilePos, Tile)>,
+    tile_query: &mut Query<&mut Tile>,
+) {
+    for (tile_pos, tile) in tiles {
+        let tile_index = morton_index(chunk.to_chunk_pos(tile_pos));
+        if let Some(tile_entity) = chunk.tiles[tile_index] {
+            if let Ok(mut existing) = tile_query.get_mut(tile_entity) {
+                *existing = tile;
+                continue;
+            }
+        }
+
+        let tile_entity = commands
+            .spawn()
+            .insert(tile)
+            .insert(tile_parent.clone())
+            .insert(tile_pos)
+            .id();
+        chunk.tiles[tile_index] = Some(tile_entity);
+    }
+    // Flagging the chunk up front skips the per tile checks for changed tiles.
+    chunk.needs_remesh = true;
+}
+
+// Finds the staggered tile whose diamond contains the position.
+// Rows are half a tile apart and even rows are shifted right by a quarter of a tile, odd rows
+// left, so a position can only be in one of two rows.
+fn unproject_staggered(pos: Vec2, grid_size: Vec2) -> Option<Vec2> {
+    let half_width = grid_size.x / 2.0;
+    let half_height = grid_size.y / 2.0;
+    let offset = (0.25 * grid_size.x).floor();
+    let row = (pos.y / half_height).floor();
+    for row in [row, row - 1.0] {
+        let offset = if row as i32 % 2 == 0 { offset } else { -offset };
+        let column = ((pos.x - offset) / grid_size.x).floor();
+        let center = Vec2::new(
+            column * grid_size.x + half_width + offset,
+            row * half_height + half_height,
+        );
+        let distance = (pos - center).abs() / Vec2::new(half_width, half_height);
+        if distance.x + distance.y <= 1.0 {
+            return Some(Vec2::new(column, row));
+        }
+    }
+
+    None
+}
+
 pub fn unproject_iso(pos: Vec2, tile_width: f32, tile_height: f32) -> Vec2 {
     let half_width = tile_width / 2.0;
     let half_height = tile_height / 2.0;
@@ -430,5 +1206,153 @@ pub fn unproject_iso(pos: Vec2, tile_width: f32, tile_height: f32) -> Vec2 {
 fn project_iso(pos: Vec2, tile_width: f32, tile_height: f32) -> Vec2 {
     let x = (pos.x - pos.y) * tile_width / 2.0;
     let y = (pos.x + pos.y) * tile_height / 2.0;
-    return Vec2::new(x, -y);
+    Vec2::new(x, -y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{layer_settings, new_world, run_system, spawn_layer};
+
+    struct ZIndex(Vec3, f32);
+
+    fn z_index(mut z_index: ResMut<ZIndex>, map_query: MapQuery) {
+        z_index.1 = map_query.get_zindex_for_pixel_pos(z_index.0, 0u16, 0u16);
+    }
+
+    fn get_zindex(world: &mut World, pixel_position: Vec3) -> f32 {
+        world.insert_resource(ZIndex(pixel_position, 0.0));
+        run_system(world, z_index.system());
+        world.get_resource::<ZIndex>().unwrap().1
+    }
+
+    // Every other position of the chunk at (1, 1) gets a tile.
+    fn generate(mut commands: Commands, mut map_query: MapQuery, mut tile_query: Query<&mut Tile>) {
+        let tiles: Vec<Option<Tile>> = (0..12)
+            .map(|index| {
+                (index % 2 == 1).then(|| Tile {
+                    texture_index: index,
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let mut set = |chunk_pos, tiles: &[Option<Tile>], layer_id: u16| {
+            map_query.set_chunk_tiles(
+                &mut commands,
+                &mut tile_query,
+                chunk_pos,
+                tiles,
+                0u16,
+                layer_id,
+            )
+        };
+        set(ChunkPos(1, 1), &tiles, 0).unwrap();
+
+        assert!(matches!(
+            set(ChunkPos(0, 0), &tiles[1..], 0),
+            Err(MapTileError::WrongTileCount)
+        ));
+        assert!(matches!(
+            set(ChunkPos(2, 0), &tiles, 0),
+            Err(MapTileError::OutOfBounds)
+        ));
+        assert!(matches!(
+            set(ChunkPos(0, 0), &tiles, 1),
+            Err(MapTileError::NonExistent)
+        ));
+    }
+
+    // Reading the chunk back and setting it again leaves it as it was.
+    fn round_trip(
+        mut commands: Commands,
+        mut map_query: MapQuery,
+        mut tile_query: QuerySet<(Query<&Tile>, Query<&mut Tile>)>,
+    ) {
+        let tiles = map_query
+            .get_chunk_tiles(tile_query.q0(), ChunkPos(1, 1), 0u16, 0u16)
+            .unwrap();
+        for (index, tile) in tiles.iter().enumerate() {
+            let expected = (index % 2 == 1).then(|| index as u16);
+            assert_eq!(tile.map(|tile| tile.texture_index), expected);
+        }
+        map_query
+            .set_chunk_tiles(
+                &mut commands,
+                tile_query.q1_mut(),
+                ChunkPos(1, 1),
+                &tiles,
+                0u16,
+                0u16,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn chunk_tiles_round_trip() {
+        let mut world = new_world();
+        let settings = layer_settings(MapSize(2, 2), ChunkSize(4, 3));
+        spawn_layer(&mut world, settings, Handle::default(), |layer_builder| {
+            // A tile which is emptied by the generated chunk.
+            layer_builder
+                .set_tile(TilePos(4, 3), TileBundle::default())
+                .unwrap();
+        });
+        run_system(&mut world, generate.system());
+        run_system(&mut world, round_trip.system());
+        run_system(&mut world, round_trip.system());
+
+        let mut tiles: Vec<(u32, u32, u16)> = world
+            .query::<(&TilePos, &Tile)>()
+            .iter(&world)
+            .map(|(tile_pos, tile)| (tile_pos.0, tile_pos.1, tile.texture_index))
+            .collect();
+        tiles.sort_unstable();
+        let mut expected: Vec<(u32, u32, u16)> = (0..12)
+            .filter(|index| index % 2 == 1)
+            .map(|index| (4 + index % 4, 3 + index / 4, index as u16))
+            .collect();
+        expected.sort_unstable();
+        assert_eq!(tiles, expected);
+    }
+
+    #[test]
+    fn zindex_without_y_sort_is_unchanged() {
+        let mut world = new_world();
+        let settings = layer_settings(MapSize(2, 2), ChunkSize(4, 4));
+        spawn_layer(&mut world, settings, Handle::default(), |_| {});
+
+        // The isometric depth the z index has always been.
+        for pixel_position in [
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(40.0, 72.0, 2.0),
+            Vec3::new(-24.0, 100.0, 0.0),
+        ] {
+            let map_pos = unproject_iso(pixel_position.xy(), 16.0, 16.0);
+            let center = project_iso(Vec2::new(map_pos.x, map_pos.y - 2.0), 16.0, 16.0);
+            let expected = pixel_position.z + (1.0 - (center.y / 128.0));
+            assert!((get_zindex(&mut world, pixel_position) - expected).abs() < f32::EPSILON);
+        }
+    }
+
+    #[test]
+    fn y_sorted_zindex_uses_the_layer_row() {
+        let mut world = new_world();
+        let mut settings = layer_settings(MapSize(2, 2), ChunkSize(4, 4));
+        settings.y_sort = true;
+        settings.anchor = TilemapAnchor::Center;
+        settings.tile_offset = Vec2::new(0.0, 4.0);
+        let layer_entity = spawn_layer(&mut world, settings, Handle::default(), |_| {});
+        world
+            .entity_mut(layer_entity)
+            .insert(GlobalTransform::from_xyz(100.0, 200.0, 0.0));
+
+        // The layer is centered on its transform, so row 0 starts 64 pixels below it.
+        let mut assert_row = |pixel_position: Vec3, row: f32| {
+            let expected = 1.0 + (1.0 - row / 8.0) * Y_SORT_DEPTH_SCALE;
+            assert!((get_zindex(&mut world, pixel_position) - expected).abs() < f32::EPSILON);
+        };
+        assert_row(Vec3::new(100.0, 200.0 - 64.0 + 4.0, 1.0), 0.0);
+        assert_row(Vec3::new(100.0, 200.0 + 4.0 + 8.0, 1.0), 4.0);
+        assert_row(Vec3::new(100.0, 200.0 + 4.0 - 1.0, 1.0), 3.0);
+    }
 }
@@ -0,0 +1,173 @@
+use crate::render::pipeline::create_chunk_pipeline_descriptor;
+use crate::{Chunk, Layer, TilemapBlendMode, TilemapMeshType, TilemapStage, TilemapTextureMode};
+use bevy::{
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        pipeline::{PipelineDescriptor, RenderPipeline},
+        render_graph::{base, AssetRenderResourcesNode, RenderGraph},
+        renderer::RenderResources,
+        shader::ShaderStages,
+    },
+};
+use std::{collections::HashMap, marker::PhantomData};
+
+/// A material which draws tilemap chunks with a custom fragment shader, and optionally a custom
+/// vertex shader.
+///
+/// By default the chunks still use the tilemap vertex shaders and the layer's `ColorMaterial`,
+/// so the fragment shader receives the same inputs as the default one:
+/// ```glsl
+/// layout(location = 0) in vec2 v_Uv;
+/// layout(location = 1) in vec4 v_color;
+/// layout(location = 2) in float v_TextureLayer;
+/// layout(location = 3) in float v_Emissive;
+/// layout(location = 4) in float v_Palette;
+/// layout(location = 6) in vec2 v_WorldPos;
+///
+/// layout(set = 1, binding = 0) uniform ColorMaterial_color { vec4 Color; };
+/// layout(set = 1, binding = 1) uniform texture2D ColorMaterial_texture;
+/// layout(set = 1, binding = 2) uniform sampler ColorMaterial_texture_sampler;
+/// ```
+/// Layers with `LayerSettings::greedy_meshing` define `TILEMAP_MERGED` for chunks with merged
+/// quads, `v_Uv` then runs past the tile's rectangle in the atlas and has to be wrapped into it:
+/// ```glsl
+/// #ifdef TILEMAP_MERGED
+/// layout(location = 5) in vec4 v_UvRect;
+/// #endif
+///
+/// vec2 uv = v_Uv;
+/// #ifdef TILEMAP_MERGED
+/// uv = v_UvRect.xy + mod(uv - v_UvRect.xy, v_UvRect.zw);
+/// #endif
+/// ```
+/// Layers with the `TilemapBlendMode::AlphaMask` blend mode define `TILEMAP_ALPHA_MASK`, the
+/// fragment shader should then discard texels below `alpha_cutoff` of the `TilemapData` uniform.
+/// The render resources of the material itself should be bound in set 3.
+/// A `Handle<Texture>` field binds a texture and its sampler, so a second atlas laid out like the
+/// color atlas, for example a normal map, can be sampled with the same `v_Uv`:
+/// ```glsl
+/// layout(set = 3, binding = 0) uniform texture2D NormalMapped_normal_map;
+/// layout(set = 3, binding = 1) uniform sampler NormalMapped_normal_map_sampler;
+/// ```
+///
+/// Add a `TilemapMaterialPlugin` for the material and insert a `Handle` to it on a layer entity
+/// to draw the layer's chunks with it.
+pub trait TilemapMaterial: RenderResources + TypeUuid + Send + Sync + 'static {
+    /// Gets the fragment shader used to draw chunks with this material.
+    fn fragment_shader(asset_server: &AssetServer, shaders: &mut Assets<Shader>) -> Handle<Shader>;
+
+    /// Gets the vertex shader used to draw chunks of a mesh type with this material, the
+    /// tilemap vertex shader for the mesh type is used when this returns `None`.
+    /// A custom vertex shader can read `TileVertexData` through the `Vertex_Data` attribute,
+    /// next to the attributes of the tilemap vertex shaders:
+    /// ```glsl
+    /// layout(location = 0) in vec3 Vertex_Position;
+    /// layout(location = 1) in ivec4 Vertex_Texture;
+    /// layout(location = 2) in vec4 Vertex_Color;
+    /// layout(location = 3) in vec4 Vertex_Data;
+    /// ```
+    /// Note: Every chunk drawn with the shader needs a tile with `TileVertexData`, otherwise
+    /// its mesh has no `Vertex_Data` attribute.
+    fn vertex_shader(
+        _asset_server: &AssetServer,
+        _shaders: &mut Assets<Shader>,
+        _mesh_type: TilemapMeshType,
+        _texture_mode: TilemapTextureMode,
+    ) -> Option<Handle<Shader>> {
+        None
+    }
+}
+
+/// Registers a `TilemapMaterial` and the systems which draw layers with it.
+/// Must be added after the `TilemapPlugin`.
+pub struct TilemapMaterialPlugin<M: TilemapMaterial>(PhantomData<M>);
+
+impl<M: TilemapMaterial> Default for TilemapMaterialPlugin<M> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<M: TilemapMaterial> Plugin for TilemapMaterialPlugin<M> {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_asset::<M>().add_system_to_stage(
+            TilemapStage,
+            apply_tilemap_material::<M>
+                .system()
+                .before("update_chunk_wireframe"),
+        );
+
+        let mut graph = app.world_mut().get_resource_mut::<RenderGraph>().unwrap();
+        let node_name = std::any::type_name::<M>();
+        graph.add_system_node(node_name, AssetRenderResourcesNode::<M>::new(true));
+        graph
+            .add_node_edge(node_name, base::node::MAIN_PASS)
+            .unwrap();
+    }
+}
+
+type PipelineKey = (TilemapMeshType, TilemapBlendMode, TilemapTextureMode);
+
+type ChangedMaterialLayerQuery<'a, M> =
+    Query<'a, (&'static Layer, &'static Handle<M>), Or<(Changed<Layer>, Changed<Handle<M>>)>>;
+
+// Swaps the pipelines of the chunks in layers with a material for ones using its shaders.
+#[allow(clippy::too_many_arguments)]
+fn apply_tilemap_material<M: TilemapMaterial>(
+    mut commands: Commands,
+    mut fragment_shader: Local<Option<Handle<Shader>>>,
+    mut material_pipelines: Local<HashMap<PipelineKey, Handle<PipelineDescriptor>>>,
+    asset_server: Res<AssetServer>,
+    mut shaders: ResMut<Assets<Shader>>,
+    mut pipelines: ResMut<Assets<PipelineDescriptor>>,
+    layer_query: ChangedMaterialLayerQuery<M>,
+    mut chunk_query: Query<(Option<&Handle<M>>, &mut RenderPipelines), With<Chunk>>,
+) {
+    for (layer, material) in layer_query.iter() {
+        let settings = &layer.settings;
+        for chunk_entity in layer.chunks.iter().flatten() {
+            if let Ok((chunk_material, mut render_pipelines)) = chunk_query.get_mut(*chunk_entity) {
+                if chunk_material == Some(material) {
+                    continue;
+                }
+
+                let fragment = fragment_shader
+                    .get_or_insert_with(|| M::fragment_shader(&asset_server, &mut shaders))
+                    .clone();
+                let key = (
+                    settings.mesh_type,
+                    settings.blend_mode,
+                    settings.texture_mode,
+                );
+                let pipeline = material_pipelines.entry(key).or_insert_with(|| {
+                    let vertex = M::vertex_shader(
+                        &asset_server,
+                        &mut shaders,
+                        settings.mesh_type,
+                        settings.texture_mode,
+                    );
+                    let shader_stages = match vertex {
+                        Some(vertex) => ShaderStages {
+                            vertex,
+                            fragment: Some(fragment),
+                        },
+                        None => settings.mesh_type.create_shader_stages(
+                            &mut shaders,
+                            settings.texture_mode,
+                            fragment,
+                        ),
+                    };
+                    pipelines.add(create_chunk_pipeline_descriptor(
+                        shader_stages,
+                        settings.blend_mode,
+                    ))
+                });
+
+                // Only the pipelines are replaced, the chunk keeps its resource bindings.
+                render_pipelines.pipelines = vec![RenderPipeline::new(pipeline.clone())];
+                commands.entity(*chunk_entity).insert(material.clone());
+            }
+        }
+    }
+}
@@ -1,7 +1,14 @@
 use std::array::IntoIter;
 
-use crate::{prelude::*, tile::GPUAnimated};
+use crate::{
+    chunk::ChunkShaderFeatures,
+    morton_index, morton_pos,
+    prelude::*,
+    round_to_power_of_two,
+    tile::{GPUAnimated, TileMaterial, TileVertexData},
+};
 use bevy::{
+    math::Mat2,
     prelude::*,
     render::mesh::{Indices, VertexAttributeValues},
 };
@@ -9,126 +16,688 @@ use bevy::{
 #[derive(Debug, Default, Clone, Copy)]
 pub(crate) struct ChunkMesher;
 
+pub(crate) type MeshTileQuery<'a> = Query<
+    'a,
+    (
+        &'static TilePos,
+        &'static Tile,
+        Option<&'static GPUAnimated>,
+        Option<&'static TileVertexData>,
+        Option<&'static TileMaterial>,
+    ),
+>;
+
+/// The data of a tile needed to mesh it.
+pub(crate) struct MeshTile {
+    /// The index of the tile in `Chunk::tiles`.
+    index: usize,
+    local_pos: LocalTilePos,
+    tile: Tile,
+    gpu_animated: Option<GPUAnimated>,
+    vertex_data: Option<TileVertexData>,
+    material: Option<Handle<ColorMaterial>>,
+}
+
+/// The vertex data of a chunk mesh built by `ChunkMesher::build`.
+pub(crate) struct ChunkMeshData {
+    positions: Vec<[f32; 3]>,
+    textures: Vec<[i32; 4]>,
+    colors: Vec<[f32; 4]>,
+    emissives: Vec<f32>,
+    /// Only built when a tile has `TileVertexData`.
+    vertex_data: Option<Vec<[f32; 4]>>,
+    /// Only built when tiles were merged into larger quads.
+    sizes: Option<Vec<[f32; 2]>>,
+    /// Only built when a tile of a square layer has an angle.
+    corners: Option<Vec<[f32; 2]>>,
+    indices: Vec<u32>,
+    features: ChunkShaderFeatures,
+    /// The first vertex of each tile, indexed like `Chunk::tiles`.
+    tile_vertices: Vec<Option<u32>>,
+}
+
+/// The meshes of a chunk built by `ChunkMesher::build_with_materials`.
+pub(crate) struct ChunkMeshes {
+    /// The mesh of the tiles drawn with the chunk's material.
+    pub chunk: ChunkMeshData,
+    /// The mesh of the tiles with each `TileMaterial`.
+    pub sub_meshes: Vec<(Handle<ColorMaterial>, ChunkMeshData)>,
+}
+
+impl ChunkMeshData {
+    /// Replaces the vertex data of the mesh and returns the shader features its tiles use,
+    /// along with the first vertex of each tile.
+    pub fn apply(self, mesh: &mut Mesh) -> (ChunkShaderFeatures, Vec<Option<u32>>) {
+        let vertex_count = self.positions.len();
+        mesh.set_attribute(
+            "Vertex_Position",
+            VertexAttributeValues::Float3(self.positions),
+        );
+        mesh.set_attribute("Vertex_Texture", VertexAttributeValues::Int4(self.textures));
+        mesh.set_attribute("Vertex_Color", VertexAttributeValues::Float4(self.colors));
+        mesh.set_attribute(
+            "Vertex_Emissive",
+            VertexAttributeValues::Float(self.emissives),
+        );
+        if let Some(vertex_data) = self.vertex_data {
+            mesh.set_attribute("Vertex_Data", VertexAttributeValues::Float4(vertex_data));
+        } else if mesh.attribute("Vertex_Data").is_some() {
+            // Attributes can't be removed, so clear the data of the tiles which had some.
+            mesh.set_attribute(
+                "Vertex_Data",
+                VertexAttributeValues::Float4(vec![[0.0; 4]; vertex_count]),
+            );
+        }
+        if let Some(sizes) = self.sizes {
+            mesh.set_attribute("Vertex_Size", VertexAttributeValues::Float2(sizes));
+        } else if mesh.attribute("Vertex_Size").is_some() {
+            mesh.set_attribute(
+                "Vertex_Size",
+                VertexAttributeValues::Float2(vec![[1.0; 2]; vertex_count]),
+            );
+        }
+        if let Some(corners) = self.corners {
+            mesh.set_attribute("Vertex_Corner", VertexAttributeValues::Float2(corners));
+        } else if mesh.attribute("Vertex_Corner").is_some() {
+            mesh.set_attribute(
+                "Vertex_Corner",
+                VertexAttributeValues::Float2(vec![[0.0; 2]; vertex_count]),
+            );
+        }
+        mesh.set_indices(Some(Indices::U32(self.indices)));
+        (self.features, self.tile_vertices)
+    }
+}
+
+/// A vertex of a chunk mesh built by `mesh_chunk`, holding the values of the chunk mesh
+/// attributes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChunkVertex {
+    /// `Vertex_Position`, the tile position in the chunk and the animation speed.
+    pub position: [f32; 3],
+    /// `Vertex_Texture`, the texture index, the flip, visibility and palette flags and the
+    /// animation frames.
+    pub texture: [i32; 4],
+    /// `Vertex_Color`.
+    pub color: [f32; 4],
+    /// `Vertex_Emissive`.
+    pub emissive: f32,
+    /// `Vertex_Size`, the size in tiles of the quad the vertex belongs to. Only part of the mesh
+    /// when tiles were merged by `LayerSettings::greedy_meshing`.
+    pub size: [f32; 2],
+    /// `Vertex_Corner`, the corner of the quad relative to the tile position in tiles, rotated
+    /// by `Tile::angle`. Only part of the mesh when a tile of a square layer has an angle.
+    pub corner: [f32; 2],
+}
+
+/// Builds the vertices and indices of a chunk mesh the same way the plugin does, without a world
+/// or a GPU, for testing mesh output or baking meshes in tools.
+///
+/// Each tile gets four vertices and two triangles, or the lines or points of the layer's
+/// `topology`. The vertices are moved into place by the vertex shader of the layer's mesh type.
+/// Tiles outside of the layer's chunk size are skipped, the last tile given for a position is
+/// used and empty positions get the layer's `default_tile`. With `LayerSettings::greedy_meshing` runs of identical tiles share a quad:
+/// ```
+/// use bevy_ecs_tilemap::prelude::*;
+///
+/// let mut settings = LayerSettings::new(
+///     MapSize(1, 1),
+///     ChunkSize(32, 32),
+///     TileSize(16.0, 16.0),
+///     TextureSize(96.0, 16.0),
+/// );
+/// settings.greedy_meshing = true;
+/// let tiles: Vec<_> = (0..32 * 32)
+///     .map(|i| (LocalTilePos(i % 32, i / 32), Tile::default()))
+///     .collect();
+///
+/// let (vertices, _) = bevy_ecs_tilemap::mesh_chunk(&tiles, &settings);
+/// assert_eq!(vertices.len() / 4, 1);
+/// ```
+/// Tiles with an angle have the corners of their quads rotated about the center of the tile:
+/// ```
+/// use bevy_ecs_tilemap::prelude::*;
+///
+/// let settings = LayerSettings::new(
+///     MapSize(1, 1),
+///     ChunkSize(8, 8),
+///     TileSize(16.0, 16.0),
+///     TextureSize(96.0, 16.0),
+/// );
+/// let tile = Tile {
+///     angle: std::f32::consts::FRAC_PI_4,
+///     ..Default::default()
+/// };
+///
+/// let (vertices, _) = bevy_ecs_tilemap::mesh_chunk(&[(LocalTilePos(0, 0), tile)], &settings);
+/// let half_diagonal = std::f32::consts::FRAC_1_SQRT_2;
+/// let expected = [
+///     [0.5, 0.5 - half_diagonal],
+///     [0.5 - half_diagonal, 0.5],
+///     [0.5, 0.5 + half_diagonal],
+///     [0.5 + half_diagonal, 0.5],
+/// ];
+/// for (vertex, expected) in vertices.iter().zip(expected.iter()) {
+///     assert!((vertex.corner[0] - expected[0]).abs() < 1e-5);
+///     assert!((vertex.corner[1] - expected[1]).abs() < 1e-5);
+/// }
+/// ```
+/// A layer drawn as lines outlines every tile:
+/// ```
+/// use bevy_ecs_tilemap::prelude::*;
+///
+/// let mut settings = LayerSettings::new(
+///     MapSize(1, 1),
+///     ChunkSize(8, 8),
+///     TileSize(16.0, 16.0),
+///     TextureSize(96.0, 16.0),
+/// );
+/// settings.topology = TilemapTopology::Lines;
+/// let tiles = [
+///     (LocalTilePos(0, 0), Tile::default()),
+///     (LocalTilePos(1, 0), Tile::default()),
+/// ];
+///
+/// let (vertices, indices) = bevy_ecs_tilemap::mesh_chunk(&tiles, &settings);
+/// assert_eq!(vertices.len(), 8);
+/// assert_eq!(
+///     indices,
+///     vec![0, 1, 1, 2, 2, 3, 3, 0, 4, 5, 5, 6, 6, 7, 7, 4]
+/// );
+/// ```
+pub fn mesh_chunk(
+    tiles: &[(LocalTilePos, Tile)],
+    settings: &LayerSettings,
+) -> (Vec<ChunkVertex>, Vec<u32>) {
+    // Tiles are meshed in the same order as in `Chunk::tiles`.
+    let tile_size = round_to_power_of_two(settings.chunk_size.0 as f32)
+        .max(round_to_power_of_two(settings.chunk_size.1 as f32));
+    let tile_count = tile_size * tile_size;
+    let mut mesh_tiles: Vec<Option<MeshTile>> = (0..tile_count).map(|_| None).collect();
+    for (local_pos, tile) in tiles.iter() {
+        if local_pos.0 >= settings.chunk_size.0 || local_pos.1 >= settings.chunk_size.1 {
+            continue;
+        }
+        let index = morton_index(*local_pos);
+        mesh_tiles[index] = Some(MeshTile {
+            index,
+            local_pos: *local_pos,
+            tile: *tile,
+            gpu_animated: None,
+            vertex_data: None,
+            material: None,
+        });
+    }
+
+    let mesh_tiles = mesh_tiles
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, mesh_tile)| mesh_tile.or_else(|| default_tile(index, settings)))
+        .collect();
+    let mesh_data = settings.mesher.build(tile_count, mesh_tiles, settings);
+    let sizes = mesh_data.sizes.as_deref();
+    let corners = mesh_data.corners.as_deref();
+    let vertices = mesh_data
+        .positions
+        .iter()
+        .zip(mesh_data.textures.iter())
+        .zip(mesh_data.colors.iter())
+        .zip(mesh_data.emissives.iter())
+        .enumerate()
+        .map(|(i, (((position, texture), color), emissive))| {
+            let size = sizes.map_or([1.0; 2], |sizes| sizes[i]);
+            ChunkVertex {
+                position: *position,
+                texture: *texture,
+                color: *color,
+                emissive: *emissive,
+                size,
+                corner: corners.map_or_else(
+                    || quad_corners(0.0, size, Vec2::ONE)[i % 4],
+                    |corners| corners[i],
+                ),
+            }
+        })
+        .collect();
+    (vertices, mesh_data.indices)
+}
+
 impl ChunkMesher {
-    pub fn mesh(
+    /// Copies the data of the chunk's tiles needed for meshing, so the mesh can be built off of
+    /// the main thread with `build`.
+    pub fn collect_tiles(&self, chunk: &Chunk, tile_query: &MeshTileQuery) -> Vec<MeshTile> {
+        chunk
+            .tiles
+            .iter()
+            .enumerate()
+            .filter_map(|(index, tile_entity)| {
+                match tile_entity.and_then(|tile_entity| tile_query.get(tile_entity).ok()) {
+                    Some((tile_pos, tile, gpu_animated, vertex_data, material)) => Some(MeshTile {
+                        index,
+                        local_pos: chunk.to_chunk_pos(*tile_pos),
+                        tile: *tile,
+                        gpu_animated: gpu_animated.copied(),
+                        vertex_data: vertex_data.copied(),
+                        material: material.map(|material| material.0.clone_weak()),
+                    }),
+                    None => default_tile(index, &chunk.settings),
+                }
+            })
+            .collect()
+    }
+
+    /// Builds the mesh of the chunk from its collected tiles without a `TileMaterial`, and a
+    /// sub-mesh for each material of the other tiles.
+    pub fn build_with_materials(
+        &self,
+        tile_count: usize,
+        tiles: Vec<MeshTile>,
+        settings: &LayerSettings,
+    ) -> ChunkMeshes {
+        let mut chunk_tiles = Vec::with_capacity(tiles.len());
+        let mut material_tiles: Vec<(Handle<ColorMaterial>, Vec<MeshTile>)> = Vec::new();
+        for tile in tiles.into_iter() {
+            match tile.material.clone() {
+                None => chunk_tiles.push(tile),
+                Some(material) => match material_tiles.iter_mut().find(|(m, _)| *m == material) {
+                    Some((_, tiles)) => tiles.push(tile),
+                    None => material_tiles.push((material, vec![tile])),
+                },
+            }
+        }
+
+        ChunkMeshes {
+            chunk: self.build(tile_count, chunk_tiles, settings),
+            sub_meshes: material_tiles
+                .into_iter()
+                .map(|(material, tiles)| (material, self.build(tile_count, tiles, settings)))
+                .collect(),
+        }
+    }
+
+    /// Builds the vertex data of a chunk from its collected tiles, merging runs of identical
+    /// tiles into larger quads when the layer merges tiles.
+    /// Hidden tiles are meshed as well, the vertex shader collapses them, so their visibility
+    /// can be toggled with `set_tile_visibility` without remeshing.
+    pub fn build(
         &self,
-        chunk: &Chunk,
-        chunk_tiles: &Vec<Option<Entity>>,
-        tile_query: &Query<(&TilePos, &Tile, Option<&GPUAnimated>)>,
-        meshes: &mut ResMut<Assets<Mesh>>,
-    ) {
-        let mesh = meshes.get_mut(chunk.mesh_handle.clone()).unwrap();
-        let size = ((chunk.settings.chunk_size.0 * chunk.settings.chunk_size.1) * 4) as usize;
+        tile_count: usize,
+        tiles: Vec<MeshTile>,
+        settings: &LayerSettings,
+    ) -> ChunkMeshData {
+        let quads = if settings.merges_tiles() {
+            merge_tiles(&tiles)
+        } else {
+            tiles.iter().map(|tile| (tile, 1, 1)).collect()
+        };
+
+        let size = quads.len() * 4;
         let mut positions: Vec<[f32; 3]> = Vec::with_capacity(size);
         let mut textures: Vec<[i32; 4]> = Vec::with_capacity(size);
         let mut colors: Vec<[f32; 4]> = Vec::with_capacity(size);
-        let mut indices: Vec<u32> = Vec::with_capacity(
-            ((chunk.settings.chunk_size.0 * chunk.settings.chunk_size.1) * 6) as usize,
-        );
+        let mut emissives: Vec<f32> = Vec::with_capacity(size);
+        let mut indices: Vec<u32> = Vec::with_capacity(quads.len() * 8);
+        let mut vertex_data: Option<Vec<[f32; 4]>> = tiles
+            .iter()
+            .any(|tile| tile.vertex_data.is_some())
+            .then(|| Vec::with_capacity(size));
+        let mut sizes: Option<Vec<[f32; 2]>> = quads
+            .iter()
+            .any(|(_, width, height)| width * height > 1)
+            .then(|| Vec::with_capacity(size));
+        // Only square layers rotate their tiles.
+        let quad_size = settings.get_quad_size();
+        let mut corners: Option<Vec<[f32; 2]>> = (settings.mesh_type == TilemapMeshType::Square
+            && tiles.iter().any(|tile| tile.tile.angle != 0.0))
+        .then(|| Vec::with_capacity(size));
 
+        let mut features = ChunkShaderFeatures {
+            merged: sizes.is_some(),
+            rotation: corners.is_some(),
+            ..Default::default()
+        };
+        let mut tile_vertices = vec![None; tile_count];
         let mut i = 0;
-        for tile_entity in chunk_tiles.iter() {
-            if let Some(tile_entity) = tile_entity {
-                if let Ok((tile_position, tile, gpu_animated)) = tile_query.get(*tile_entity) {
-                    if !tile.visible {
-                        continue;
-                    }
-
-                    let tile_pos = Vec2::new(
-                        (tile_position.0 - (chunk.position.0 * chunk.settings.chunk_size.0)) as f32,
-                        (tile_position.1 - (chunk.position.1 * chunk.settings.chunk_size.1)) as f32,
-                    );
-                    let (animation_start, animation_end, animation_speed) =
-                        if let Some(ani) = gpu_animated {
-                            (ani.start as i32, ani.end as i32, ani.speed)
-                        } else {
-                            (tile.texture_index as i32, tile.texture_index as i32, 0.0)
-                        };
-
-                    positions.extend(IntoIter::new([
-                        // X, Y
-                        [tile_pos.x, tile_pos.y, animation_speed],
-                        // X, Y + 1
-                        //[tile_pos.x, tile_pos.y + 1.0, animation_speed],
-                        [tile_pos.x, tile_pos.y, animation_speed],
-                        // X + 1, Y + 1
-                        //[tile_pos.x + 1.0, tile_pos.y + 1.0, animation_speed],
-                        [tile_pos.x, tile_pos.y, animation_speed],
-                        // X + 1, Y
-                        //[tile_pos.x + 1.0, tile_pos.y, animation_speed],
-                        [tile_pos.x, tile_pos.y, animation_speed],
-                    ]));
-
-                    colors.extend(IntoIter::new([
-                        [
-                            tile.color.r(),
-                            tile.color.g(),
-                            tile.color.b(),
-                            tile.color.a(),
-                        ],
-                        [
-                            tile.color.r(),
-                            tile.color.g(),
-                            tile.color.b(),
-                            tile.color.a(),
-                        ],
-                        [
-                            tile.color.r(),
-                            tile.color.g(),
-                            tile.color.b(),
-                            tile.color.a(),
-                        ],
-                        [
-                            tile.color.r(),
-                            tile.color.g(),
-                            tile.color.b(),
-                            tile.color.a(),
-                        ],
-                    ]));
-
-                    // flipping and rotation packed in bits
-                    // bit 0 : flip_x
-                    // bit 1 : flip_y
-                    // bit 2 : flip_d (anti diagonal)
-
-                    let tile_flip_bits =
-                        tile.flip_x as i32 | (tile.flip_y as i32) << 1 | (tile.flip_d as i32) << 2;
-
-                    textures.extend(IntoIter::new([
-                        [
-                            tile.texture_index as i32,
-                            tile_flip_bits,
-                            animation_start,
-                            animation_end,
-                        ],
-                        [
-                            tile.texture_index as i32,
-                            tile_flip_bits,
-                            animation_start,
-                            animation_end,
-                        ],
-                        [
-                            tile.texture_index as i32,
-                            tile_flip_bits,
-                            animation_start,
-                            animation_end,
-                        ],
-                        [
-                            tile.texture_index as i32,
-                            tile_flip_bits,
-                            animation_start,
-                            animation_end,
-                        ],
-                    ]));
-
-                    indices.extend_from_slice(&[i + 0, i + 2, i + 1, i + 0, i + 3, i + 2]);
-                    i += 4;
+        for (
+            MeshTile {
+                index,
+                local_pos,
+                tile,
+                gpu_animated,
+                vertex_data: tile_vertex_data,
+                ..
+            },
+            width,
+            height,
+        ) in quads.into_iter()
+        {
+            // Merged tiles can't be updated in place.
+            if width * height == 1 {
+                tile_vertices[*index] = Some(i);
+            }
+
+            let tile_pos = Vec2::new(local_pos.0 as f32, local_pos.1 as f32);
+            features.animation |= gpu_animated.is_some();
+            features.tint |= tile.color != Color::WHITE;
+            features.emissive |= tile.emissive != 0.0;
+
+            let (animation_start, animation_end, animation_speed) =
+                animation(tile, gpu_animated.as_ref());
+
+            positions.extend(IntoIter::new([
+                // X, Y
+                [tile_pos.x, tile_pos.y, animation_speed],
+                // X, Y + 1
+                //[tile_pos.x, tile_pos.y + 1.0, animation_speed],
+                [tile_pos.x, tile_pos.y, animation_speed],
+                // X + 1, Y + 1
+                //[tile_pos.x + 1.0, tile_pos.y + 1.0, animation_speed],
+                [tile_pos.x, tile_pos.y, animation_speed],
+                // X + 1, Y
+                //[tile_pos.x + 1.0, tile_pos.y, animation_speed],
+                [tile_pos.x, tile_pos.y, animation_speed],
+            ]));
+
+            let color = [
+                tile.color.r(),
+                tile.color.g(),
+                tile.color.b(),
+                tile.color.a(),
+            ];
+            colors.extend(IntoIter::new([color, color, color, color]));
+            emissives.extend(IntoIter::new([tile.emissive; 4]));
+            if let Some(vertex_data) = vertex_data.as_mut() {
+                let data: [f32; 4] = tile_vertex_data.unwrap_or_default().0.into();
+                vertex_data.extend(IntoIter::new([data; 4]));
+            }
+            if let Some(sizes) = sizes.as_mut() {
+                sizes.extend(IntoIter::new([[width as f32, height as f32]; 4]));
+            }
+            if let Some(corners) = corners.as_mut() {
+                let size = [width as f32, height as f32];
+                corners.extend(IntoIter::new(quad_corners(tile.angle, size, quad_size)));
+            }
+
+            let tile_flip_bits = tile_flags(tile);
+            let texture = [
+                tile.texture_index as i32,
+                tile_flip_bits,
+                animation_start,
+                animation_end,
+            ];
+            textures.extend(IntoIter::new([texture, texture, texture, texture]));
+
+            push_quad_indices(&mut indices, settings.topology, i);
+            i += 4;
+        }
+
+        ChunkMeshData {
+            positions,
+            textures,
+            colors,
+            emissives,
+            vertex_data,
+            sizes,
+            corners,
+            indices,
+            features,
+            tile_vertices,
+        }
+    }
+
+    /// Updates the visibility of an already meshed tile starting at `vertex` in place.
+    /// Returns false if anything else about the tile changed, in which case the chunk needs to
+    /// be remeshed instead.
+    pub fn set_tile_visibility(
+        &self,
+        mesh: &mut Mesh,
+        vertex: u32,
+        tile: &Tile,
+        gpu_animated: Option<&GPUAnimated>,
+        quad_size: Vec2,
+    ) -> bool {
+        let vertex = vertex as usize;
+        let (animation_start, animation_end, animation_speed) = animation(tile, gpu_animated);
+        match mesh.attribute("Vertex_Position") {
+            Some(VertexAttributeValues::Float3(positions))
+                if positions.get(vertex).map(|position| position[2]) == Some(animation_speed) => {}
+            _ => return false,
+        }
+        let color = [
+            tile.color.r(),
+            tile.color.g(),
+            tile.color.b(),
+            tile.color.a(),
+        ];
+        match mesh.attribute("Vertex_Color") {
+            Some(VertexAttributeValues::Float4(colors)) if colors.get(vertex) == Some(&color) => {}
+            _ => return false,
+        }
+        match mesh.attribute("Vertex_Emissive") {
+            Some(VertexAttributeValues::Float(emissives))
+                if emissives.get(vertex) == Some(&tile.emissive) => {}
+            _ => return false,
+        }
+        let corners = quad_corners(tile.angle, [1.0; 2], quad_size);
+        match mesh.attribute("Vertex_Corner") {
+            Some(VertexAttributeValues::Float2(mesh_corners))
+                if mesh_corners.get(vertex..vertex + 4) == Some(&corners[..]) => {}
+            None if tile.angle == 0.0 => {}
+            _ => return false,
+        }
+
+        let flags = tile_flags(tile);
+        if let Some(VertexAttributeValues::Int4(textures)) = mesh.attribute_mut("Vertex_Texture") {
+            if let Some(tile_textures) = textures.get_mut(vertex..vertex + 4) {
+                // Only the hidden bit may differ.
+                if tile_textures[0]
+                    != [
+                        tile.texture_index as i32,
+                        tile_textures[0][1] & 8 | flags & !8,
+                        animation_start,
+                        animation_end,
+                    ]
+                {
+                    return false;
+                }
+                for texture in tile_textures.iter_mut() {
+                    texture[1] = flags;
                 }
+                return true;
             }
         }
-        mesh.set_attribute("Vertex_Position", VertexAttributeValues::Float3(positions));
-        mesh.set_attribute("Vertex_Texture", VertexAttributeValues::Int4(textures));
-        mesh.set_attribute("Vertex_Color", VertexAttributeValues::Float4(colors));
-        mesh.set_indices(Some(Indices::U32(indices)));
+
+        false
+    }
+}
+
+// Gets the layer's default tile for an empty position in a chunk.
+fn default_tile(index: usize, settings: &LayerSettings) -> Option<MeshTile> {
+    let tile = settings.default_tile?;
+    let local_pos = morton_pos(index);
+    if local_pos.x >= settings.chunk_size.0 || local_pos.y >= settings.chunk_size.1 {
+        return None;
+    }
+    Some(MeshTile {
+        index,
+        local_pos: LocalTilePos(local_pos.x, local_pos.y),
+        tile,
+        gpu_animated: None,
+        vertex_data: None,
+        material: None,
+    })
+}
+
+// Greedily merges runs of identical tiles into larger quads, growing each quad along its row
+// first and then along its column. Returns the first tile and the size in tiles of each quad.
+// Animated and hidden tiles and tiles with vertex data keep a quad of their own.
+fn merge_tiles(tiles: &[MeshTile]) -> Vec<(&MeshTile, u32, u32)> {
+    let width = tiles
+        .iter()
+        .map(|tile| tile.local_pos.0 + 1)
+        .max()
+        .unwrap_or(0) as usize;
+    let height = tiles
+        .iter()
+        .map(|tile| tile.local_pos.1 + 1)
+        .max()
+        .unwrap_or(0) as usize;
+    let mut grid: Vec<Option<&MeshTile>> = vec![None; width * height];
+    for tile in tiles.iter() {
+        grid[tile.local_pos.1 as usize * width + tile.local_pos.0 as usize] = Some(tile);
+    }
+
+    let can_merge = |tile: &MeshTile| {
+        tile.gpu_animated.is_none()
+            && tile.vertex_data.is_none()
+            && tile.tile.visible
+            && tile.tile.angle == 0.0
+    };
+
+    let mut quads = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            let first = match grid[y * width + x] {
+                Some(tile) => tile,
+                None => continue,
+            };
+            if !can_merge(first) {
+                grid[y * width + x] = None;
+                quads.push((first, 1, 1));
+                continue;
+            }
+
+            let matches = |tile: Option<&MeshTile>| {
+                tile.map_or(false, |tile| {
+                    can_merge(tile)
+                        && tile.tile.texture_index == first.tile.texture_index
+                        && tile_flags(&tile.tile) == tile_flags(&first.tile)
+                        && tile.tile.color == first.tile.color
+                        && tile.tile.emissive.to_bits() == first.tile.emissive.to_bits()
+                })
+            };
+            let mut quad_width = 1;
+            while x + quad_width < width && matches(grid[y * width + x + quad_width]) {
+                quad_width += 1;
+            }
+            let mut quad_height = 1;
+            while y + quad_height < height
+                && grid[(y + quad_height) * width + x..][..quad_width]
+                    .iter()
+                    .all(|tile| matches(*tile))
+            {
+                quad_height += 1;
+            }
+
+            for row in y..y + quad_height {
+                for cell in grid[row * width + x..][..quad_width].iter_mut() {
+                    *cell = None;
+                }
+            }
+            quads.push((first, quad_width as u32, quad_height as u32));
+        }
+    }
+    quads
+}
+
+// Gets the corners of a quad of `size` tiles relative to its tile position, rotated counter
+// clockwise by `angle` about the center of the quad. The rotation happens in world units, so
+// tiles which aren't square keep their shape.
+fn quad_corners(angle: f32, size: [f32; 2], quad_size: Vec2) -> [[f32; 2]; 4] {
+    let [width, height] = size;
+    let corners = [[0.0, 0.0], [0.0, height], [width, height], [width, 0.0]];
+    if angle == 0.0 {
+        return corners;
+    }
+    let center = Vec2::new(width, height) * 0.5;
+    let rotation = Mat2::from_angle(angle);
+    corners.map(|corner| {
+        let offset = (Vec2::from(corner) - center) * quad_size;
+        (rotation * offset / quad_size + center).into()
+    })
+}
+
+// Adds the indices of the quad starting at vertex `i`.
+fn push_quad_indices(indices: &mut Vec<u32>, topology: TilemapTopology, i: u32) {
+    match topology {
+        TilemapTopology::Triangles => {
+            indices.extend_from_slice(&[i, i + 2, i + 1, i, i + 3, i + 2]);
+        }
+        TilemapTopology::Lines => {
+            indices.extend_from_slice(&[i, i + 1, i + 1, i + 2, i + 2, i + 3, i + 3, i]);
+        }
+        TilemapTopology::Points => indices.extend_from_slice(&[i, i + 1, i + 2, i + 3]),
+    }
+}
+
+// Gets the animation start, end and speed of a tile.
+fn animation(tile: &Tile, gpu_animated: Option<&GPUAnimated>) -> (i32, i32, f32) {
+    if let Some(ani) = gpu_animated {
+        (ani.start as i32, ani.end as i32, ani.speed)
+    } else {
+        (tile.texture_index as i32, tile.texture_index as i32, 0.0)
+    }
+}
+
+// flipping, rotation and visibility packed in bits
+// bit 0 : flip_x
+// bit 1 : flip_y
+// bit 2 : flip_d (anti diagonal)
+// bit 3 : hidden
+// bits 4 - 11 : palette
+fn tile_flags(tile: &Tile) -> i32 {
+    tile.flip_x as i32
+        | (tile.flip_y as i32) << 1
+        | (tile.flip_d as i32) << 2
+        | (!tile.visible as i32) << 3
+        | (tile.palette as i32) << 4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emissive_tiles_enable_the_emissive_feature() {
+        let settings = LayerSettings::new(
+            MapSize(1, 1),
+            ChunkSize(2, 1),
+            TileSize(16.0, 16.0),
+            TextureSize(96.0, 16.0),
+        );
+        let glowing = Tile {
+            emissive: 0.75,
+            ..Default::default()
+        };
+        let tiles = [
+            (LocalTilePos(0, 0), Tile::default()),
+            (LocalTilePos(1, 0), glowing),
+        ];
+
+        let (vertices, _) = mesh_chunk(&tiles, &settings);
+        // Each tile's four vertices carry its emissive value.
+        for (index, vertex) in vertices.iter().enumerate() {
+            let expected = if index < 4 { 0.0 } else { 0.75 };
+            assert!((vertex.emissive - expected).abs() < f32::EPSILON);
+        }
+        assert_eq!(vertices.len(), 8);
+
+        let mesh_tiles = tiles
+            .iter()
+            .map(|(local_pos, tile)| MeshTile {
+                index: morton_index(*local_pos),
+                local_pos: *local_pos,
+                tile: *tile,
+                gpu_animated: None,
+                vertex_data: None,
+                material: None,
+            })
+            .collect();
+        let mesh_data = settings.mesher.build(4, mesh_tiles, &settings);
+        assert!(mesh_data.features.emissive);
+        assert!(mesh_data
+            .features
+            .shader_defs()
+            .contains(&"TILEMAP_EMISSIVE".to_string()));
     }
 }
@@ -3,19 +3,25 @@ use crate::layer_builder::LayerBuilder;
 use crate::map::MapId;
 use crate::map_query::MapQuery;
 use crate::tile::TileBundleTrait;
-use crate::TilePos;
+use crate::{HexType, IsoType, MapSize, TilePos, TilemapMeshType};
 
-use bevy::prelude::Entity;
+use bevy::prelude::{Entity, IVec2};
 
 impl<T: TileBundleTrait> LayerBuilder<T> {
     /// Retrieves a list of neighbors in the following order:
     /// N, S, W, E, NW, NE, SW, SE.
+    /// For staggered isometric layers these are screen directions, see `get_neighboring_pos_staggered`.
     ///
     /// None will be returned if no valid entity is found at the appropriate coordinate,
     /// including if the tile is at the edge of the map.
     /// ```
     pub fn get_tile_neighbors(&self, tile_pos: TilePos) -> Vec<Option<(Option<Entity>, &T)>> {
-        let neighboring_tile_pos = get_neighboring_pos(tile_pos);
+        let neighboring_tile_pos = match self.settings.mesh_type {
+            TilemapMeshType::Isometric(IsoType::Staggered) => {
+                get_neighboring_pos_staggered(tile_pos)
+            }
+            _ => get_neighboring_pos(tile_pos),
+        };
 
         neighboring_tile_pos
             .iter()
@@ -28,19 +34,22 @@ impl<T: TileBundleTrait> LayerBuilder<T> {
 }
 
 impl<'a> MapQuery<'a> {
-    /// Retrieves a list of neighbor entities in the following order:
-    /// N, S, W, E, NW, NE, SW, SE.
-    ///
+    /// Retrieves a list of neighbor entities.
+    /// For square and isometric layers the order is: N, S, W, E, NW, NE, SW, SE.
+    /// For staggered isometric layers these are screen directions, see `get_neighboring_pos_staggered`.
+    /// For hex layers the six hex neighbors are returned in the order of `get_neighboring_pos_hex`.
     ///
-    /// If a tile's coordinates are out of bounds, None will be returned in the Option<TilePos>
-    /// If a tile's coordinates are valid but no tile entity is found, None will be returned in the Option<Entity>
+    /// Neighbors in other chunks of the layer are returned as well, and in layers with
+    /// `LayerSettings::wrap` the neighbors past an edge are the tiles on the other side.
+    /// If a neighbor's coordinates are outside of the layer `MapTileError::OutOfBounds` is returned.
+    /// If a neighbor's coordinates are valid but no tile entity is found `MapTileError::NonExistent` is returned.
     ///
     /// ## Example
     ///
-    /// ```
-    /// let neighbors = map.get_tile_neighbors(TilePos(0, 0));
-    /// assert!(neighbors[1].1.is_none()); // Outside of tile bounds.
-    /// assert!(neighbors[0].1.is_none()); // Entity returned inside bounds.
+    /// ```ignore
+    /// let neighbors = map_query.get_tile_neighbors(TilePos(0, 0), 0u16, 0u16);
+    /// assert!(neighbors[1].is_err()); // Outside of tile bounds.
+    /// assert!(neighbors[0].is_ok()); // Entity returned inside bounds.
     /// ```
     pub fn get_tile_neighbors(
         &self,
@@ -48,8 +57,59 @@ impl<'a> MapQuery<'a> {
         map_id: impl MapId,
         layer_id: impl LayerId,
     ) -> Vec<Result<Entity, MapTileError>> {
-        let neighboring_tile_pos = get_neighboring_pos(tile_pos);
+        let map_id = map_id.into();
+        let layer_id = layer_id.into();
+        self.get_neighbor_entities(
+            &self.get_neighbor_positions(tile_pos, map_id, layer_id, true),
+            map_id,
+            layer_id,
+        )
+    }
+
+    /// Retrieves a list of the neighbor entities which share an edge with the tile.
+    /// For square and diamond isometric layers the order is: N, S, W, E.
+    /// For staggered isometric layers the diagonal screen directions share an edge,
+    /// so the order is: NW, NE, SW, SE.
+    /// For hex layers every neighbor shares an edge, so this is the same as `get_tile_neighbors`.
+    pub fn get_tile_orthogonal_neighbors(
+        &self,
+        tile_pos: TilePos,
+        map_id: impl MapId,
+        layer_id: impl LayerId,
+    ) -> Vec<Result<Entity, MapTileError>> {
+        let map_id = map_id.into();
+        let layer_id = layer_id.into();
+        self.get_neighbor_entities(
+            &self.get_neighbor_positions(tile_pos, map_id, layer_id, false),
+            map_id,
+            layer_id,
+        )
+    }
+
+    pub(crate) fn get_neighbor_positions(
+        &self,
+        tile_pos: TilePos,
+        map_id: u16,
+        layer_id: u16,
+        diagonals: bool,
+    ) -> Vec<Option<TilePos>> {
+        match self.get_layer(map_id, layer_id) {
+            Some((_, layer)) => neighbor_positions(
+                tile_pos,
+                layer.settings.mesh_type,
+                diagonals,
+                layer.settings.wrap.then(|| layer.get_layer_size_in_tiles()),
+            ),
+            None => neighbor_positions(tile_pos, TilemapMeshType::Square, diagonals, None),
+        }
+    }
 
+    fn get_neighbor_entities(
+        &self,
+        neighboring_tile_pos: &[Option<TilePos>],
+        map_id: u16,
+        layer_id: u16,
+    ) -> Vec<Result<Entity, MapTileError>> {
         neighboring_tile_pos
             .iter()
             .map(|maybe_pos| match maybe_pos {
@@ -60,6 +120,39 @@ impl<'a> MapQuery<'a> {
     }
 }
 
+/// Gets the positions of the neighbors of a tile for a mesh type, only the ones sharing an edge
+/// with the tile unless `diagonals` is set.
+/// With a `wrap_size` the neighbors past the edges of a layer of that size in tiles wrap around
+/// to the other side.
+pub(crate) fn neighbor_positions(
+    tile_pos: TilePos,
+    mesh_type: TilemapMeshType,
+    diagonals: bool,
+    wrap_size: Option<MapSize>,
+) -> Vec<Option<TilePos>> {
+    if let Some(size) = wrap_size {
+        // Shifting the tile a whole layer away keeps its neighbors positive, the parity of rows
+        // and columns is kept as long as the layer size is even.
+        let shifted = TilePos(tile_pos.0 + size.0, tile_pos.1 + size.1);
+        return neighbor_positions(shifted, mesh_type, diagonals, None)
+            .into_iter()
+            .map(|pos| pos.map(|pos| TilePos(pos.0 % size.0, pos.1 % size.1)))
+            .collect();
+    }
+
+    match mesh_type {
+        TilemapMeshType::Hexagon(hex_type) => get_neighboring_pos_hex(tile_pos, hex_type).to_vec(),
+        TilemapMeshType::Isometric(IsoType::Staggered) if diagonals => {
+            get_neighboring_pos_staggered(tile_pos).to_vec()
+        }
+        TilemapMeshType::Isometric(IsoType::Staggered) => {
+            get_neighboring_pos_staggered(tile_pos)[4..].to_vec()
+        }
+        _ if diagonals => get_neighboring_pos(tile_pos).to_vec(),
+        _ => get_neighboring_pos(tile_pos)[..4].to_vec(),
+    }
+}
+
 /// Gets the positions of the neighbors of the specified position
 /// Order: N, S, W, E, NW, NE, SW, SE.
 ///
@@ -117,3 +210,113 @@ pub fn get_neighboring_pos(tile_pos: TilePos) -> [Option<TilePos>; 8] {
         north, south, west, east, northwest, northeast, southwest, southeast,
     ]
 }
+
+/// Gets the positions of the neighbors of the specified position in a staggered isometric layer.
+/// Order: N, S, W, E, NW, NE, SW, SE, as seen on screen.
+///
+/// Rows are half a tile apart, so the tiles sharing an edge are the diagonal ones and N and S
+/// are two rows away. Even rows are shifted right by half a tile relative to odd rows,
+/// which decides the column of the diagonal neighbors.
+///
+/// Tile positions are bounded between 0 and u32::MAX, so None may be returned
+pub fn get_neighboring_pos_staggered(tile_pos: TilePos) -> [Option<TilePos>; 8] {
+    let x = tile_pos.0 as i64;
+    let y = tile_pos.1 as i64;
+    // Column of the diagonal neighbors to the left, the ones to the right are one further.
+    let left = if y % 2 == 0 { x } else { x - 1 };
+
+    let offsets = [
+        (x, y + 2),
+        (x, y - 2),
+        (x - 1, y),
+        (x + 1, y),
+        (left, y + 1),
+        (left + 1, y + 1),
+        (left, y - 1),
+        (left + 1, y - 1),
+    ];
+
+    let mut neighbors = [None; 8];
+    for (neighbor, (x, y)) in neighbors.iter_mut().zip(offsets.iter()) {
+        if (0..=u32::MAX as i64).contains(x) && (0..=u32::MAX as i64).contains(y) {
+            *neighbor = Some(TilePos(*x as u32, *y as u32));
+        }
+    }
+    neighbors
+}
+
+/// Converts a tile position into axial hex coordinates for the given hex type.
+///
+/// For `HexType::Row` and `HexType::Column` tile positions already are axial coordinates.
+/// For the offset types the conversion follows how the tiles are laid out when rendered:
+/// - `RowEven`: odd rows are shifted right by half a tile.
+/// - `RowOdd`: even rows are shifted right by half a tile.
+/// - `ColumnEven`: odd columns are shifted up by half a tile.
+/// - `ColumnOdd`: even columns are shifted up by half a tile.
+///
+/// You can find out more about axial coordinates here: https://www.redblobgames.com/grids/hexagons/#coordinates-axial
+pub fn tile_pos_to_axial(tile_pos: TilePos, hex_type: HexType) -> IVec2 {
+    let x = tile_pos.0 as i32;
+    let y = tile_pos.1 as i32;
+    match hex_type {
+        HexType::Row | HexType::Column => IVec2::new(x, y),
+        HexType::RowEven => IVec2::new(x - (y - (y & 1)) / 2, y),
+        HexType::RowOdd => IVec2::new(x - (y + (y & 1)) / 2, y),
+        HexType::ColumnEven => IVec2::new(x, y - (x - (x & 1)) / 2),
+        HexType::ColumnOdd => IVec2::new(x, y - (x + (x & 1)) / 2),
+    }
+}
+
+/// Converts axial hex coordinates back into a tile position for the given hex type.
+///
+/// Returns None if the axial coordinates lie outside of the positive tile coordinate space.
+pub fn axial_to_tile_pos(axial: IVec2, hex_type: HexType) -> Option<TilePos> {
+    let q = axial.x;
+    let r = axial.y;
+    let (x, y) = match hex_type {
+        HexType::Row | HexType::Column => (q, r),
+        HexType::RowEven => (q + (r - (r & 1)) / 2, r),
+        HexType::RowOdd => (q + (r + (r & 1)) / 2, r),
+        HexType::ColumnEven => (q, r + (q - (q & 1)) / 2),
+        HexType::ColumnOdd => (q, r + (q + (q & 1)) / 2),
+    };
+
+    if x < 0 || y < 0 {
+        return None;
+    }
+
+    Some(TilePos(x as u32, y as u32))
+}
+
+/// Gets the positions of the hex neighbors of the specified position.
+/// Order for row hex types (pointy top): E, W, NE, NW, SE, SW.
+/// Order for column hex types (flat top): N, S, NE, NW, SE, SW.
+///
+/// None is returned for neighbors with negative coordinates.
+pub fn get_neighboring_pos_hex(tile_pos: TilePos, hex_type: HexType) -> [Option<TilePos>; 6] {
+    let directions = match hex_type {
+        HexType::Row | HexType::RowEven | HexType::RowOdd => [
+            IVec2::new(1, 0),
+            IVec2::new(-1, 0),
+            IVec2::new(0, 1),
+            IVec2::new(-1, 1),
+            IVec2::new(1, -1),
+            IVec2::new(0, -1),
+        ],
+        HexType::Column | HexType::ColumnEven | HexType::ColumnOdd => [
+            IVec2::new(0, 1),
+            IVec2::new(0, -1),
+            IVec2::new(1, 0),
+            IVec2::new(-1, 1),
+            IVec2::new(1, -1),
+            IVec2::new(-1, 0),
+        ],
+    };
+
+    let axial = tile_pos_to_axial(tile_pos, hex_type);
+    let mut neighbors = [None; 6];
+    for (neighbor, direction) in neighbors.iter_mut().zip(directions.iter()) {
+        *neighbor = axial_to_tile_pos(axial + *direction, hex_type);
+    }
+    neighbors
+}
@@ -0,0 +1,44 @@
+use crate::TilemapMaterial;
+use bevy::{
+    prelude::*,
+    reflect::TypeUuid,
+    render::{renderer::RenderResources, shader::ShaderStage},
+};
+
+/// A `TilemapMaterial` which colors tiles through a palette, for retro palette swap effects.
+///
+/// The red channel of the layer's texture holds the index of a color, which is looked up in a
+/// row of `palettes`. The row is `Tile::palette` added to `active_palette`, so changing
+/// `active_palette` swaps the palette of every tile on the layer without remeshing.
+/// Note: The layer's texture needs a linear format like `TextureFormat::Rgba8Unorm`, with the
+/// sRGB format of loaded images the indices would be converted to linear colors. Only
+/// `TilemapTextureMode::Atlas` is supported.
+///
+/// Add a `TilemapMaterialPlugin::<TilemapPalette>` and insert a `Handle<TilemapPalette>` on a
+/// layer entity to use it.
+#[derive(Debug, Clone, RenderResources, TypeUuid)]
+#[uuid = "3c8124cc-70fc-400a-b773-2c93b2640494"]
+pub struct TilemapPalette {
+    /// A texture with a palette in each row and a color in each column.
+    pub palettes: Handle<Texture>,
+    /// The palette used by tiles with a `Tile::palette` of 0, wraps around to the first row.
+    pub active_palette: u32,
+}
+
+impl TilemapPalette {
+    pub fn new(palettes: Handle<Texture>) -> Self {
+        Self {
+            palettes,
+            active_palette: 0,
+        }
+    }
+}
+
+impl TilemapMaterial for TilemapPalette {
+    fn fragment_shader(_: &AssetServer, shaders: &mut Assets<Shader>) -> Handle<Shader> {
+        shaders.add(Shader::from_glsl(
+            ShaderStage::Fragment,
+            include_str!("render/tilemap-palette.frag"),
+        ))
+    }
+}
@@ -0,0 +1,55 @@
+use crate::{Layer, TilemapStage};
+use bevy::{ecs::component::Component, prelude::*, render::render_graph::base::MainPass};
+use std::marker::PhantomData;
+
+/// Draws the chunks of layers in a custom render graph pass instead of the main pass.
+///
+/// A `PassNode::<&P>` only draws entities with a `P` component. Add the plugin for the marker
+/// component of the pass and insert the marker on a layer entity, the layer's chunks then get
+/// the marker in place of `MainPass`, so only the custom pass draws them. Removing the marker
+/// from the layer moves the chunks back to the main pass.
+/// The chunks keep their pipelines, so the pass needs the same kind of color and depth
+/// attachments as the main pass or a `TilemapTargetFormat` for other color formats. See the
+/// `custom_pass` example for a pass which draws a layer over everything in the main pass.
+/// Must be added after the `TilemapPlugin`.
+pub struct TilemapPassPlugin<P: Component + Clone>(PhantomData<P>);
+
+impl<P: Component + Clone> Default for TilemapPassPlugin<P> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<P: Component + Clone> Plugin for TilemapPassPlugin<P> {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_to_stage(TilemapStage, update_chunk_pass::<P>.system());
+    }
+}
+
+// Swaps the `MainPass` of the chunks in layers with a pass marker for the marker.
+fn update_chunk_pass<P: Component + Clone>(
+    mut commands: Commands,
+    changed_layers: Query<(&Layer, &P), Changed<P>>,
+    removed_passes: RemovedComponents<P>,
+    layer_query: Query<&Layer>,
+) {
+    for (layer, pass) in changed_layers.iter() {
+        for chunk_entity in layer.chunks.iter().flatten() {
+            commands
+                .entity(*chunk_entity)
+                .remove::<MainPass>()
+                .insert(pass.clone());
+        }
+    }
+
+    for layer_entity in removed_passes.iter() {
+        if let Ok(layer) = layer_query.get(layer_entity) {
+            for chunk_entity in layer.chunks.iter().flatten() {
+                commands
+                    .entity(*chunk_entity)
+                    .remove::<P>()
+                    .insert(MainPass);
+            }
+        }
+    }
+}
@@ -3,9 +3,16 @@
 layout(location = 0) in vec3 Vertex_Position;
 layout(location = 1) in ivec4 Vertex_Texture;
 layout(location = 2) in vec4 Vertex_Color;
+#ifdef TILEMAP_EMISSIVE
+layout(location = 3) in float Vertex_Emissive;
+#endif
 
 layout(location = 0) out vec2 v_Uv;
 layout(location = 1) out vec4 v_color;
+layout(location = 2) out float v_TextureLayer;
+layout(location = 3) out float v_Emissive;
+layout(location = 4) out float v_Palette;
+layout(location = 6) out vec2 v_WorldPos;
 
 layout(set = 0, binding = 0) uniform CameraViewProj {
     mat4 ViewProj;
@@ -16,13 +23,29 @@ layout(set = 2, binding = 0) uniform Transform {
 };
 
 layout(set = 2, binding = 1) uniform TilemapData {
+    vec4 fog_color;
+    vec4 tint;
     vec2 texture_size;
     vec2 tile_size;
     vec2 grid_size;
     vec2 spacing;
+    vec2 margin;
     vec2 chunk_pos;
     vec2 map_size;
+    vec2 viewport_size;
+    vec2 scroll;
+    vec2 quad_size;
+    vec2 tile_offset;
+    vec2 fog_center;
     float time;
+    float texture_padding;
+    float alpha;
+    float y_sort;
+    float flip_v;
+    float pixel_snap;
+    float fog_radius;
+    float fog_falloff;
+    float alpha_cutoff;
 };
 
 void main() {
@@ -47,61 +70,138 @@ void main() {
     }
     position.x -= Vertex_Position.x * ceil(0.25 * tile_size.x);
 
-    vec4 world_pos = Model * vec4(position.xy, 0.0, 1.0);
+    // Tiles further down the map are drawn in front when y sorting is enabled.
+    // The depths are scaled to stay below 1.0, which is the depth of the layer above.
+    float depth = y_sort * (1.0 - (chunk_pos.y + Vertex_Position.y) * grid_size.y / map_size.y) * (1.0 - 1.0 / 1024.0);
 
+    vec4 world_pos = Model * vec4(position.xy, depth, 1.0);
+
+#ifdef TILEMAP_ANIMATION
     float frames = float(Vertex_Texture.w - Vertex_Texture.z);
 
-    float current_animation_frame = fract(time * Vertex_Position.z) * frames;
+    float current_animation_frame = float(Vertex_Texture.z) + fract(time * Vertex_Position.z) * frames;
 
     current_animation_frame = clamp(current_animation_frame, float(Vertex_Texture.z), float(Vertex_Texture.w));
 
     int texture_index = int(current_animation_frame);
-    
-    int columns = int(floor(texture_size.x / tile_size.x));
-
-    float sprite_sheet_x = floor(mod(float(texture_index), float(columns)) * (tile_size.x + spacing.x) - spacing.x);
-    float sprite_sheet_y = floor((texture_index / columns)) * (tile_size.y + spacing.y) - spacing.y;
+#else
+    int texture_index = Vertex_Texture.x;
+#endif
+
+#ifdef TILEMAP_TEXTURE_ARRAY
+    // Each tile is its own layer of the array texture.
+    float start_u = 0.0;
+    float end_u = 1.0;
+    float start_v = 0.0;
+    float end_v = 1.0;
+#else
+    int columns = int(floor((texture_size.x - margin.x) / tile_size.x));
+
+    float sprite_sheet_x = margin.x + floor(mod(float(texture_index), float(columns)) * (tile_size.x + spacing.x) - spacing.x);
+    float sprite_sheet_y = margin.y + floor((texture_index / columns)) * (tile_size.y + spacing.y) - spacing.y;
 
     float start_u = sprite_sheet_x / texture_size.x;
     float end_u = (sprite_sheet_x + tile_size.x) / texture_size.x;
     float start_v = sprite_sheet_y / texture_size.y;
     float end_v = (sprite_sheet_y + tile_size.y) / texture_size.y;
 
+    // Inset the uvs so sampling never reaches into the neighboring tiles.
+    vec2 padding = texture_padding / texture_size;
+    start_u += padding.x;
+    end_u -= padding.x;
+    start_v += padding.y;
+    end_v -= padding.y;
+#endif
+
+    // Flips the texture vertically for atlases with their origin at the bottom left.
+    if (flip_v > 0.5) {
+        start_v = 1.0 - start_v;
+        end_v = 1.0 - end_v;
+    }
+
     vec2 atlas_uvs[4];
     
-    // Texture flipping..
-    if (Vertex_Texture.y == 0) {
-        atlas_uvs = vec2[4](
-            vec2(start_u, end_v),
-            vec2(start_u, start_v),
-            vec2(end_u, start_v),
-            vec2(end_u, end_v)
-        );
-    } else if (Vertex_Texture.y == 1) { // flip x
-        atlas_uvs = vec2[4](
-            vec2(end_u, end_v),
-            vec2(end_u, start_v),
-            vec2(start_u, start_v),
-            vec2(start_u, end_v)
-        );
-    } else if(Vertex_Texture.y == 2) { // flip y
-        atlas_uvs = vec2[4](
-            vec2(start_u, start_v),
-            vec2(start_u, end_v),
-            vec2(end_u, end_v),
-            vec2(end_u, start_v)
-        );
-    } else if(Vertex_Texture.y == 3) { // both
-        atlas_uvs = vec2[4](
-            vec2(end_u, start_v),
-            vec2(end_u, end_v),
-            vec2(start_u, end_v),
-            vec2(start_u, start_v)
-        );
-    }
+    // Texture flipping and rotation.
+    vec2 x1[8] = {
+        vec2(start_u, end_v),       // no flip/rotation
+        vec2(end_u, end_v),         // flip x
+        vec2(start_u, start_v),     // flip y
+        vec2(end_u, start_v),       // flip x y
+        vec2(end_u, start_v),       // flip     d
+        vec2(end_u, end_v),         // flip x   d
+        vec2(start_u, start_v),     // flip y   d
+        vec2(start_u, end_v)
+    };
+
+    vec2 x2[8] = {
+        vec2(start_u, start_v),
+        vec2(end_u, start_v),
+        vec2(start_u, end_v),
+        vec2(end_u, end_v),
+        vec2(start_u, start_v),
+        vec2(start_u, end_v),
+        vec2(end_u, start_v),
+        vec2(end_u, end_v)
+    };
+
+    vec2 x3[8] = {
+        vec2(end_u, start_v),
+        vec2(start_u, start_v),
+        vec2(end_u, end_v),
+        vec2(start_u, end_v),
+        vec2(start_u, end_v),
+        vec2(start_u, start_v),
+        vec2(end_u, end_v),
+        vec2(end_u, start_v)
+    };
+
+    vec2 x4[8] = {
+        vec2(end_u, end_v),
+        vec2(start_u, end_v),
+        vec2(end_u, start_v),
+        vec2(start_u, start_v),
+        vec2(end_u, end_v),
+        vec2(end_u, start_v),
+        vec2(start_u, end_v),
+        vec2(start_u, start_v),
+    };
+
+    atlas_uvs = vec2[4](
+        x1[Vertex_Texture.y & 7],
+        x2[Vertex_Texture.y & 7],
+        x3[Vertex_Texture.y & 7],
+        x4[Vertex_Texture.y & 7]
+    );
 
     v_Uv = atlas_uvs[gl_VertexIndex % 4];
     // v_Uv += 1e-5;
+#ifdef TILEMAP_TINT
     v_color = Vertex_Color;
+    v_color *= vec4(tint.rgb, alpha);
+#else
+    v_color = vec4(tint.rgb, alpha);
+#endif
+    v_TextureLayer = float(texture_index);
+#ifdef TILEMAP_EMISSIVE
+    v_Emissive = Vertex_Emissive;
+#else
+    v_Emissive = 0.0;
+#endif
+    v_Palette = float((Vertex_Texture.y >> 4) & 255);
     gl_Position = ViewProj * world_pos;
+    v_WorldPos = world_pos.xy;
+
+    // Snaps the tile corners to whole pixels so tiles don't shimmer at fractional camera
+    // positions.
+    if (pixel_snap > 0.5 && viewport_size.x > 0.0) {
+        vec2 half_viewport = viewport_size * 0.5;
+        vec2 ndc = gl_Position.xy / gl_Position.w;
+        ndc = round((ndc + 1.0) * half_viewport) / half_viewport - 1.0;
+        gl_Position.xy = ndc * gl_Position.w;
+    }
+
+    // Hidden tiles collapse to a point outside of the screen so they produce no fragments.
+    if ((Vertex_Texture.y & 8) != 0) {
+        gl_Position = vec4(2.0, 2.0, 2.0, 1.0);
+    }
 }
\ No newline at end of file
@@ -15,30 +15,67 @@ pub(crate) mod pipeline;
 #[uuid = "7233c597-ccfa-411f-bd59-9af349432ada"]
 #[repr(C)]
 pub(crate) struct TilemapData {
+    pub(crate) fog_color: Vec4,
+    pub(crate) tint: Vec4,
     pub(crate) texture_size: Vec2,
     pub(crate) tile_size: Vec2,
     pub(crate) grid_size: Vec2,
     pub(crate) spacing: Vec2,
+    pub(crate) margin: Vec2,
     pub(crate) chunk_pos: Vec2,
     pub(crate) map_size: Vec2,
+    pub(crate) viewport_size: Vec2,
+    pub(crate) scroll: Vec2,
+    pub(crate) quad_size: Vec2,
+    pub(crate) tile_offset: Vec2,
+    pub(crate) fog_center: Vec2,
     pub(crate) time: f32,
+    pub(crate) texture_padding: f32,
+    pub(crate) alpha: f32,
+    pub(crate) y_sort: f32,
+    pub(crate) flip_v: f32,
+    pub(crate) pixel_snap: f32,
+    pub(crate) fog_radius: f32,
+    pub(crate) fog_falloff: f32,
+    pub(crate) alpha_cutoff: f32,
 }
 
 unsafe impl Byteable for TilemapData {}
 
+// The layer tint as it's passed to the shaders, which multiply it with the tile colors.
+pub(crate) fn tint_to_vec4(tint: Color) -> Vec4 {
+    Vec4::new(tint.r(), tint.g(), tint.b(), tint.a())
+}
+
 impl From<&Chunk> for TilemapData {
     fn from(chunk: &Chunk) -> Self {
         let chunk_pos: Vec2 = chunk.position.into();
         let chunk_size: Vec2 = chunk.settings.chunk_size.into();
         let map_size: Vec2 = chunk.settings.map_size.into();
         Self {
+            fog_color: Vec4::ZERO,
+            tint: tint_to_vec4(chunk.settings.tint),
             texture_size: chunk.settings.texture_size.into(),
             tile_size: chunk.settings.tile_size.into(),
             grid_size: chunk.settings.grid_size,
             spacing: chunk.settings.tile_spacing,
+            margin: chunk.settings.tile_margin,
             chunk_pos: chunk_pos * chunk_size,
             map_size: map_size * chunk_size * chunk.settings.grid_size,
+            viewport_size: Vec2::ZERO,
+            scroll: Vec2::ZERO,
+            quad_size: chunk.settings.get_quad_size(),
+            tile_offset: chunk.settings.tile_offset,
+            fog_center: Vec2::ZERO,
             time: 0.0,
+            texture_padding: chunk.settings.texture_padding,
+            alpha: chunk.settings.alpha,
+            y_sort: if chunk.settings.y_sort { 1.0 } else { 0.0 },
+            flip_v: if chunk.settings.flip_v { 1.0 } else { 0.0 },
+            pixel_snap: if chunk.settings.pixel_snap { 1.0 } else { 0.0 },
+            fog_radius: 0.0,
+            fog_falloff: 0.0,
+            alpha_cutoff: chunk.settings.alpha_cutoff,
         }
     }
 }
@@ -1,11 +1,12 @@
 use bevy::{
+    asset::HandleId,
     prelude::*,
     reflect::TypeUuid,
     render::{
         pipeline::{
             BlendFactor, BlendOperation, BlendState, ColorTargetState, ColorWrite, CompareFunction,
-            DepthBiasState, DepthStencilState, PipelineDescriptor, RenderPipeline,
-            StencilFaceState, StencilState,
+            CullMode, DepthBiasState, DepthStencilState, PipelineDescriptor, PrimitiveState,
+            RenderPipeline, StencilFaceState, StencilState,
         },
         render_graph::{base, RenderGraph, RenderResourcesNode},
         shader::{ShaderStage, ShaderStages},
@@ -13,7 +14,10 @@ use bevy::{
     },
 };
 
-use crate::TilemapMeshType;
+use crate::{
+    layer::TilemapTargetFormat, Chunk, Layer, TilemapBlendMode, TilemapMeshType, TilemapTextureMode,
+};
+use std::collections::HashMap;
 
 use super::TilemapData;
 
@@ -23,60 +27,266 @@ macro_rules! create_chunk_pipeline {
         pub(crate) const $pipeline_handle: HandleUntyped =
             HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, $pipeline_id);
 
-        /// Builds the chunk render pipeline.
-        fn $function(shaders: &mut Assets<Shader>) -> PipelineDescriptor {
-            PipelineDescriptor {
-                color_target_states: vec![ColorTargetState {
-                    format: TextureFormat::default(),
-                    color_blend: BlendState {
-                        src_factor: BlendFactor::SrcAlpha,
-                        dst_factor: BlendFactor::OneMinusSrcAlpha,
-                        operation: BlendOperation::Add,
-                    },
-                    alpha_blend: BlendState {
-                        src_factor: BlendFactor::One,
-                        dst_factor: BlendFactor::One,
-                        operation: BlendOperation::Add,
-                    },
-                    write_mask: ColorWrite::ALL,
-                }],
-                depth_stencil: Some(DepthStencilState {
-                    format: TextureFormat::Depth32Float,
-                    depth_write_enabled: true,
-                    depth_compare: CompareFunction::LessEqual,
-                    stencil: StencilState {
-                        front: StencilFaceState::IGNORE,
-                        back: StencilFaceState::IGNORE,
-                        read_mask: 0,
-                        write_mask: 0,
-                    },
-                    bias: DepthBiasState {
-                        constant: 0,
-                        slope_scale: 0.0,
-                        clamp: 0.0,
-                    },
-                    clamp_depth: false,
-                }),
-                ..PipelineDescriptor::new(ShaderStages {
-                    vertex: shaders.add(Shader::from_glsl(
-                        ShaderStage::Vertex,
-                        include_str!($vert_file),
-                    )),
-                    fragment: Some(shaders.add(Shader::from_glsl(
+        /// Builds the chunk shader stages for a texture mode.
+        /// Uses the default tilemap fragment shader unless one is given.
+        fn $function(
+            shaders: &mut Assets<Shader>,
+            texture_mode: TilemapTextureMode,
+            fragment: Option<Handle<Shader>>,
+        ) -> ShaderStages {
+            ShaderStages {
+                vertex: shaders.add(Shader::from_glsl(
+                    ShaderStage::Vertex,
+                    &texture_mode.shader_source(include_str!($vert_file)),
+                )),
+                fragment: Some(fragment.unwrap_or_else(|| {
+                    shaders.add(Shader::from_glsl(
                         ShaderStage::Fragment,
-                        include_str!($frag_file),
-                    ))),
-                })
+                        &texture_mode.shader_source(include_str!($frag_file)),
+                    ))
+                })),
             }
         }
     };
 }
 
+const BLEND_MODES: [TilemapBlendMode; 5] = [
+    TilemapBlendMode::AlphaBlend,
+    TilemapBlendMode::Additive,
+    TilemapBlendMode::Multiply,
+    TilemapBlendMode::Opaque,
+    TilemapBlendMode::AlphaMask,
+];
+
+impl TilemapBlendMode {
+    /// Returns the (color, alpha) blend states used by this blend mode.
+    fn blend_states(&self) -> (BlendState, BlendState) {
+        match self {
+            TilemapBlendMode::AlphaBlend => (
+                BlendState {
+                    src_factor: BlendFactor::SrcAlpha,
+                    dst_factor: BlendFactor::OneMinusSrcAlpha,
+                    operation: BlendOperation::Add,
+                },
+                BlendState {
+                    src_factor: BlendFactor::One,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+            ),
+            TilemapBlendMode::Additive => (
+                BlendState {
+                    src_factor: BlendFactor::One,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+                BlendState {
+                    src_factor: BlendFactor::One,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+            ),
+            TilemapBlendMode::Multiply => (
+                BlendState {
+                    src_factor: BlendFactor::DstColor,
+                    dst_factor: BlendFactor::Zero,
+                    operation: BlendOperation::Add,
+                },
+                BlendState {
+                    src_factor: BlendFactor::Zero,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+            ),
+            TilemapBlendMode::Opaque | TilemapBlendMode::AlphaMask => {
+                (BlendState::REPLACE, BlendState::REPLACE)
+            }
+        }
+    }
+
+    /// Offset applied to a base pipeline id to get the id of this blend mode's variant.
+    fn pipeline_offset(&self) -> u64 {
+        match self {
+            TilemapBlendMode::AlphaBlend => 0,
+            TilemapBlendMode::Additive => 1,
+            TilemapBlendMode::Multiply => 2,
+            TilemapBlendMode::Opaque => 3,
+            TilemapBlendMode::AlphaMask => 4,
+        }
+    }
+
+    /// Returns the shader define used by this blend mode, if any.
+    pub(crate) fn shader_def(&self) -> Option<&'static str> {
+        match self {
+            TilemapBlendMode::AlphaMask => Some("TILEMAP_ALPHA_MASK"),
+            _ => None,
+        }
+    }
+}
+
+const TEXTURE_MODES: [TilemapTextureMode; 2] =
+    [TilemapTextureMode::Atlas, TilemapTextureMode::Array];
+
+impl TilemapTextureMode {
+    /// Adds the defines used by this texture mode after the `#version` line of a shader.
+    fn shader_source(&self, source: &str) -> String {
+        match self {
+            TilemapTextureMode::Atlas => source.to_string(),
+            TilemapTextureMode::Array => {
+                let (version, rest) = source.split_at(source.find('\n').unwrap_or(0));
+                format!("{}\n#define TILEMAP_TEXTURE_ARRAY{}", version, rest)
+            }
+        }
+    }
+
+    /// Offset applied to a base pipeline id to get the id of this texture mode's variants.
+    fn pipeline_offset(&self) -> u64 {
+        match self {
+            TilemapTextureMode::Atlas => 0,
+            TilemapTextureMode::Array => BLEND_MODES.len() as u64,
+        }
+    }
+}
+
+/// Builds the chunk render pipeline for the given shader stages and blend mode.
+/// Chunks are drawn double sided, so layers mirrored with a negative scale still show up.
+pub(crate) fn create_chunk_pipeline_descriptor(
+    shader_stages: ShaderStages,
+    blend_mode: TilemapBlendMode,
+) -> PipelineDescriptor {
+    let (color_blend, alpha_blend) = blend_mode.blend_states();
+    let descriptor = PipelineDescriptor::new(shader_stages);
+    PipelineDescriptor {
+        primitive: PrimitiveState {
+            cull_mode: CullMode::None,
+            ..descriptor.primitive
+        },
+        color_target_states: vec![ColorTargetState {
+            format: TextureFormat::default(),
+            color_blend,
+            alpha_blend,
+            write_mask: ColorWrite::ALL,
+        }],
+        depth_stencil: Some(DepthStencilState {
+            format: TextureFormat::Depth32Float,
+            depth_write_enabled: true,
+            depth_compare: CompareFunction::LessEqual,
+            stencil: StencilState {
+                front: StencilFaceState::IGNORE,
+                back: StencilFaceState::IGNORE,
+                read_mask: 0,
+                write_mask: 0,
+            },
+            bias: DepthBiasState {
+                constant: 0,
+                slope_scale: 0.0,
+                clamp: 0.0,
+            },
+            clamp_depth: false,
+        }),
+        ..descriptor
+    }
+}
+
+type TargetFormatPipelines =
+    HashMap<(Handle<PipelineDescriptor>, TextureFormat), Handle<PipelineDescriptor>>;
+
+// Swaps the pipelines of chunks for copies which output to the `TilemapTargetFormat` of their
+// layer. Runs every frame so pipelines replaced by materials or wireframe rendering are
+// swapped as well.
+pub(crate) fn update_chunk_target_format(
+    mut target_pipelines: Local<TargetFormatPipelines>,
+    mut pipelines: ResMut<Assets<PipelineDescriptor>>,
+    layer_query: Query<&TilemapTargetFormat, With<Layer>>,
+    mut chunk_query: Query<(&Chunk, &mut RenderPipelines)>,
+) {
+    for (chunk, mut render_pipelines) in chunk_query.iter_mut() {
+        // The chunk's `map_entity` is the layer it was built in.
+        let format = layer_query
+            .get(chunk.map_entity)
+            .map_or(TextureFormat::default(), |target_format| target_format.0);
+        let has_format = |handle: &Handle<PipelineDescriptor>| {
+            pipelines.get(handle).map_or(true, |descriptor| {
+                descriptor
+                    .color_target_states
+                    .iter()
+                    .all(|target| target.format == format)
+            })
+        };
+        if render_pipelines
+            .pipelines
+            .iter()
+            .all(|render_pipeline| has_format(&render_pipeline.pipeline))
+        {
+            continue;
+        }
+
+        for render_pipeline in render_pipelines.pipelines.iter_mut() {
+            let key = (render_pipeline.pipeline.clone(), format);
+            if let Some(target_pipeline) = target_pipelines.get(&key) {
+                render_pipeline.pipeline = target_pipeline.clone();
+            } else if let Some(descriptor) = pipelines.get(&render_pipeline.pipeline) {
+                let mut descriptor = descriptor.clone();
+                let original_format = descriptor.color_target_states.first().map(|t| t.format);
+                for target in descriptor.color_target_states.iter_mut() {
+                    target.format = format;
+                }
+                let target_pipeline = pipelines.add(descriptor);
+                // Switching back to the original format reuses the original pipeline.
+                if let Some(original_format) = original_format {
+                    target_pipelines.insert(
+                        (target_pipeline.clone(), original_format),
+                        render_pipeline.pipeline.clone(),
+                    );
+                }
+                target_pipelines.insert(key, target_pipeline.clone());
+                render_pipeline.pipeline = target_pipeline;
+            }
+        }
+    }
+}
+
+/// Gets the handle of the pipeline variant for a base chunk pipeline, blend mode and texture mode.
+fn get_pipeline_handle(
+    base: HandleUntyped,
+    blend_mode: TilemapBlendMode,
+    texture_mode: TilemapTextureMode,
+) -> HandleUntyped {
+    match base.id {
+        HandleId::Id(type_uuid, id) => HandleUntyped::weak(HandleId::Id(
+            type_uuid,
+            id.wrapping_add(texture_mode.pipeline_offset() + blend_mode.pipeline_offset()),
+        )),
+        HandleId::AssetPathId(_) => base,
+    }
+}
+
+type CreateShaderStages =
+    fn(&mut Assets<Shader>, TilemapTextureMode, Option<Handle<Shader>>) -> ShaderStages;
+
+/// Registers a pipeline variant per blend mode and texture mode for a base chunk pipeline.
+fn set_chunk_pipelines(
+    pipelines: &mut Assets<PipelineDescriptor>,
+    shaders: &mut Assets<Shader>,
+    base: HandleUntyped,
+    create_shader_stages: CreateShaderStages,
+) {
+    for texture_mode in TEXTURE_MODES.iter() {
+        let shader_stages = create_shader_stages(shaders, *texture_mode, None);
+        for blend_mode in BLEND_MODES.iter() {
+            pipelines.set_untracked(
+                get_pipeline_handle(base.clone(), *blend_mode, *texture_mode),
+                create_chunk_pipeline_descriptor(shader_stages.clone(), *blend_mode),
+            );
+        }
+    }
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 create_chunk_pipeline!(
     SQUARE_PIPELINE,
     8094008129742001941,
-    create_square_pipeline,
+    create_square_shader_stages,
     "square-tilemap.vert",
     "tilemap.frag"
 );
@@ -84,7 +294,7 @@ create_chunk_pipeline!(
 create_chunk_pipeline!(
     SQUARE_PIPELINE,
     8094008129742001941,
-    create_square_pipeline,
+    create_square_shader_stages,
     "square-tilemap.es.vert",
     "tilemap.es.frag"
 );
@@ -92,7 +302,7 @@ create_chunk_pipeline!(
 create_chunk_pipeline!(
     DIAMOND_ISO_PIPELINE,
     5716002228110903793,
-    create_iso_diamond_pipeline,
+    create_iso_diamond_shader_stages,
     "diamondiso-tilemap.vert",
     "tilemap.frag"
 );
@@ -100,7 +310,7 @@ create_chunk_pipeline!(
 create_chunk_pipeline!(
     STAGGERED_ISO_PIPELINE,
     6571326172373592468,
-    create_iso_staggered_pipeline,
+    create_iso_staggered_shader_stages,
     "staggerediso-tilemap.vert",
     "tilemap.frag"
 );
@@ -108,7 +318,7 @@ create_chunk_pipeline!(
 create_chunk_pipeline!(
     COLUMN_EVEN_HEX_PIPELINE,
     5336568075571462317,
-    create_hex_column_even_pipeline,
+    create_hex_column_even_shader_stages,
     "columnevenhex-tilemap.vert",
     "tilemap.frag"
 );
@@ -116,7 +326,7 @@ create_chunk_pipeline!(
 create_chunk_pipeline!(
     COLUMN_ODD_HEX_PIPELINE,
     11472021184100190415,
-    create_hex_column_odd_pipeline,
+    create_hex_column_odd_shader_stages,
     "columnoddhex-tilemap.vert",
     "tilemap.frag"
 );
@@ -124,7 +334,7 @@ create_chunk_pipeline!(
 create_chunk_pipeline!(
     COLUMN_HEX_PIPELINE,
     12158158650956014109,
-    create_hex_column_pipeline,
+    create_hex_column_shader_stages,
     "columnhex-tilemap.vert",
     "tilemap.frag"
 );
@@ -132,7 +342,7 @@ create_chunk_pipeline!(
 create_chunk_pipeline!(
     ROW_EVEN_HEX_PIPELINE,
     14433932828806852042,
-    create_hex_row_even_pipeline,
+    create_hex_row_even_shader_stages,
     "rowevenhex-tilemap.vert",
     "tilemap.frag"
 );
@@ -140,7 +350,7 @@ create_chunk_pipeline!(
 create_chunk_pipeline!(
     ROW_ODD_HEX_PIPELINE,
     14864388685772956547,
-    create_hex_row_odd_pipeline,
+    create_hex_row_odd_shader_stages,
     "rowoddhex-tilemap.vert",
     "tilemap.frag"
 );
@@ -148,108 +358,145 @@ create_chunk_pipeline!(
 create_chunk_pipeline!(
     ROW_HEX_PIPELINE,
     15900471900964169180,
-    create_hex_row_pipeline,
+    create_hex_row_shader_stages,
     "rowhex-tilemap.vert",
     "tilemap.frag"
 );
 
 pub mod node {
-    pub const TILEMAP_DATA: &'static str = "tile_map_data";
+    pub const TILEMAP_DATA: &str = "tile_map_data";
 }
 
-impl Into<RenderPipelines> for TilemapMeshType {
-    fn into(self) -> RenderPipelines {
+impl TilemapMeshType {
+    /// Gets the base (alpha blended) pipeline handle for this mesh type.
+    fn base_pipeline(&self) -> HandleUntyped {
         match self {
-            TilemapMeshType::Square => {
-                RenderPipelines::from_pipelines(vec![RenderPipeline::new(SQUARE_PIPELINE.typed())])
-            }
-            TilemapMeshType::Isometric(iso_type) => match iso_type {
-                crate::IsoType::Diamond => {
-                    RenderPipelines::from_pipelines(vec![RenderPipeline::new(
-                        DIAMOND_ISO_PIPELINE.typed(),
-                    )])
-                }
-                crate::IsoType::Staggered => {
-                    RenderPipelines::from_pipelines(vec![RenderPipeline::new(
-                        STAGGERED_ISO_PIPELINE.typed(),
-                    )])
-                }
-            },
-            TilemapMeshType::Hexagon(hex_type) => match hex_type {
-                crate::HexType::Column => {
-                    RenderPipelines::from_pipelines(vec![RenderPipeline::new(
-                        COLUMN_HEX_PIPELINE.typed(),
-                    )])
-                }
-                crate::HexType::ColumnEven => {
-                    RenderPipelines::from_pipelines(vec![RenderPipeline::new(
-                        COLUMN_EVEN_HEX_PIPELINE.typed(),
-                    )])
-                }
-                crate::HexType::ColumnOdd => {
-                    RenderPipelines::from_pipelines(vec![RenderPipeline::new(
-                        COLUMN_ODD_HEX_PIPELINE.typed(),
-                    )])
-                }
-                crate::HexType::Row => RenderPipelines::from_pipelines(vec![RenderPipeline::new(
-                    ROW_HEX_PIPELINE.typed(),
-                )]),
-                crate::HexType::RowEven => {
-                    RenderPipelines::from_pipelines(vec![RenderPipeline::new(
-                        ROW_EVEN_HEX_PIPELINE.typed(),
-                    )])
-                }
-                crate::HexType::RowOdd => {
-                    RenderPipelines::from_pipelines(vec![RenderPipeline::new(
-                        ROW_ODD_HEX_PIPELINE.typed(),
-                    )])
-                }
-            },
+            TilemapMeshType::Square => SQUARE_PIPELINE,
+            TilemapMeshType::Isometric(crate::IsoType::Diamond) => DIAMOND_ISO_PIPELINE,
+            TilemapMeshType::Isometric(crate::IsoType::Staggered) => STAGGERED_ISO_PIPELINE,
+            TilemapMeshType::Hexagon(crate::HexType::Column) => COLUMN_HEX_PIPELINE,
+            TilemapMeshType::Hexagon(crate::HexType::ColumnEven) => COLUMN_EVEN_HEX_PIPELINE,
+            TilemapMeshType::Hexagon(crate::HexType::ColumnOdd) => COLUMN_ODD_HEX_PIPELINE,
+            TilemapMeshType::Hexagon(crate::HexType::Row) => ROW_HEX_PIPELINE,
+            TilemapMeshType::Hexagon(crate::HexType::RowEven) => ROW_EVEN_HEX_PIPELINE,
+            TilemapMeshType::Hexagon(crate::HexType::RowOdd) => ROW_ODD_HEX_PIPELINE,
         }
     }
+
+    /// Builds the shader stages for this mesh type with a custom fragment shader.
+    pub(crate) fn create_shader_stages(
+        &self,
+        shaders: &mut Assets<Shader>,
+        texture_mode: TilemapTextureMode,
+        fragment: Handle<Shader>,
+    ) -> ShaderStages {
+        let create_shader_stages: CreateShaderStages = match self {
+            TilemapMeshType::Square => create_square_shader_stages,
+            TilemapMeshType::Isometric(crate::IsoType::Diamond) => create_iso_diamond_shader_stages,
+            TilemapMeshType::Isometric(crate::IsoType::Staggered) => {
+                create_iso_staggered_shader_stages
+            }
+            TilemapMeshType::Hexagon(crate::HexType::Column) => create_hex_column_shader_stages,
+            TilemapMeshType::Hexagon(crate::HexType::ColumnEven) => {
+                create_hex_column_even_shader_stages
+            }
+            TilemapMeshType::Hexagon(crate::HexType::ColumnOdd) => {
+                create_hex_column_odd_shader_stages
+            }
+            TilemapMeshType::Hexagon(crate::HexType::Row) => create_hex_row_shader_stages,
+            TilemapMeshType::Hexagon(crate::HexType::RowEven) => create_hex_row_even_shader_stages,
+            TilemapMeshType::Hexagon(crate::HexType::RowOdd) => create_hex_row_odd_shader_stages,
+        };
+        create_shader_stages(shaders, texture_mode, Some(fragment))
+    }
+}
+
+/// Gets the render pipelines used to draw chunks of the given mesh type, blend mode and
+/// texture mode.
+pub(crate) fn get_render_pipelines(
+    mesh_type: TilemapMeshType,
+    blend_mode: TilemapBlendMode,
+    texture_mode: TilemapTextureMode,
+) -> RenderPipelines {
+    let handle = get_pipeline_handle(mesh_type.base_pipeline(), blend_mode, texture_mode);
+    RenderPipelines::from_pipelines(vec![RenderPipeline::new(handle.typed())])
+}
+
+impl From<TilemapMeshType> for RenderPipelines {
+    fn from(mesh_type: TilemapMeshType) -> RenderPipelines {
+        get_render_pipelines(
+            mesh_type,
+            TilemapBlendMode::AlphaBlend,
+            TilemapTextureMode::Atlas,
+        )
+    }
 }
 
 pub(crate) fn add_tile_map_graph(world: &mut World) {
     world.resource_scope(|world, mut pipelines: Mut<Assets<PipelineDescriptor>>| {
         world.resource_scope(|world, mut shaders: Mut<Assets<Shader>>| {
             let mut graph = world.get_resource_mut::<RenderGraph>().unwrap();
-            pipelines.set_untracked(SQUARE_PIPELINE, create_square_pipeline(&mut shaders));
+            set_chunk_pipelines(
+                &mut pipelines,
+                &mut shaders,
+                SQUARE_PIPELINE,
+                create_square_shader_stages,
+            );
 
-            pipelines.set_untracked(
+            set_chunk_pipelines(
+                &mut pipelines,
+                &mut shaders,
                 DIAMOND_ISO_PIPELINE,
-                create_iso_diamond_pipeline(&mut shaders),
+                create_iso_diamond_shader_stages,
             );
 
-            pipelines.set_untracked(
+            set_chunk_pipelines(
+                &mut pipelines,
+                &mut shaders,
                 STAGGERED_ISO_PIPELINE,
-                create_iso_staggered_pipeline(&mut shaders),
+                create_iso_staggered_shader_stages,
             );
 
-            pipelines.set_untracked(ROW_HEX_PIPELINE, create_hex_row_pipeline(&mut shaders));
+            set_chunk_pipelines(
+                &mut pipelines,
+                &mut shaders,
+                ROW_HEX_PIPELINE,
+                create_hex_row_shader_stages,
+            );
 
-            pipelines.set_untracked(
+            set_chunk_pipelines(
+                &mut pipelines,
+                &mut shaders,
                 ROW_ODD_HEX_PIPELINE,
-                create_hex_row_odd_pipeline(&mut shaders),
+                create_hex_row_odd_shader_stages,
             );
 
-            pipelines.set_untracked(
+            set_chunk_pipelines(
+                &mut pipelines,
+                &mut shaders,
                 ROW_EVEN_HEX_PIPELINE,
-                create_hex_row_even_pipeline(&mut shaders),
+                create_hex_row_even_shader_stages,
             );
 
-            pipelines.set_untracked(
+            set_chunk_pipelines(
+                &mut pipelines,
+                &mut shaders,
                 COLUMN_HEX_PIPELINE,
-                create_hex_column_pipeline(&mut shaders),
+                create_hex_column_shader_stages,
             );
 
-            pipelines.set_untracked(
+            set_chunk_pipelines(
+                &mut pipelines,
+                &mut shaders,
                 COLUMN_ODD_HEX_PIPELINE,
-                create_hex_column_odd_pipeline(&mut shaders),
+                create_hex_column_odd_shader_stages,
             );
 
-            pipelines.set_untracked(
+            set_chunk_pipelines(
+                &mut pipelines,
+                &mut shaders,
                 COLUMN_EVEN_HEX_PIPELINE,
-                create_hex_column_even_pipeline(&mut shaders),
+                create_hex_column_even_shader_stages,
             );
 
             graph.add_system_node(
@@ -263,3 +510,145 @@ pub(crate) fn add_tile_map_graph(world: &mut World) {
         });
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_pipelines_test_depth() {
+        // Y sorted tiles only draw in row order with a depth buffer.
+        for blend_mode in BLEND_MODES.iter() {
+            let descriptor =
+                create_chunk_pipeline_descriptor(ShaderStages::new(Handle::default()), *blend_mode);
+            let depth_stencil = descriptor.depth_stencil.unwrap();
+            assert!(depth_stencil.depth_write_enabled);
+            assert_eq!(depth_stencil.depth_compare, CompareFunction::LessEqual);
+        }
+    }
+
+    #[test]
+    fn additive_pipelines_add_the_source() {
+        let descriptor = create_chunk_pipeline_descriptor(
+            ShaderStages::new(Handle::default()),
+            TilemapBlendMode::Additive,
+        );
+        let color_target = &descriptor.color_target_states[0];
+        assert_eq!(color_target.color_blend.src_factor, BlendFactor::One);
+        assert_eq!(color_target.alpha_blend.src_factor, BlendFactor::One);
+    }
+
+    #[test]
+    fn alpha_mask_pipelines_dont_blend() {
+        // Masked fragments are discarded by the shader, the rest replace what is below them.
+        let descriptor = create_chunk_pipeline_descriptor(
+            ShaderStages::new(Handle::default()),
+            TilemapBlendMode::AlphaMask,
+        );
+        let color_target = &descriptor.color_target_states[0];
+        assert_eq!(color_target.color_blend, BlendState::REPLACE);
+        assert_eq!(color_target.alpha_blend, BlendState::REPLACE);
+        assert_eq!(
+            TilemapBlendMode::AlphaMask.shader_def(),
+            Some("TILEMAP_ALPHA_MASK")
+        );
+    }
+
+    #[test]
+    fn chunk_pipelines_draw_both_faces() {
+        // Layers mirrored with a negative scale are drawn back facing.
+        for blend_mode in BLEND_MODES.iter() {
+            let descriptor =
+                create_chunk_pipeline_descriptor(ShaderStages::new(Handle::default()), *blend_mode);
+            assert_eq!(descriptor.primitive.cull_mode, CullMode::None);
+        }
+    }
+
+    #[test]
+    fn chunk_pipelines_use_the_msaa_sample_count() {
+        use crate::prelude::*;
+        use crate::test_util::{layer_settings, spawn_layer};
+        use bevy::app::Events;
+        use bevy::render::{
+            draw::OutsideFrustum,
+            pipeline::PipelineCompiler,
+            renderer::{HeadlessRenderResourceContext, RenderResourceContext, SharedBuffers},
+        };
+
+        let mut app = App::build();
+        app.insert_resource(Msaa { samples: 4 })
+            .add_plugins(MinimalPlugins)
+            .add_plugin(bevy::transform::TransformPlugin)
+            .add_plugin(bevy::window::WindowPlugin::default())
+            .add_plugin(bevy::asset::AssetPlugin)
+            .add_plugin(bevy::render::RenderPlugin::default())
+            .add_plugin(bevy::sprite::SpritePlugin)
+            .add_plugin(TilemapPlugin);
+        let render_resource_context: Box<dyn RenderResourceContext> =
+            Box::new(HeadlessRenderResourceContext::default());
+        app.world_mut().insert_resource(render_resource_context);
+        app.world_mut().insert_resource(SharedBuffers::new(4096));
+        // The headless context hands shaders back uncompiled, so compile them up front for
+        // the pipeline compiler to reflect their layouts.
+        let mut shaders = app
+            .world_mut()
+            .get_resource_mut::<Assets<Shader>>()
+            .unwrap();
+        let glsl_shaders: Vec<(HandleId, Shader)> = shaders
+            .iter()
+            .map(|(id, shader)| (id, shader.get_spirv_shader(None).unwrap()))
+            .collect();
+        for (id, shader) in glsl_shaders {
+            shaders.set_untracked(id, shader);
+        }
+
+        let settings = layer_settings(MapSize(1, 1), ChunkSize(4, 4));
+        spawn_layer(
+            app.world_mut(),
+            settings,
+            Handle::default(),
+            |layer_builder| {
+                layer_builder.set_all(Tile::default().into());
+            },
+        );
+        // Keep the chunk from being drawn until its mesh is built, so it isn't drawn without the
+        // vertex attributes of the tilemap shaders.
+        let chunk_entity = app
+            .world_mut()
+            .query_filtered::<Entity, With<Chunk>>()
+            .iter(app.world())
+            .next()
+            .unwrap();
+        app.world_mut()
+            .entity_mut(chunk_entity)
+            .insert(OutsideFrustum);
+        let mut meshed = 0;
+        while meshed == 0 {
+            app.app.update();
+            let events = app.world().get_resource::<Events<ChunkMeshed>>().unwrap();
+            meshed += events.get_reader().iter(events).count();
+        }
+        app.world_mut()
+            .entity_mut(chunk_entity)
+            .remove::<OutsideFrustum>();
+        app.app.update();
+
+        let mut chunk_pipelines = app
+            .world_mut()
+            .query_filtered::<&RenderPipelines, With<Chunk>>();
+        let world = app.world();
+        let compiler = world.get_resource::<PipelineCompiler>().unwrap();
+        let pipelines = world.get_resource::<Assets<PipelineDescriptor>>().unwrap();
+        let counts: Vec<u32> = chunk_pipelines
+            .iter(world)
+            .flat_map(|render_pipelines| render_pipelines.pipelines.iter())
+            .filter_map(|render_pipeline| {
+                compiler.iter_compiled_pipelines(render_pipeline.pipeline.clone_weak())
+            })
+            .flatten()
+            .map(|handle| pipelines.get(handle).unwrap().multisample.count)
+            .collect();
+        assert!(!counts.is_empty());
+        assert!(counts.iter().all(|count| *count == 4));
+    }
+}
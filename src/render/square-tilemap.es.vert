@@ -4,9 +4,25 @@ precision highp float;
 in vec3 Vertex_Position;
 in ivec4 Vertex_Texture;
 in vec4 Vertex_Color;
+#ifdef TILEMAP_EMISSIVE
+in float Vertex_Emissive;
+#endif
+#ifdef TILEMAP_MERGED
+in vec2 Vertex_Size;
+#endif
+#ifdef TILEMAP_ROTATION
+in vec2 Vertex_Corner;
+#endif
 
 out vec2 v_Uv;
 out vec4 v_color;
+out float v_TextureLayer;
+out float v_Emissive;
+out float v_Palette;
+out vec2 v_WorldPos;
+#ifdef TILEMAP_MERGED
+out vec4 v_UvRect;
+#endif
 # define gl_VertexIndex gl_VertexID
 
 
@@ -19,46 +35,106 @@ uniform Transform {
 };
 
 uniform TilemapData {
+    vec4 fog_color;
+    vec4 tint;
     vec2 texture_size;
     vec2 tile_size;
     vec2 grid_size;
     vec2 spacing;
+    vec2 margin;
     vec2 chunk_pos;
     vec2 map_size;
+    vec2 viewport_size;
+    vec2 scroll;
+    vec2 quad_size;
+    vec2 tile_offset;
+    vec2 fog_center;
     float time;
+    float texture_padding;
+    float alpha;
+    float y_sort;
+    float flip_v;
+    float pixel_snap;
+    float fog_radius;
+    float fog_falloff;
+    float alpha_cutoff;
 };
 void main() {
     vec2 uv = vec2(0.0);
     vec2 position = Vertex_Position.xy;
 
+#ifdef TILEMAP_MERGED
+    // Merged quads cover several identical tiles.
+    vec2 size = Vertex_Size;
+#else
+    vec2 size = vec2(1.0);
+#endif
+
     vec2 positions[4] = vec2[4](
         vec2(position.x, position.y),
-        vec2(position.x, position.y + 1.0),
-        vec2(position.x + 1.0, position.y + 1.0),
-        vec2(position.x + 1.0, position.y)
+        vec2(position.x, position.y + size.y),
+        vec2(position.x + size.x, position.y + size.y),
+        vec2(position.x + size.x, position.y)
     );
 
-    position = positions[gl_VertexID % 4];
-    position.xy *= tile_size;
-
+    // Tiles are laid out on the grid but keep their own size, so tiles taller than the grid
+    // overlap the row above them.
+#ifdef TILEMAP_ROTATION
+    // Rotated tiles have the corners of their quads baked into the mesh.
+    vec2 quad_corner = Vertex_Corner;
+#else
+    vec2 quad_corner = positions[gl_VertexID % 4] - Vertex_Position.xy;
+#endif
+    position = Vertex_Position.xy * grid_size + quad_corner * quad_size;
+    position += tile_offset;
+
+    // Scrolls the tile, wrapping it around to the other side of the layer.
+    vec2 tile_origin = (chunk_pos + Vertex_Position.xy) * grid_size;
+    position += mod(tile_origin + scroll, map_size) - tile_origin;
+
+#ifdef TILEMAP_ANIMATION
     float frames = float(Vertex_Texture.w - Vertex_Texture.z);
 
-    float current_animation_frame = fract(time * Vertex_Position.z) * frames;
+    float current_animation_frame = float(Vertex_Texture.z) + fract(time * Vertex_Position.z) * frames;
 
     current_animation_frame = clamp(current_animation_frame, float(Vertex_Texture.z), float(Vertex_Texture.w));
 
     int texture_index = int(current_animation_frame);
-
-    int columns = int((texture_size.x + spacing.x) / (tile_size.x + spacing.x));
-
-    float sprite_sheet_x = floor(float(texture_index % columns)) * (tile_size.x + spacing.x);
-    float sprite_sheet_y = floor(float(texture_index) / float(columns)) * (tile_size.y + spacing.y);
+#else
+    int texture_index = Vertex_Texture.x;
+#endif
+
+#ifdef TILEMAP_TEXTURE_ARRAY
+    // Each tile is its own layer of the array texture.
+    float start_u = 0.0;
+    float end_u = 1.0;
+    float start_v = 0.0;
+    float end_v = 1.0;
+#else
+    int columns = int((texture_size.x - margin.x + spacing.x) / (tile_size.x + spacing.x));
+
+    float sprite_sheet_x = margin.x + floor(float(texture_index % columns)) * (tile_size.x + spacing.x);
+    float sprite_sheet_y = margin.y + floor(float(texture_index) / float(columns)) * (tile_size.y + spacing.y);
 
     float start_u = sprite_sheet_x / texture_size.x;
     float end_u = (sprite_sheet_x + tile_size.x) / texture_size.x;
     float start_v = sprite_sheet_y / texture_size.y;
     float end_v = (sprite_sheet_y + tile_size.y) / texture_size.y;
 
+    // Inset the uvs so sampling never reaches into the neighboring tiles.
+    vec2 padding = texture_padding / texture_size;
+    start_u += padding.x;
+    end_u -= padding.x;
+    start_v += padding.y;
+    end_v -= padding.y;
+#endif
+
+    // Flips the texture vertically for atlases with their origin at the bottom left.
+    if (flip_v > 0.5) {
+        start_v = 1.0 - start_v;
+        end_v = 1.0 - end_v;
+    }
+
     vec2 atlas_uvs[4];
 
     vec2 x1[8] = vec2[](
@@ -106,14 +182,59 @@ void main() {
     );
 
     atlas_uvs = vec2[4](
-        x1[Vertex_Texture.y],
-        x2[Vertex_Texture.y],
-        x3[Vertex_Texture.y],
-        x4[Vertex_Texture.y]
+        x1[Vertex_Texture.y & 7],
+        x2[Vertex_Texture.y & 7],
+        x3[Vertex_Texture.y & 7],
+        x4[Vertex_Texture.y & 7]
     );
 
     v_Uv = atlas_uvs[gl_VertexIndex % 4];
+#ifdef TILEMAP_MERGED
+    // Extends the uvs of a single tile across the quad, the fragment shader wraps them back into
+    // the tile's rectangle so the texture repeats once per tile.
+    vec2 corners[4] = vec2[4](vec2(0.0), vec2(0.0, size.y), size, vec2(size.x, 0.0));
+    vec2 corner = corners[gl_VertexIndex % 4];
+    v_Uv = atlas_uvs[0]
+        + corner.x * (atlas_uvs[3] - atlas_uvs[0])
+        + corner.y * (atlas_uvs[1] - atlas_uvs[0]);
+    v_UvRect = vec4(
+        min(start_u, end_u),
+        min(start_v, end_v),
+        abs(end_u - start_u),
+        abs(end_v - start_v));
+#endif
     v_Uv += 1e-5;
+#ifdef TILEMAP_TINT
     v_color = Vertex_Color;
-    gl_Position = ViewProj * Model * vec4(position, 0.0, 1.0);
+    v_color *= vec4(tint.rgb, alpha);
+#else
+    v_color = vec4(tint.rgb, alpha);
+#endif
+    v_TextureLayer = float(texture_index);
+#ifdef TILEMAP_EMISSIVE
+    v_Emissive = Vertex_Emissive;
+#else
+    v_Emissive = 0.0;
+#endif
+    v_Palette = float((Vertex_Texture.y >> 4) & 255);
+    // Tiles further down the map are drawn in front when y sorting is enabled.
+    // The depths are scaled to stay below 1.0, which is the depth of the layer above.
+    float depth = y_sort * (1.0 - (chunk_pos.y + Vertex_Position.y) * grid_size.y / map_size.y) * (1.0 - 1.0 / 1024.0);
+    vec4 world_position = Model * vec4(position, depth, 1.0);
+    gl_Position = ViewProj * world_position;
+    v_WorldPos = world_position.xy;
+
+    // Snaps the tile corners to whole pixels so tiles don't shimmer at fractional camera
+    // positions.
+    if (pixel_snap > 0.5 && viewport_size.x > 0.0) {
+        vec2 half_viewport = viewport_size * 0.5;
+        vec2 ndc = gl_Position.xy / gl_Position.w;
+        ndc = round((ndc + 1.0) * half_viewport) / half_viewport - 1.0;
+        gl_Position.xy = ndc * gl_Position.w;
+    }
+
+    // Hidden tiles collapse to a point outside of the screen so they produce no fragments.
+    if ((Vertex_Texture.y & 8) != 0) {
+        gl_Position = vec4(2.0, 2.0, 2.0, 1.0);
+    }
 }
@@ -0,0 +1,92 @@
+#version 450
+
+layout(location = 0) in vec2 v_Uv;
+layout(location = 1) in vec4 v_color;
+layout(location = 2) in float v_TextureLayer;
+layout(location = 3) in float v_Emissive;
+layout(location = 4) in float v_Palette;
+#ifdef TILEMAP_MERGED
+layout(location = 5) in vec4 v_UvRect;
+#endif
+layout(location = 6) in vec2 v_WorldPos;
+
+layout(location = 0) out vec4 o_Target;
+
+layout(set = 1, binding = 0) uniform ColorMaterial_color {
+    vec4 Color;
+};
+layout(set = 1, binding = 1) uniform texture2D ColorMaterial_texture;
+layout(set = 1, binding = 2) uniform sampler ColorMaterial_texture_sampler;
+
+layout(set = 3, binding = 0) uniform texture2D TilemapPalette_palettes;
+layout(set = 3, binding = 1) uniform sampler TilemapPalette_palettes_sampler;
+layout(set = 3, binding = 2) uniform TilemapPalette_active_palette {
+    uint active_palette;
+};
+
+layout(set = 2, binding = 1) uniform TilemapData {
+    vec4 fog_color;
+    vec4 tint;
+    vec2 texture_size;
+    vec2 tile_size;
+    vec2 grid_size;
+    vec2 spacing;
+    vec2 margin;
+    vec2 chunk_pos;
+    vec2 map_size;
+    vec2 viewport_size;
+    vec2 scroll;
+    vec2 quad_size;
+    vec2 tile_offset;
+    vec2 fog_center;
+    float time;
+    float texture_padding;
+    float alpha;
+    float y_sort;
+    float flip_v;
+    float pixel_snap;
+    float fog_radius;
+    float fog_falloff;
+    float alpha_cutoff;
+};
+
+void main() {
+    vec2 uv = v_Uv;
+#ifdef TILEMAP_MERGED
+    // Repeats the tile's texture across merged quads.
+    uv = v_UvRect.xy + mod(uv - v_UvRect.xy, v_UvRect.zw);
+#endif
+
+    vec4 texel = texture(
+        sampler2D(ColorMaterial_texture, ColorMaterial_texture_sampler),
+        uv);
+
+    // The red channel of the tile texture picks a column of the palette texture and the palette
+    // of the tile picks a row, wrapping around to the first palette.
+    ivec2 palette_size = textureSize(
+        sampler2D(TilemapPalette_palettes, TilemapPalette_palettes_sampler), 0);
+    int color_index = min(int(round(texel.r * 255.0)), palette_size.x - 1);
+    int palette = (int(v_Palette) + int(active_palette)) % palette_size.y;
+    vec4 palette_color = texelFetch(
+        sampler2D(TilemapPalette_palettes, TilemapPalette_palettes_sampler),
+        ivec2(color_index, palette), 0);
+
+    vec4 color = Color * v_color * vec4(palette_color.rgb, palette_color.a * texel.a);
+
+    // Fades the tile towards the fog color with its distance from the center of the fog.
+    float fog = smoothstep(
+        fog_radius, fog_radius + max(fog_falloff, 0.001), distance(v_WorldPos, fog_center));
+    color.rgb = mix(color.rgb, fog_color.rgb, fog * fog_color.a);
+
+#ifdef TILEMAP_ALPHA_MASK
+    // Cuts out the texels below the cutoff, as the layer isn't blended.
+    if (color.a < alpha_cutoff) {
+        discard;
+    }
+#endif
+    if (color.a < 0.001) {
+        discard;
+    }
+
+    o_Target = color;
+}
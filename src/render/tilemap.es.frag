@@ -4,10 +4,17 @@ precision highp float;
 
 # define TEXTURE_2D sampler2D
 # define sampler2D(a, b) (a)
+# define TEXTURE_2D_ARRAY highp sampler2DArray
+# define sampler2DArray(a, b) (a)
 # define gl_VertexIndex gl_VertexID
 
 in vec2 v_Uv;
 in vec4 v_color;
+in float v_TextureLayer;
+#ifdef TILEMAP_MERGED
+in vec4 v_UvRect;
+#endif
+in vec2 v_WorldPos;
 
  out vec4 o_Target;
 
@@ -16,17 +23,70 @@ layout(std140) uniform ColorMaterial_color {
 };
 
 # ifdef COLORMATERIAL_TEXTURE 
+# ifdef TILEMAP_TEXTURE_ARRAY
+uniform TEXTURE_2D_ARRAY ColorMaterial_texture;  // set = 2, binding = 1
+# else
 uniform TEXTURE_2D ColorMaterial_texture;  // set = 2, binding = 1
 # endif
+# endif
+
+uniform TilemapData {
+    vec4 fog_color;
+    vec4 tint;
+    vec2 texture_size;
+    vec2 tile_size;
+    vec2 grid_size;
+    vec2 spacing;
+    vec2 margin;
+    vec2 chunk_pos;
+    vec2 map_size;
+    vec2 viewport_size;
+    vec2 scroll;
+    vec2 quad_size;
+    vec2 tile_offset;
+    vec2 fog_center;
+    float time;
+    float texture_padding;
+    float alpha;
+    float y_sort;
+    float flip_v;
+    float pixel_snap;
+    float fog_radius;
+    float fog_falloff;
+    float alpha_cutoff;
+};
 
 void main() {
+    vec2 uv = v_Uv;
+#ifdef TILEMAP_MERGED
+    // Repeats the tile's texture across merged quads.
+    uv = v_UvRect.xy + mod(uv - v_UvRect.xy, v_UvRect.zw);
+#endif
+
     vec4 color = Color * v_color;
 # ifdef COLORMATERIAL_TEXTURE
+# ifdef TILEMAP_TEXTURE_ARRAY
+    color *= texture(
+        sampler2DArray(ColorMaterial_texture, ColorMaterial_texture_sampler),
+        vec3(uv, v_TextureLayer));
+# else
     color *= texture(
         sampler2D(ColorMaterial_texture, ColorMaterial_texture_sampler),
-        v_Uv);
+        uv);
 # endif
+# endif
+
+    // Fades the tile towards the fog color with its distance from the center of the fog.
+    float fog = smoothstep(
+        fog_radius, fog_radius + max(fog_falloff, 0.001), distance(v_WorldPos, fog_center));
+    color.rgb = mix(color.rgb, fog_color.rgb, fog * fog_color.a);
 
+#ifdef TILEMAP_ALPHA_MASK
+    // Cuts out the texels below the cutoff, as the layer isn't blended.
+    if (color.a < alpha_cutoff) {
+        discard;
+    }
+#endif
     if (color.a < 0.001) {
         discard;
     }
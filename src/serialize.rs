@@ -0,0 +1,164 @@
+use crate::{LayerSettings, Tile, TilePos};
+use serde::{Deserialize, Serialize};
+
+/// The version written at the start of every serialized map.
+/// Bump this whenever the layout of `SerializedMap` changes.
+pub(crate) const MAP_FORMAT_VERSION: u32 = 18;
+
+/// Errors returned when saving or loading a map.
+#[derive(Debug)]
+pub enum MapSerializeError {
+    /// The map doesn't exist.
+    NonExistent,
+    /// The data was written with a format version this crate can't read.
+    UnsupportedVersion(u32),
+    /// The data couldn't be encoded or decoded.
+    Bincode(bincode::Error),
+}
+
+impl From<bincode::Error> for MapSerializeError {
+    fn from(error: bincode::Error) -> Self {
+        MapSerializeError::Bincode(error)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SerializedMap {
+    pub version: u32,
+    pub map_id: u16,
+    pub layers: Vec<SerializedLayer>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SerializedLayer {
+    pub settings: LayerSettings,
+    pub tiles: Vec<(TilePos, Tile)>,
+}
+
+impl SerializedMap {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, MapSerializeError> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MapSerializeError> {
+        // Check the version on its own first so older data fails with a useful error
+        // instead of a decoding error.
+        let version: u32 = bincode::deserialize(bytes)?;
+        if version != MAP_FORMAT_VERSION {
+            return Err(MapSerializeError::UnsupportedVersion(version));
+        }
+        Ok(bincode::deserialize(bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::test_util::{layer_settings, new_world, run_system};
+    use bevy::prelude::*;
+
+    struct SavedMap(Vec<u8>);
+
+    fn spawn_map(mut commands: Commands, mut map_query: MapQuery) {
+        let map_entity = commands.spawn().id();
+        let mut map = Map::new(3u16, map_entity);
+        for layer_id in 0..2u16 {
+            let mut settings = layer_settings(MapSize(2, 2), ChunkSize(4, 4));
+            settings.mesh_type = if layer_id == 0 {
+                TilemapMeshType::Square
+            } else {
+                TilemapMeshType::Isometric(IsoType::Diamond)
+            };
+            let (mut layer_builder, layer_entity) =
+                LayerBuilder::<TileBundle>::new(&mut commands, settings, 3u16, layer_id, None);
+            for x in 0..8 {
+                for y in 0..8 {
+                    // Every other tile of the second layer is left empty.
+                    if layer_id == 1 && (x + y) % 2 == 0 {
+                        continue;
+                    }
+                    let tile = Tile {
+                        texture_index: (x + y * 8) as u16,
+                        flip_x: x % 3 == 0,
+                        visible: y != 5,
+                        color: Color::rgba(x as f32 / 8.0, 0.5, 1.0, 0.25),
+                        ..Default::default()
+                    };
+                    layer_builder.set_tile(TilePos(x, y), tile.into()).unwrap();
+                }
+            }
+            map_query.build_layer(&mut commands, layer_builder, Handle::default());
+            map.add_layer(&mut commands, layer_id, layer_entity);
+        }
+        commands.entity(map_entity).insert(map);
+    }
+
+    fn save_map(mut commands: Commands, map_query: MapQuery, tile_query: Query<&Tile>) {
+        commands.insert_resource(SavedMap(map_query.save_map(3u16, &tile_query).unwrap()));
+    }
+
+    fn load_map(mut commands: Commands, saved_map: Res<SavedMap>, mut map_query: MapQuery) {
+        let map_entity = commands.spawn().id();
+        let map = map_query
+            .load_map(&mut commands, &saved_map.0, map_entity, |_| {
+                Handle::default()
+            })
+            .unwrap();
+        commands.entity(map_entity).insert(map);
+    }
+
+    // The settings of every layer and every tile of the world, sorted by layer and position.
+    fn map_contents(world: &mut World) -> (Vec<String>, Vec<String>) {
+        let mut layers: Vec<(u16, String)> = world
+            .query::<&Layer>()
+            .iter(world)
+            .map(|layer| (layer.settings.layer_id, format!("{:?}", layer.settings)))
+            .collect();
+        layers.sort_by_key(|(layer_id, _)| *layer_id);
+        let mut tiles: Vec<((u16, u16, u32, u32), String)> = world
+            .query::<(&TilePos, &Tile, &TileParent)>()
+            .iter(world)
+            .map(|(tile_pos, tile, tile_parent)| {
+                (
+                    (
+                        tile_parent.map_id,
+                        tile_parent.layer_id,
+                        tile_pos.1,
+                        tile_pos.0,
+                    ),
+                    format!("{:?}", tile),
+                )
+            })
+            .collect();
+        tiles.sort_by_key(|(key, _)| *key);
+        (
+            layers.into_iter().map(|(_, layer)| layer).collect(),
+            tiles
+                .into_iter()
+                .map(|(key, tile)| format!("{:?} {}", key, tile))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn saved_maps_load_the_same_layers_and_tiles() {
+        let mut world = new_world();
+        run_system(&mut world, spawn_map.system());
+        run_system(&mut world, save_map.system());
+        let saved_map = world.remove_resource::<SavedMap>().unwrap();
+        let (layers, tiles) = map_contents(&mut world);
+        assert_eq!(layers.len(), 2);
+        assert_eq!(tiles.len(), 64 + 32);
+
+        let mut loaded_world = new_world();
+        loaded_world.insert_resource(saved_map);
+        run_system(&mut loaded_world, load_map.system());
+        assert_eq!(map_contents(&mut loaded_world), (layers, tiles));
+        let map = loaded_world
+            .query::<&Map>()
+            .iter(&loaded_world)
+            .next()
+            .unwrap();
+        assert_eq!(map.id, 3);
+    }
+}
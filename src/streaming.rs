@@ -0,0 +1,350 @@
+use crate::{
+    chunk::ChunkBundle,
+    layer_builder::LayerBuilder,
+    morton_index,
+    render::{pipeline::get_render_pipelines, TilemapData},
+    Chunk, ChunkPos, Layer, Tile, TileBundle, TileParent, TilePos, TilemapMeshType,
+};
+use bevy::{
+    prelude::*,
+    render::{
+        camera::Camera,
+        mesh::{Indices, VertexAttributeValues},
+        render_graph::base::camera::CAMERA_2D,
+    },
+};
+
+/// A component which streams the chunks of a layer around the camera, for procedural worlds
+/// which are too large to spawn at once.
+///
+/// Insert it on a layer entity. Chunks within `radius` chunks of the chunk under the 2d camera
+/// are spawned with the tiles returned by the generator and meshed like any other chunk.
+/// Chunks further away are despawned along with their tiles, which frees their meshes. The
+/// layer's `map_size` bounds the streamed chunks, so make it as large as the world can get.
+///
+/// Note: Only square layers are streamed.
+/// ```
+/// use bevy::prelude::*;
+/// use bevy_ecs_tilemap::prelude::*;
+///
+/// fn startup(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+///     let map_entity = commands.spawn().id();
+///     let mut map = Map::new(0u16, map_entity);
+///
+///     let mut settings = LayerSettings::new(
+///         MapSize(1024, 1024),
+///         ChunkSize(16, 16),
+///         TileSize(16.0, 16.0),
+///         TextureSize(96.0, 16.0),
+///     );
+///     settings.set_map_id(0u16);
+///     settings.set_layer_id(0u16);
+///
+///     let material = materials.add(ColorMaterial::default());
+///     let layer_entity = commands
+///         .spawn_bundle(LayerBundle {
+///             layer: Layer::new(settings),
+///             ..Default::default()
+///         })
+///         .insert(TilemapStreaming::new(2, material, |chunk_pos: ChunkPos| {
+///             (0..16).flat_map(move |x| {
+///                 (0..16).map(move |y| {
+///                     let tile_pos = TilePos(chunk_pos.0 * 16 + x, chunk_pos.1 * 16 + y);
+///                     let tile = Tile {
+///                         texture_index: ((x + y) % 6) as u16,
+///                         ..Default::default()
+///                     };
+///                     (tile_pos, tile)
+///                 })
+///             })
+///         }))
+///         .id();
+///     map.add_layer(&mut commands, 0u16, layer_entity);
+///
+///     commands
+///         .entity(map_entity)
+///         .insert(map)
+///         .insert(Transform::default())
+///         .insert(GlobalTransform::default());
+/// }
+/// ```
+pub struct TilemapStreaming {
+    /// The number of chunks kept around the chunk under the camera in every direction.
+    pub radius: u32,
+    /// The material drawing the streamed chunks.
+    pub material: Handle<ColorMaterial>,
+    generator: Box<dyn Fn(ChunkPos) -> Vec<(TilePos, Tile)> + Send + Sync>,
+}
+
+impl TilemapStreaming {
+    /// Creates a streaming component. The generator is called with the position of every chunk
+    /// that is spawned and returns the tiles of the chunk, tiles outside of the chunk are
+    /// ignored.
+    pub fn new<F, I>(radius: u32, material: Handle<ColorMaterial>, generator: F) -> Self
+    where
+        F: Fn(ChunkPos) -> I + Send + Sync + 'static,
+        I: IntoIterator<Item = (TilePos, Tile)>,
+    {
+        Self {
+            radius,
+            material,
+            generator: Box::new(move |chunk_pos| generator(chunk_pos).into_iter().collect()),
+        }
+    }
+}
+
+// Spawns the chunks of streamed layers which came within range of the camera and despawns the
+// chunks which left it.
+pub(crate) fn update_streamed_chunks(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    camera: Query<(&Camera, &Transform)>,
+    mut layers: Query<(Entity, &mut Layer, &TilemapStreaming, &GlobalTransform)>,
+    chunks: Query<&Chunk>,
+) {
+    let camera_pos = match camera
+        .iter()
+        .find(|(camera, _)| camera.name.as_deref() == Some(CAMERA_2D))
+    {
+        Some((_, transform)) => transform.translation,
+        None => return,
+    };
+
+    for (layer_entity, mut layer, streaming, transform) in layers.iter_mut() {
+        let settings = layer.settings;
+        if settings.mesh_type != TilemapMeshType::Square {
+            continue;
+        }
+
+        // Find the chunks in range in the layer's space.
+        let local_pos =
+            transform.rotation.inverse() * (camera_pos - transform.translation) / transform.scale;
+        let chunk_pixel_size = Vec2::from(settings.chunk_size) * settings.grid_size;
+        let center =
+            ((local_pos.truncate() + settings.get_anchor_offset()) / chunk_pixel_size).floor();
+        let radius = streaming.radius as f32;
+        let min = (center - Vec2::splat(radius)).max(Vec2::ZERO);
+        let max = (center + Vec2::splat(radius))
+            .min(Vec2::new(settings.map_size.0 as f32, settings.map_size.1 as f32) - Vec2::ONE);
+        let in_range = |chunk_pos: ChunkPos| {
+            chunk_pos.0 as f32 >= min.x
+                && chunk_pos.1 as f32 >= min.y
+                && chunk_pos.0 as f32 <= max.x
+                && chunk_pos.1 as f32 <= max.y
+        };
+
+        let out_of_range: Vec<(ChunkPos, Entity)> = layer
+            .iter_chunks()
+            .filter(|(chunk_pos, _)| !in_range(*chunk_pos))
+            .collect();
+        for (chunk_pos, chunk_entity) in out_of_range {
+            if let Ok(chunk) = chunks.get(chunk_entity) {
+                for tile_entity in chunk.tiles.iter().flatten() {
+                    commands.entity(*tile_entity).despawn_recursive();
+                }
+            }
+            commands.entity(chunk_entity).despawn_recursive();
+            layer.chunks[morton_index(chunk_pos)] = None;
+        }
+
+        if min.x > max.x || min.y > max.y {
+            continue;
+        }
+        for y in min.y as u32..=max.y as u32 {
+            for x in min.x as u32..=max.x as u32 {
+                let chunk_pos = ChunkPos(x, y);
+                if layer.get_chunk(chunk_pos).is_some() {
+                    continue;
+                }
+                let chunk_entity = spawn_chunk(
+                    &mut commands,
+                    &mut meshes,
+                    layer_entity,
+                    &layer,
+                    streaming,
+                    chunk_pos,
+                );
+                layer.chunks[morton_index(chunk_pos)] = Some(chunk_entity);
+            }
+        }
+    }
+}
+
+fn spawn_chunk(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    layer_entity: Entity,
+    layer: &Layer,
+    streaming: &TilemapStreaming,
+    chunk_pos: ChunkPos,
+) -> Entity {
+    let settings = layer.settings;
+    let mut chunk_entity = None;
+    commands
+        .entity(layer_entity)
+        .with_children(|child_builder| {
+            chunk_entity = Some(child_builder.spawn().id());
+        });
+    let chunk_entity = chunk_entity.unwrap();
+
+    let mut mesh = Mesh::new(settings.topology.primitive_topology());
+    mesh.set_attribute("Vertex_Position", VertexAttributeValues::Float3(vec![]));
+    mesh.set_attribute("Vertex_Texture", VertexAttributeValues::Int4(vec![]));
+    mesh.set_indices(Some(Indices::U32(vec![])));
+    let mesh_handle = meshes.add(mesh);
+    let mut chunk = Chunk::new(layer_entity, settings, chunk_pos, mesh_handle.clone());
+
+    let origin = TilePos(
+        chunk_pos.0 * settings.chunk_size.0,
+        chunk_pos.1 * settings.chunk_size.1,
+    );
+    for (tile_pos, tile) in (streaming.generator)(chunk_pos) {
+        if tile_pos.0 < origin.0
+            || tile_pos.1 < origin.1
+            || tile_pos.0 >= origin.0 + settings.chunk_size.0
+            || tile_pos.1 >= origin.1 + settings.chunk_size.1
+        {
+            continue;
+        }
+        let tile_entity = commands
+            .spawn()
+            .insert_bundle(TileBundle {
+                tile,
+                position: tile_pos,
+                parent: TileParent {
+                    chunk: chunk_entity,
+                    layer_id: settings.layer_id,
+                    map_id: settings.map_id,
+                },
+            })
+            .id();
+        let local_pos = TilePos(tile_pos.0 - origin.0, tile_pos.1 - origin.1);
+        chunk.tiles[morton_index(local_pos)] = Some(tile_entity);
+    }
+
+    let tilemap_data = TilemapData::from(&chunk);
+    commands.entity(chunk_entity).insert_bundle(ChunkBundle {
+        chunk,
+        mesh: mesh_handle,
+        material: streaming.material.clone(),
+        transform: LayerBuilder::<TileBundle>::get_chunk_coords(chunk_pos, &settings),
+        tilemap_data,
+        render_pipeline: get_render_pipelines(
+            settings.mesh_type,
+            settings.blend_mode,
+            settings.texture_mode,
+        ),
+        ..Default::default()
+    });
+
+    chunk_entity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use crate::test_util::{layer_settings, new_world};
+    use bevy::ecs::schedule::{Schedule, SystemStage};
+
+    fn run_streaming(world: &mut World) {
+        let mut schedule = Schedule::default();
+        schedule.add_stage(
+            "stream",
+            SystemStage::single_threaded().with_system(update_streamed_chunks.system()),
+        );
+        schedule.run(world);
+    }
+
+    fn spawned_chunks(world: &mut World) -> Vec<ChunkPos> {
+        let mut chunk_positions: Vec<ChunkPos> = world
+            .query::<&Chunk>()
+            .iter(world)
+            .map(|chunk| chunk.position)
+            .collect();
+        chunk_positions.sort_by_key(|chunk_pos| (chunk_pos.1, chunk_pos.0));
+        chunk_positions
+    }
+
+    #[test]
+    fn moving_the_camera_streams_chunks() {
+        let mut world = new_world();
+        let mut settings = layer_settings(MapSize(16, 16), ChunkSize(4, 4));
+        settings.set_map_id(0u16);
+        settings.set_layer_id(0u16);
+        let streaming = TilemapStreaming::new(1, Handle::default(), |chunk_pos: ChunkPos| {
+            (0..4).flat_map(move |x| {
+                (0..4).map(move |y| {
+                    let tile_pos = TilePos(chunk_pos.0 * 4 + x, chunk_pos.1 * 4 + y);
+                    (tile_pos, Tile::default())
+                })
+            })
+        });
+        let layer_entity = world
+            .spawn()
+            .insert_bundle(LayerBundle {
+                layer: Layer::new(settings),
+                ..Default::default()
+            })
+            .insert(streaming)
+            .id();
+        let camera_entity = world
+            .spawn()
+            .insert_bundle(OrthographicCameraBundle::new_2d())
+            .id();
+
+        // Chunks are 64 pixels wide, the camera starts over chunk (0, 0).
+        world
+            .get_mut::<Transform>(camera_entity)
+            .unwrap()
+            .translation = Vec3::new(10.0, 10.0, 0.0);
+        run_streaming(&mut world);
+        assert_eq!(
+            spawned_chunks(&mut world),
+            vec![
+                ChunkPos(0, 0),
+                ChunkPos(1, 0),
+                ChunkPos(0, 1),
+                ChunkPos(1, 1)
+            ]
+        );
+        assert_eq!(world.query::<&Tile>().iter(&world).count(), 4 * 16);
+        let old_chunk = world
+            .get::<Layer>(layer_entity)
+            .unwrap()
+            .get_chunk(ChunkPos(0, 0))
+            .unwrap();
+
+        // Moving the camera over chunk (5, 5) streams in the chunks around it.
+        world
+            .get_mut::<Transform>(camera_entity)
+            .unwrap()
+            .translation = Vec3::new(5.0 * 64.0 + 32.0, 5.0 * 64.0 + 32.0, 0.0);
+        run_streaming(&mut world);
+        let expected: Vec<ChunkPos> = (4..=6)
+            .flat_map(|y| (4..=6).map(move |x| ChunkPos(x, y)))
+            .collect();
+        assert_eq!(spawned_chunks(&mut world), expected);
+        assert_eq!(world.query::<&Tile>().iter(&world).count(), 9 * 16);
+        assert!(world.get_entity(old_chunk).is_none());
+
+        let layer = world.get::<Layer>(layer_entity).unwrap();
+        assert_eq!(layer.iter_chunks().count(), 9);
+        assert!(layer.get_chunk(ChunkPos(0, 0)).is_none());
+        let chunk_entity = layer.get_chunk(ChunkPos(5, 5)).unwrap();
+        let chunk = world.get::<Chunk>(chunk_entity).unwrap();
+        assert_eq!(chunk.iter_tile_entities().count(), 16);
+        let tile_entity = chunk.get_tile_entity(LocalTilePos(1, 2)).unwrap();
+        assert_eq!(world.get::<TilePos>(tile_entity), Some(&TilePos(21, 22)));
+
+        // Nothing is respawned while the camera stays in the same chunk.
+        run_streaming(&mut world);
+        assert_eq!(
+            world
+                .get::<Layer>(layer_entity)
+                .unwrap()
+                .get_chunk(ChunkPos(5, 5)),
+            Some(chunk_entity)
+        );
+    }
+}
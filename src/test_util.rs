@@ -0,0 +1,93 @@
+// Fixtures shared by the unit tests.
+use crate::prelude::*;
+use bevy::{
+    ecs::schedule::{Schedule, SystemStage},
+    prelude::*,
+    render::pipeline::PipelineDescriptor,
+};
+
+type FillLayer = Box<dyn FnOnce(&mut LayerBuilder<TileBundle>) + Send + Sync>;
+
+struct LayerFixture {
+    settings: LayerSettings,
+    material: Handle<ColorMaterial>,
+    fill: Option<FillLayer>,
+    layer_entity: Option<Entity>,
+}
+
+// An app with the assets and events the tilemap systems use, without a renderer.
+pub(crate) fn new_app() -> AppBuilder {
+    let mut app = App::build();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(bevy::asset::AssetPlugin)
+        .add_asset::<Mesh>()
+        .add_asset::<Texture>()
+        .add_asset::<ColorMaterial>()
+        .add_asset::<PipelineDescriptor>()
+        .add_event::<ChunkMeshed>();
+    app
+}
+
+pub(crate) fn into_world(mut app: AppBuilder) -> World {
+    std::mem::take(app.world_mut())
+}
+
+pub(crate) fn new_world() -> World {
+    into_world(new_app())
+}
+
+pub(crate) fn run_system(world: &mut World, system: impl System<In = (), Out = ()>) {
+    let mut schedule = Schedule::default();
+    schedule.add_stage("run", SystemStage::single_threaded().with_system(system));
+    schedule.run(world);
+}
+
+fn build_fixture_layer(
+    mut commands: Commands,
+    mut fixture: ResMut<LayerFixture>,
+    mut map_query: MapQuery,
+) {
+    let map_entity = commands.spawn().id();
+    let mut map = Map::new(0u16, map_entity);
+    let (mut layer_builder, layer_entity) =
+        LayerBuilder::<TileBundle>::new(&mut commands, fixture.settings, 0u16, 0u16, None);
+    if let Some(fill) = fixture.fill.take() {
+        fill(&mut layer_builder);
+    }
+    map_query.build_layer(&mut commands, layer_builder, fixture.material.clone());
+    map.add_layer(&mut commands, 0u16, layer_entity);
+    commands
+        .entity(map_entity)
+        .insert(map)
+        .insert(Transform::default())
+        .insert(GlobalTransform::default());
+    fixture.layer_entity = Some(layer_entity);
+}
+
+// Builds layer 0 of map 0 with the tiles set by `fill` and returns the layer entity.
+pub(crate) fn spawn_layer(
+    world: &mut World,
+    settings: LayerSettings,
+    material: Handle<ColorMaterial>,
+    fill: impl FnOnce(&mut LayerBuilder<TileBundle>) + Send + Sync + 'static,
+) -> Entity {
+    world.insert_resource(LayerFixture {
+        settings,
+        material,
+        fill: Some(Box::new(fill)),
+        layer_entity: None,
+    });
+    run_system(world, build_fixture_layer.system());
+    let fixture = world.remove_resource::<LayerFixture>().unwrap();
+    fixture.layer_entity.unwrap()
+}
+
+// A square layer with 16x16 pixel tiles.
+pub(crate) fn layer_settings(map_size: MapSize, chunk_size: ChunkSize) -> LayerSettings {
+    LayerSettings::new(
+        map_size,
+        chunk_size,
+        TileSize(16.0, 16.0),
+        TextureSize(96.0, 16.0),
+    )
+}
@@ -3,6 +3,7 @@ use bevy::prelude::*;
 
 /// A component that represents the basic tile information.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tile {
     /// The texture index in the atlas or array.
     pub texture_index: u16,
@@ -12,8 +13,21 @@ pub struct Tile {
     pub flip_y: bool,
     pub flip_d: bool, // anti
     /// Visibility, if false will still process tile events, but will not render the tile.
+    /// Toggling only this updates the tile in the chunk mesh without remeshing the chunk.
     pub visible: bool,
     pub color: Color,
+    /// How strongly the tile emits light, 0 for none.
+    /// Passed to fragment shaders as `v_Emissive` for use by lighting, see `TilemapMaterial`.
+    pub emissive: f32,
+    /// The palette used to color the tile by `TilemapPalette`, as an offset from the material's
+    /// active palette. Passed to fragment shaders as `v_Palette`.
+    pub palette: u8,
+    /// Rotates the tile counter clockwise about its center by this many radians, for scattered
+    /// decorations like rocks or debris. Unlike `set_rotation` this isn't limited to steps of
+    /// 90 degrees, but the rotated tile reaches into its neighboring cells.
+    /// Note: Only tiles of square layers are rotated, rotated tiles aren't merged by
+    /// `LayerSettings::greedy_meshing`.
+    pub angle: f32,
 }
 
 impl Default for Tile {
@@ -25,14 +39,35 @@ impl Default for Tile {
             flip_d: false, // anti diagonal for rotation
             visible: true,
             color: Color::WHITE,
+            emissive: 0.0,
+            palette: 0,
+            angle: 0.0,
         }
     }
 }
 
-impl Into<TileBundle> for Tile {
-    fn into(self) -> TileBundle {
+impl Tile {
+    /// Rotates the tile clockwise by `quarter_turns` times 90 degrees.
+    /// Rotations are stored as flips the same way Tiled stores them, so this overwrites the
+    /// flip flags. Toggling `flip_x` or `flip_y` afterwards mirrors the rotated tile, which
+    /// covers all eight orientations of a tile.
+    pub fn set_rotation(&mut self, quarter_turns: u8) {
+        let (flip_x, flip_y, flip_d) = match quarter_turns % 4 {
+            0 => (false, false, false),
+            1 => (true, false, true),
+            2 => (true, true, false),
+            _ => (false, true, true),
+        };
+        self.flip_x = flip_x;
+        self.flip_y = flip_y;
+        self.flip_d = flip_d;
+    }
+}
+
+impl From<Tile> for TileBundle {
+    fn from(tile: Tile) -> TileBundle {
         TileBundle {
-            tile: self,
+            tile,
             ..Default::default()
         }
     }
@@ -46,7 +81,7 @@ pub struct GPUAnimated {
     pub start: u32,
     /// The end frame index in the tilemap atlas/array.
     pub end: u32,
-    /// The speed the animation plays back at.
+    /// The speed the animation plays back at, in loops per second.
     pub speed: f32,
 }
 
@@ -54,8 +89,32 @@ impl GPUAnimated {
     pub fn new(start: u32, end: u32, speed: f32) -> Self {
         Self { start, end, speed }
     }
+
+    /// Creates an animation which plays the frames from `start` up to but not including `end`
+    /// at the given frames per second.
+    /// The same animation can be inserted on any number of tiles, they are all animated on the
+    /// GPU without any per frame work on the CPU.
+    pub fn from_fps(start: u32, end: u32, fps: f32) -> Self {
+        let frames = end.saturating_sub(start).max(1);
+        Self::new(start, end, fps / frames as f32)
+    }
 }
 
+/// A component with custom data for a tile, which is written to the `Vertex_Data` attribute of
+/// the tile's vertices for use in a custom vertex shader, see `TilemapMaterial::vertex_shader`.
+/// Chunks without any of these don't have the attribute.
+/// Note: Changing the data doesn't remesh the chunk, use `MapQuery::notify_chunk_for_tile`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TileVertexData(pub Vec4);
+
+/// A component which draws a tile with another material than the rest of its layer, so tiles
+/// from several textures can share a layer.
+/// The tiles of each material are meshed into a sub-mesh of their chunk, which is drawn with
+/// the material. Its texture is indexed with the layer's `texture_size` and `tile_size`.
+/// Note: Removing the component doesn't remesh the chunk, use `MapQuery::notify_chunk_for_tile`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TileMaterial(pub Handle<ColorMaterial>);
+
 /// This trait is used to allow the layer builder to access specific information inside of the bundle.
 pub trait TileBundleTrait: Bundle + Clone + Sized {
     /// Gets the tile position from inside of the bundle.
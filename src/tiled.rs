@@ -0,0 +1,443 @@
+use crate::prelude::*;
+use bevy::{
+    asset::{AssetLoader, AssetPath, BoxedFuture, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+};
+use std::{collections::HashMap, io::BufReader};
+
+/// Adds the `.tmx` asset loader and the systems which turn loaded Tiled maps into layers.
+#[derive(Default)]
+pub struct TiledMapPlugin;
+
+impl Plugin for TiledMapPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_asset::<TiledMap>()
+            .add_asset_loader(TiledLoader)
+            .add_system(process_loaded_tile_maps.system());
+    }
+}
+
+/// A map loaded from a Tiled `.tmx` file.
+#[derive(TypeUuid)]
+#[uuid = "e51081d0-6168-4881-a1c6-4249b2000d7f"]
+pub struct TiledMap {
+    pub map: ::tiled::Map,
+    /// Tileset textures keyed by the first gid of the tileset.
+    pub tilesets: HashMap<u32, Handle<Texture>>,
+}
+
+/// Spawn this bundle on a map entity to build its layers from a Tiled map.
+#[derive(Default, Bundle)]
+pub struct TiledMapBundle {
+    pub tiled_map: Handle<TiledMap>,
+    pub map: Map,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+}
+
+pub struct TiledLoader;
+
+impl AssetLoader for TiledLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let root_dir = load_context
+                .path()
+                .parent()
+                .ok_or_else(|| anyhow::anyhow!("{:?} isn't in a directory", load_context.path()))?
+                .to_path_buf();
+
+            let (mut map, external_tilesets) = parse_map(std::str::from_utf8(bytes)?)?;
+
+            // Image sources are relative to the file the tileset was defined in.
+            let mut image_dirs = HashMap::new();
+            for (first_gid, source) in external_tilesets {
+                let tileset_path = root_dir.join(source);
+                let tileset_bytes = load_context.read_asset_bytes(&tileset_path).await?;
+                let tileset =
+                    ::tiled::parse_tileset(BufReader::new(&tileset_bytes[..]), first_gid)?;
+                image_dirs.insert(first_gid, tileset_path.parent().unwrap().to_path_buf());
+                map.tilesets.push(tileset);
+            }
+            map.tilesets.sort_by_key(|tileset| tileset.first_gid);
+
+            let mut dependencies = Vec::new();
+            for tileset in &map.tilesets {
+                // TODO: support multiple tileset images?
+                if let Some(image) = tileset.images.first() {
+                    let image_dir = image_dirs.get(&tileset.first_gid).unwrap_or(&root_dir);
+                    let image_path = image_dir.join(image.source.as_str());
+                    dependencies.push((tileset.first_gid, AssetPath::new(image_path, None)));
+                }
+            }
+
+            let loaded_asset = LoadedAsset::new(TiledMap {
+                map,
+                tilesets: dependencies
+                    .iter()
+                    .map(|dep| {
+                        let texture: Handle<Texture> = load_context.get_handle(dep.1.clone());
+                        (dep.0, texture)
+                    })
+                    .collect(),
+            });
+            load_context.set_default_asset(
+                loaded_asset.with_dependencies(dependencies.iter().map(|x| x.1.clone()).collect()),
+            );
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        static EXTENSIONS: &[&str] = &["tmx"];
+        EXTENSIONS
+    }
+}
+
+/// Parses a map without its external tilesets, which are returned as `(first_gid, source)`
+/// pairs. Infinite maps aren't supported and return an error.
+fn parse_map(xml: &str) -> Result<(::tiled::Map, Vec<(u32, String)>), anyhow::Error> {
+    // The tiled crate can only resolve external tilesets from the file system, so they are
+    // stripped from the map and loaded through the asset io instead.
+    let (map_xml, external_tilesets) = split_external_tilesets(xml);
+    let map = ::tiled::parse(BufReader::new(map_xml.as_bytes()))?;
+    if map.infinite {
+        anyhow::bail!("infinite maps aren't supported");
+    }
+    Ok((map, external_tilesets))
+}
+
+/// Removes `<tileset firstgid=".." source=".."/>` references from the map xml and returns
+/// them as `(first_gid, source)` pairs.
+fn split_external_tilesets(xml: &str) -> (String, Vec<(u32, String)>) {
+    let mut map_xml = String::with_capacity(xml.len());
+    let mut external_tilesets = Vec::new();
+
+    let mut rest = xml;
+    while let Some(start) = rest.find("<tileset") {
+        let end = match rest[start..].find('>') {
+            Some(end) => start + end + 1,
+            None => break,
+        };
+        let tag = &rest[start..end];
+        let first_gid = get_attribute(tag, "firstgid").and_then(|gid| gid.parse().ok());
+        match (first_gid, get_attribute(tag, "source")) {
+            (Some(first_gid), Some(source)) => {
+                map_xml.push_str(&rest[..start]);
+                external_tilesets.push((first_gid, source.to_string()));
+            }
+            _ => map_xml.push_str(&rest[..end]),
+        }
+        rest = &rest[end..];
+    }
+    map_xml.push_str(rest);
+
+    (map_xml, external_tilesets)
+}
+
+fn get_attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let pattern = format!(" {}=\"", name);
+    let start = tag.find(&pattern)? + pattern.len();
+    let end = tag[start..].find('"')?;
+    Some(&tag[start..start + end])
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn process_loaded_tile_maps(
+    mut commands: Commands,
+    mut map_events: EventReader<AssetEvent<TiledMap>>,
+    maps: Res<Assets<TiledMap>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut query: Query<(Entity, &Handle<TiledMap>, &mut Map)>,
+    new_maps: Query<&Handle<TiledMap>, Added<Handle<TiledMap>>>,
+    layer_query: Query<&Layer>,
+    chunk_query: Query<&Chunk>,
+) {
+    let mut changed_maps = Vec::<Handle<TiledMap>>::default();
+    for event in map_events.iter() {
+        match event {
+            AssetEvent::Created { handle } => {
+                log::info!("Map added!");
+                changed_maps.push(handle.clone());
+            }
+            AssetEvent::Modified { handle } => {
+                log::info!("Map changed!");
+                changed_maps.push(handle.clone());
+            }
+            AssetEvent::Removed { handle } => {
+                log::info!("Map removed!");
+                // if mesh was modified and removed in the same update, ignore the modification
+                // events are ordered so future modification events are ok
+                changed_maps = changed_maps
+                    .into_iter()
+                    .filter(|changed_handle| changed_handle != handle)
+                    .collect();
+            }
+        }
+    }
+
+    // If we have new map entities add them to the changed_maps list.
+    for new_map_handle in new_maps.iter() {
+        changed_maps.push(new_map_handle.clone());
+    }
+
+    for changed_map in changed_maps.iter() {
+        for (_, map_handle, mut map) in query.iter_mut() {
+            // only deal with currently changed map
+            if map_handle != changed_map {
+                continue;
+            }
+            if let Some(tiled_map) = maps.get(map_handle) {
+                // Despawn all tiles/chunks/layers.
+                for (layer_id, layer_entity) in map.get_layers() {
+                    if let Ok(layer) = layer_query.get(layer_entity) {
+                        for x in 0..layer.get_layer_size_in_tiles().0 {
+                            for y in 0..layer.get_layer_size_in_tiles().1 {
+                                let tile_pos = TilePos(x, y);
+                                let chunk_pos = ChunkPos(
+                                    tile_pos.0 / layer.settings.chunk_size.0,
+                                    tile_pos.1 / layer.settings.chunk_size.1,
+                                );
+                                if let Some(chunk_entity) = layer.get_chunk(chunk_pos) {
+                                    if let Ok(chunk) = chunk_query.get(chunk_entity) {
+                                        let chunk_tile_pos = chunk.to_chunk_pos(tile_pos);
+                                        if let Some(tile) = chunk.get_tile_entity(chunk_tile_pos) {
+                                            commands.entity(tile).despawn_recursive();
+                                        }
+                                    }
+
+                                    commands.entity(chunk_entity).despawn_recursive();
+                                }
+                            }
+                        }
+                    }
+                    map.remove_layer(&mut commands, layer_id);
+                }
+
+                let tileset_count = tiled_map.map.tilesets.len() as u16;
+                for (tileset_index, tileset) in tiled_map.map.tilesets.iter().enumerate() {
+                    let texture = match tiled_map.tilesets.get(&tileset.first_gid) {
+                        Some(texture) => texture,
+                        None => continue,
+                    };
+                    let tilecount = match tileset.tilecount {
+                        Some(tilecount) => tilecount,
+                        None => {
+                            log::warn!("Skipping tileset {}, it has no tile count.", tileset.name);
+                            continue;
+                        }
+                    };
+                    let material = materials.add(ColorMaterial::texture(texture.clone()));
+
+                    // Once materials have been created/added we need to then create the layers.
+                    for layer in tiled_map.map.layers.iter() {
+                        let tiles = match &layer.tiles {
+                            ::tiled::LayerData::Finite(tiles) => tiles,
+                            ::tiled::LayerData::Infinite(_) => {
+                                log::warn!("Skipping layer {}, it is infinite.", layer.name);
+                                continue;
+                            }
+                        };
+
+                        let mut map_settings = LayerSettings::new(
+                            MapSize(
+                                (tiled_map.map.width as f32 / 64.0).ceil() as u32,
+                                (tiled_map.map.height as f32 / 64.0).ceil() as u32,
+                            ),
+                            ChunkSize(64, 64),
+                            TileSize(tileset.tile_width as f32, tileset.tile_height as f32),
+                            TextureSize(
+                                tileset.images[0].width as f32,
+                                tileset.images[0].height as f32,
+                            ),
+                        );
+                        map_settings.grid_size = Vec2::new(
+                            tiled_map.map.tile_width as f32,
+                            tiled_map.map.tile_height as f32,
+                        );
+                        map_settings.tile_spacing = Vec2::splat(tileset.spacing as f32);
+                        map_settings.tile_margin = Vec2::splat(tileset.margin as f32);
+
+                        // Each tileset needs its own material, so every tiled layer gets one
+                        // tilemap layer per tileset.
+                        let layer_id =
+                            layer.layer_index as u16 * tileset_count + tileset_index as u16;
+                        map_settings.set_layer_id(layer_id);
+
+                        map_settings.mesh_type = match tiled_map.map.orientation {
+                            ::tiled::Orientation::Hexagonal => {
+                                TilemapMeshType::Hexagon(HexType::Row) // TODO: Support hex for real.
+                            }
+                            ::tiled::Orientation::Isometric => {
+                                TilemapMeshType::Isometric(IsoType::Diamond)
+                            }
+                            ::tiled::Orientation::Staggered => {
+                                TilemapMeshType::Isometric(IsoType::Staggered)
+                            }
+                            ::tiled::Orientation::Orthogonal => TilemapMeshType::Square,
+                        };
+
+                        let color = Color::rgba(1.0, 1.0, 1.0, layer.opacity);
+                        let layer_entity = LayerBuilder::<TileBundle>::new_batch(
+                            &mut commands,
+                            map_settings,
+                            &mut meshes,
+                            material.clone(),
+                            map.id,
+                            layer_id,
+                            None,
+                            move |mut tile_pos| {
+                                if tile_pos.0 >= tiled_map.map.width
+                                    || tile_pos.1 >= tiled_map.map.height
+                                {
+                                    return None;
+                                }
+
+                                if tiled_map.map.orientation == ::tiled::Orientation::Orthogonal {
+                                    tile_pos.1 = (tiled_map.map.height - 1) as u32 - tile_pos.1;
+                                }
+
+                                let x = tile_pos.0 as usize;
+                                let y = tile_pos.1 as usize;
+
+                                let map_tile = &tiles[y][x];
+                                if map_tile.gid < tileset.first_gid
+                                    || map_tile.gid >= tileset.first_gid + tilecount
+                                {
+                                    return None;
+                                }
+
+                                let tile_id = map_tile.gid - tileset.first_gid;
+
+                                let tile = Tile {
+                                    texture_index: tile_id as u16,
+                                    flip_x: map_tile.flip_h,
+                                    flip_y: map_tile.flip_v,
+                                    flip_d: map_tile.flip_d,
+                                    visible: layer.visible,
+                                    color,
+                                    ..Default::default()
+                                };
+
+                                Some(TileBundle {
+                                    tile,
+                                    ..Default::default()
+                                })
+                            },
+                        );
+
+                        commands.entity(layer_entity).insert(Transform::from_xyz(
+                            layer.offset_x,
+                            -layer.offset_y,
+                            map_settings.get_layer_depth(),
+                        ));
+                        map.add_layer(&mut commands, layer_id, layer_entity);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{into_world, new_app};
+    use bevy::ecs::schedule::{Schedule, SystemStage};
+
+    fn load_fixture() -> TiledMap {
+        let (map, _) = parse_map(include_str!("../assets/map.tmx")).unwrap();
+        let mut tilesets = HashMap::new();
+        tilesets.insert(1, Handle::<Texture>::default());
+        TiledMap { map, tilesets }
+    }
+
+    // Builds the layers of `tiled_map` and returns the world with the map entity.
+    fn build_map(tiled_map: TiledMap) -> (World, Entity) {
+        let mut app = new_app();
+        app.add_asset::<TiledMap>();
+        let mut world = into_world(app);
+
+        let tiled_map = world
+            .get_resource_mut::<Assets<TiledMap>>()
+            .unwrap()
+            .add(tiled_map);
+        let map_entity = world.spawn().id();
+        world.entity_mut(map_entity).insert_bundle(TiledMapBundle {
+            tiled_map,
+            map: Map::new(0u16, map_entity),
+            ..Default::default()
+        });
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage(
+            "process",
+            SystemStage::single_threaded().with_system(process_loaded_tile_maps.system()),
+        );
+        schedule.run(&mut world);
+        (world, map_entity)
+    }
+
+    fn tiles(world: &mut World, layer_id: u16) -> HashMap<TilePos, Tile> {
+        world
+            .query::<(&TilePos, &Tile, &TileParent)>()
+            .iter(world)
+            .filter(|(_, _, parent)| parent.layer_id == layer_id)
+            .map(|(tile_pos, tile, _)| (*tile_pos, *tile))
+            .collect()
+    }
+
+    #[test]
+    fn builds_fixture_layers() {
+        let (mut world, map_entity) = build_map(load_fixture());
+        assert_eq!(world.get::<Map>(map_entity).unwrap().get_layers().len(), 2);
+
+        let grass = tiles(&mut world, 0);
+        assert_eq!(grass.len(), 100 * 100);
+        // The first row of the map is the top row of the layer, gids start at 1.
+        assert_eq!(grass[&TilePos(0, 99)].texture_index, 2);
+        assert_eq!(grass[&TilePos(1, 99)].texture_index, 0);
+        assert_eq!(grass[&TilePos(2, 0)].texture_index, 2);
+
+        let water = tiles(&mut world, 1);
+        assert_eq!(water.len(), 1311);
+        assert_eq!(water[&TilePos(35, 79)].texture_index, 1);
+        assert!(!water.contains_key(&TilePos(0, 99)));
+    }
+
+    #[test]
+    fn skips_tilesets_without_tile_count() {
+        let mut tiled_map = load_fixture();
+        tiled_map.map.tilesets[0].tilecount = None;
+        let (mut world, map_entity) = build_map(tiled_map);
+        assert!(world
+            .get::<Map>(map_entity)
+            .unwrap()
+            .get_layers()
+            .is_empty());
+        assert!(tiles(&mut world, 0).is_empty());
+    }
+
+    #[test]
+    fn rejects_infinite_maps() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.4" orientation="orthogonal" renderorder="right-down" width="16" height="16" tilewidth="16" tileheight="16" infinite="1">
+ <tileset firstgid="1" source="tiles.tsx"/>
+ <layer id="1" name="ground" width="16" height="16">
+  <data encoding="csv">
+   <chunk x="0" y="0" width="2" height="2">
+1,2,
+3,4
+</chunk>
+  </data>
+ </layer>
+</map>"#;
+        let error = parse_map(xml).err().unwrap();
+        assert_eq!(error.to_string(), "infinite maps aren't supported");
+    }
+}